@@ -179,6 +179,10 @@ fn main() {
   // renderer.camera.move_to(Vec3::new(5.0, 5.0, 5.0));
 
   let mut material = Material::default();
+  material.texture_map.diffuse = Some("african_head_diffuse".to_string());
+  material.texture_map.norm = Some("african_head_nm".to_string());
+  material.texture_map.bump = Some("african_head_nm_tangent".to_string());
+  material.texture_map.specular_highlight = Some("african_head_spec".to_string());
 
   // material.shader = make_gouraud_shader(Vec3::new(1.0, 1.0, 1.0));
   material.shader = make_phong_shader(Vec3::new(1.0, 1.0, 1.0));
@@ -4,309 +4,293 @@ use proc_macro::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{parse::Parse, parse_macro_input, AttributeArgs, DeriveInput, Field, Lit};
 
+/// Declare a scene object: `#[object_3d(ObjectActions, Mesh)]` on a struct holding only its own
+/// fields adds the common object fields, implements `ObjectActions` through `impl_object_actions!`
+/// and adds a `with_fields` constructor taking the own fields, the second argument is the `ObjectType`.
+/// Any further arguments name the traits the object is viewed as by the renderer, e.g.
+/// `#[object_3d(ObjectActions, Mesh, Drawable)]` makes `as_drawable` return the object.
 #[proc_macro_attribute]
 pub fn object_3d(args: TokenStream, input: TokenStream) -> TokenStream {
   let attr_ast = parse_macro_input!(args as AttributeArgs);
-  // let enum_name = match &attr_ast[0] {
-  //   syn::NestedMeta::Meta(syn::Meta::Path(p)) => {
-  //     quote! {#p }
-  //   }
-  //   _ => quote! {},
-  // };
-  let obj_trait = match &attr_ast[0] {
-    syn::NestedMeta::Meta(syn::Meta::Path(p)) => {
-      quote! {#p}
+  let ast = parse_macro_input!(input as DeriveInput);
+
+  let paths: Vec<_> = attr_ast
+    .iter()
+    .filter_map(|arg| match arg {
+      syn::NestedMeta::Meta(syn::Meta::Path(p)) => Some(p),
+      _ => None,
+    })
+    .collect();
+
+  let (obj_trait, object_type, views) = match paths.as_slice() {
+    [obj_trait, object_type, views @ ..] if paths.len() == attr_ast.len() => {
+      (obj_trait, object_type, views)
+    }
+    _ => {
+      return syn::Error::new_spanned(
+        &ast.ident,
+        "expected #[object_3d(ObjectActions, <ObjectType variant>, <viewed as trait>..)]",
+      )
+      .to_compile_error()
+      .into()
     }
-
-    _ => quote! {},
   };
 
-  let ast: DeriveInput = syn::parse(input).unwrap();
-  let struct_name = ast.ident;
-  let mut attributes = vec![];
-
-  if let syn::Data::Struct(data_struct) = ast.data {
-    for field in data_struct.fields.iter() {
-      let Field { ident, ty, vis, .. } = field;
-      // let ident_name = ident.as_ref().unwrap().to_string().repeat(2);
-      // let ident = Some(syn::Ident::new(&ident_name, ident.as_ref().unwrap().span()));
-      let attr = quote! {
-        #vis #ident:#ty,
-      };
-      attributes.push(attr)
+  let fields = match &ast.data {
+    syn::Data::Struct(syn::DataStruct {
+      fields: syn::Fields::Named(fields),
+      ..
+    }) => fields.named.iter().collect(),
+    syn::Data::Struct(syn::DataStruct {
+      fields: syn::Fields::Unit,
+      ..
+    }) => vec![],
+    _ => {
+      return syn::Error::new_spanned(
+        &ast.ident,
+        "object_3d can only be used on structs with named fields",
+      )
+      .to_compile_error()
+      .into()
     }
-  }
+  };
+
+  let DeriveInput {
+    attrs, vis, ident, ..
+  } = &ast;
+  let idents: Vec<_> = fields.iter().map(|f| &f.ident).collect();
+  let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
 
   quote! {
-    pub struct #struct_name{
-      #(#attributes)*
-      parent: std::cell::RefCell<Option<std::rc::Rc<dyn #obj_trait>>>,
+    #(#attrs)*
+    #vis struct #ident {
+      #(#fields,)*
+      parent: std::cell::RefCell<Option<std::rc::Weak<dyn #obj_trait>>>,
       children: std::cell::RefCell<Vec<std::rc::Rc<dyn #obj_trait>>>,
       matrix: std::cell::RefCell<crate::math::Mat4>,
       global_matrix: std::cell::RefCell<crate::math::Mat4>,
       position: std::cell::RefCell<crate::math::Vec3>,
       rotation: std::cell::RefCell<crate::math::Rotation>,
       scale: std::cell::RefCell<crate::math::Vec3>,
-      visible: bool,
-      cast_shadow: bool,
-      receive_shadow: bool,
+      layers: std::cell::RefCell<crate::core::layer::Layers>,
+      cast_shadow: std::cell::RefCell<bool>,
+      receive_shadow: std::cell::RefCell<bool>,
+      visible: std::cell::RefCell<bool>,
+      matrix_world_auto_update: std::cell::RefCell<bool>,
+      global_matrix_dirty: std::cell::RefCell<bool>,
       user_data: std::collections::HashMap<String, Box<dyn std::any::Any>>,
-      is_camera: bool,
-      is_light: bool,
-      _self_ref: std::rc::Weak<dyn #obj_trait>,
+      name: std::cell::RefCell<String>,
+      events: crate::core::event_dispatcher::EventDispatcher,
+      before_render: std::cell::RefCell<Option<std::rc::Rc<crate::core::object_3d::RenderCallback>>>,
+      after_render: std::cell::RefCell<Option<std::rc::Rc<crate::core::object_3d::RenderCallback>>>,
+      object_type: crate::core::object_3d::ObjectType,
+      _self_ref: std::cell::OnceCell<std::rc::Weak<dyn #obj_trait>>,
       _uuid: String,
     }
 
-    impl #obj_trait for #struct_name {
-     fn parent(&self) -> Option<std::rc::Rc<dyn #obj_trait>> {
-        if let Some(p) = self.parent.borrow().as_ref() {
-          Some(p.clone())
-        } else {
-          None
-        }
-      }
+    crate::core::object_3d::impl_object_actions!(#ident #(, #views)*);
 
-      fn set_parent(&self, parent: std::rc::Rc<dyn #obj_trait>) {
-        let mut p = self.parent.borrow_mut();
-        *p = Some(parent);
+    impl #ident {
+      /// the common fields are defaulted and `_self_ref` points at the returned object
+      fn with_fields(#(#idents: #types),*) -> std::rc::Rc<Self> {
+        crate::core::object_3d::with_default_fields!(#object_type; #(#idents),*)
       }
+    }
 
-      fn remove_from_parent(&self) {
-        let mut p = self.parent.borrow_mut();
-
-        if let Some(parent) = *p {
-          parent.remove(&self._uuid);
-        }
-
-        *p = None;
+    impl crate::core::object_3d::CloneFields for #ident {
+      fn clone_fields(&self) -> std::rc::Rc<Self> {
+        Self::with_fields(#(std::clone::Clone::clone(&self.#idents)),*)
       }
+    }
+  }
+  .into()
+}
 
-      fn remove(&self, uuid: &str) {
-        let mut children = self.children.borrow_mut();
-
-        if let Some(index) = children.iter().position(|x| (*x).uuid() == uuid) {
-          children.remove(index);
-        }
-      }
+/// Generate `three::core::varying::Varyings` for a struct of varyings.
+/// The generated `lerp` interpolates every field through `Interpolate`, which requires
+/// `Copy` since the fields are read out of `&[Self; 3]`. Fields marked `#[flat]` are
+/// cloned from the first vertex instead, so they only need `Clone`.
+#[proc_macro_derive(Varyings, attributes(flat))]
+pub fn derive_varyings(input: TokenStream) -> TokenStream {
+  let ast = parse_macro_input!(input as DeriveInput);
+  let struct_name = &ast.ident;
+  let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+  let fields = match &ast.data {
+    syn::Data::Struct(syn::DataStruct {
+      fields: syn::Fields::Named(fields),
+      ..
+    }) => &fields.named,
+    _ => {
+      return syn::Error::new_spanned(
+        &ast.ident,
+        "Varyings can only be derived for structs with named fields",
+      )
+      .to_compile_error()
+      .into()
+    }
+  };
 
-      fn add(&self, child: std::rc::Rc<dyn #obj_trait>) {
-        let mut children = self.children.borrow_mut();
+  let interpolated = fields.iter().map(|field| {
+    let Field {
+      ident, ty, attrs, ..
+    } = field;
+    let flat = attrs.iter().any(|attr| attr.path.is_ident("flat"));
 
-        if let Some(me) = self._self_ref.upgrade() {
-          child.remove_from_parent();
-          child.set_parent(me.clone());
-          children.push(child.clone());
-        }
+    if flat {
+      quote! {
+        #ident: std::clone::Clone::clone(&vertices[0].#ident),
       }
-
-      fn clear(&self) {
-        let mut children = self.children.borrow_mut();
-
-        for child in *children {
-          child.remove_from_parent();
-        }
-
-        *children = vec![];
+    } else {
+      quote! {
+        #ident: <#ty as ::three::core::varying::Interpolate>::interpolate(
+          [vertices[0].#ident, vertices[1].#ident, vertices[2].#ident],
+          weights,
+        ),
       }
+    }
+  });
 
-      fn attach(&self, child: Box<dyn #obj_trait>) {
-        self.update_global_matrix();
-
-        let mut res = self
-          .global_matrix
-          .borrow()
-          .inverse()
-          .expect("expected a invertable global matrix");
-
-        if let Some(parent) = child.parent() {
-          parent.update_global_matrix();
-          res = res * parent.global_matrix();
-        }
-
-        child.apply_matrix(res);
-      }
-
-      fn look_at(&self, target: crate::math::Vec3) {
-        // let back = (self.position - target).normalize();
-        self.update_global_matrix();
-
-        let position = crate::math::extract_position(*self.global_matrix.borrow());
-
-        let (eye, target) = if self.is_camera || self.is_light {
-          // camera default looking back along -z;
-          (position, target)
-        } else {
-          (target, position)
-        };
-
-        let orthogonal_basis =
-          crate::math::Mat3::get_orthogonal_basis(eye, target, *crate::math::Vec3::y_axis());
-
-        let mut rotate_mat = crate::math::Mat4::identity();
-
-        for i in 0..2 {
-          let col = crate::math::Vec4::from_vec3(&orthogonal_basis.get_col(i), 0.0);
-          rotate_mat.set_col(i, col);
-        }
-
-        let mut q: crate::math::Quaternion = rotate_mat.into();
-
-        if let Some(parent) = *self.parent.borrow() {
-          let (_, r, _) = crate::math::decompose(parent.global_matrix());
-
-          let q_parent: crate::math::Quaternion = r.into();
-
-          q = q_parent.inverse() * q;
-        }
-
-        {
-          let mut rotate_ref = self.rotation.borrow_mut();
-          rotate_ref.set_quaternion(q);
+  quote! {
+    impl #impl_generics ::three::core::varying::Varyings for #struct_name #ty_generics #where_clause {
+      fn lerp(vertices: &[Self; 3], weights: [f32; 3]) -> Self {
+        Self {
+          #(#interpolated)*
         }
       }
+    }
+  }
+  .into()
+}
 
-      fn matrix(&self) -> crate::math::Mat4 {
-        *self.matrix.borrow()
-      }
-
-      fn global_matrix(&self) -> crate::math::Mat4 {
-        *self.global_matrix.borrow()
-      }
-
-      fn update_global_matrix(&self) {
-        self.update_matrix();
+/// Generate `three::material::material::ConvertUniform` for a material parameter struct.
+/// Every field goes through `UnifromTypeEnum::from` under its own name,
+/// `#[uniform(rename = "name")]` picks another key and `#[uniform(skip)]` leaves it out.
+/// `Rc<Texture>` fields are bound with `Uniform::bind_texture`, `Option<Rc<Texture>>`
+/// ones only when they hold a texture.
+#[proc_macro_derive(Uniforms, attributes(uniform))]
+pub fn derive_uniforms(input: TokenStream) -> TokenStream {
+  let ast = parse_macro_input!(input as DeriveInput);
+  let struct_name = &ast.ident;
+  let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+  let fields = match &ast.data {
+    syn::Data::Struct(syn::DataStruct {
+      fields: syn::Fields::Named(fields),
+      ..
+    }) => &fields.named,
+    _ => {
+      return syn::Error::new_spanned(
+        &ast.ident,
+        "Uniforms can only be derived for structs with named fields",
+      )
+      .to_compile_error()
+      .into()
+    }
+  };
 
-        if let Some(parent) = self.parent.borrow().as_ref() {
-          parent.update_global_matrix();
-          let parent_global = parent.global_matrix();
-          let mut global_matrix = self.global_matrix.borrow_mut();
-          *global_matrix = parent_global * *global_matrix;
+  let mut inserts = vec![];
+  for field in fields {
+    let ident = field.ident.as_ref().unwrap();
+    let mut key = ident.to_string();
+    let mut skip = false;
+
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("uniform")) {
+      let nested = match attr.parse_meta() {
+        Ok(syn::Meta::List(list)) => list.nested,
+        Ok(meta) => {
+          return syn::Error::new_spanned(meta, "expected #[uniform(...)]")
+            .to_compile_error()
+            .into()
         }
+        Err(err) => return err.to_compile_error().into(),
+      };
 
-        for child in std::ops::Deref::deref(&self.children.borrow()) {
-          child.update_global_matrix();
+      for meta in nested {
+        match meta {
+          syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => skip = true,
+          syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+            path,
+            lit: Lit::Str(name),
+            ..
+          }))
+            if path.is_ident("rename") =>
+          {
+            key = name.value()
+          }
+          meta => {
+            return syn::Error::new_spanned(meta, "expected `skip` or `rename = \"...\"`")
+              .to_compile_error()
+              .into()
+          }
         }
       }
+    }
 
-      fn update_matrix(&self) {
-        let next_matrix = self.compose();
-        let mut matrix = self.matrix.borrow_mut();
-        *matrix = next_matrix;
-      }
-
-      fn compose(&self) -> crate::math::Mat4 {
-        let translate_matrix = crate::math::apply_translate(&self.position.borrow());
-        let rotate_matrix = (*self.rotation.borrow()).quaternion.make_rotate_matrix();
-        let scale_matrix = crate::math::apply_scale(&self.scale.borrow());
-
-        translate_matrix * rotate_matrix * scale_matrix
-      }
-      /// refer to http://facweb.cs.depaul.edu/andre/gam374/extractingTRS.pdf
-      fn decompose(&self) {
-        let mat = *self.matrix.borrow();
-        let (position, rotate_matrix, scale) = crate::math::decompose(mat);
-
-        {
-          self
-            .rotation
-            .borrow_mut()
-            .update_quaternion_from_matrix(rotate_matrix);
-        }
-
-        {
-          let mut scale_ref = self.scale.borrow_mut();
-          *scale_ref = scale;
-        }
-        {
-          let mut position_ref = self.position.borrow_mut();
-          *position_ref = position;
-        }
-      }
+    if skip {
+      continue;
+    }
 
-      fn apply_matrix(&self, matrix: crate::math::Mat4) {
-        self.update_matrix();
-        let mut next_matrix = crate::math::Mat4::zeros();
-        {
-          next_matrix = matrix * *self.matrix.borrow();
-        }
-        {
-          let mut matrix_ref = self.matrix.borrow_mut();
-          *matrix_ref = next_matrix;
+    let optional_texture = generic_argument(&field.ty, "Option").is_some_and(is_texture);
+    if is_texture(&field.ty) {
+      inserts.push(quote! {
+        res.bind_texture(#key, std::clone::Clone::clone(&self.#ident));
+      });
+    } else if optional_texture {
+      inserts.push(quote! {
+        if let Some(texture) = &self.#ident {
+          res.bind_texture(#key, std::clone::Clone::clone(texture));
         }
-        self.decompose();
-      }
-
-      fn apply_quaternion(&self, q: crate::math::Quaternion) {
-        let next_q = (*self.rotation.borrow()).quaternion * q;
-        let mut rotation_ref = self.rotation.borrow_mut();
-        rotation_ref.set_quaternion(next_q);
-      }
-
-      fn rotate_on_world_axis(&self, axis: crate::math::Vec3, angle: f32) {
-        let mut rotate = self.rotation.borrow_mut();
-        // apply the rotation first(before any rotation occurs)
-        // means the rotate axis is defined in the world coordinate
-        rotate.quaternion_rotate(axis, angle, true)
-      }
-
-      fn rotate_on_axis(&self, axis: crate::math::Vec3, angle: f32) {
-        let mut rotate = self.rotation.borrow_mut();
-        rotate.quaternion_rotate(axis, angle, false)
-      }
-
-      fn rotate_x(&self, angle: f32) {
-        self.rotate_on_axis(*crate::math::Vec3::x_axis(), angle);
-      }
-
-      fn rotate_y(&self, angle: f32) {
-        self.rotate_on_axis(*crate::math::Vec3::y_axis(), angle);
-      }
-
-      fn rotate_z(&self, angle: f32) {
-        self.rotate_on_axis(*crate::math::Vec3::z_axis(), angle);
-      }
-
-      fn translate_on_axis(&self, axis: crate::math::Vec3, distance: f32) {
-        let q = self.rotation.borrow().quaternion;
-        let crate::math::Vec3 {
-          x: vx,
-          y: vy,
-          z: vz,
-        } = axis;
-      }
-
-      fn translate_x(&self, distance: f32) {}
-      fn translate_y(&self, distance: f32) {}
-      fn translate_z(&self, distance: f32) {}
-
-      fn global_scale(&self) -> crate::math::Vec3 {
-        self.update_global_matrix();
-        let mat = self.global_matrix();
-        let (_, _, scale) = crate::math::decompose(mat);
-        scale
-      }
-
-      fn global_position(&self) -> crate::math::Vec3 {
-        self.update_global_matrix();
-        let mat = self.global_matrix();
-        let (position, _, _) = crate::math::decompose(mat);
-        position
-      }
-
-      fn global_rotation(&self) -> crate::math::Rotation {
-        self.update_global_matrix();
-        let mat = self.global_matrix();
-        let (_, rotation, _) = crate::math::decompose(mat);
+      });
+    } else {
+      inserts.push(quote! {
+        res.insert(
+          #key.to_string(),
+          ::three::core::unifrom::UnifromTypeEnum::from(self.#ident),
+        );
+      });
+    }
+  }
 
-        rotation.into()
+  quote! {
+    impl #impl_generics ::three::material::material::ConvertUniform for #struct_name #ty_generics #where_clause {
+      fn to_uniform(&self) -> ::three::core::unifrom::Uniform {
+        let mut res = ::three::core::unifrom::Uniform::default();
+        #(#inserts)*
+        res
       }
+    }
+  }
+  .into()
+}
 
-      fn uuid(&self) -> &str {
-        &self._uuid
-      }
+/// the single type argument of `ty` when its last path segment is `wrapper`, like `T` of `Rc<T>`
+fn generic_argument<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+  let segment = match ty {
+    syn::Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+    _ => return None,
+  };
+  if segment.ident != wrapper {
+    return None;
+  }
 
-    }
+  match &segment.arguments {
+    syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+      syn::GenericArgument::Type(ty) => Some(ty),
+      _ => None,
+    },
+    _ => None,
+  }
+}
 
+/// `Rc<Texture>`, however the two are spelled out
+fn is_texture(ty: &syn::Type) -> bool {
+  match generic_argument(ty, "Rc") {
+    Some(syn::Type::Path(path)) => path
+      .path
+      .segments
+      .last()
+      .is_some_and(|segment| segment.ident == "Texture"),
+    _ => false,
   }
-  .into()
 }
@@ -4,6 +4,7 @@ use crate::cohen_sutherland;
 // use fltk::browser::BrowserScrollbar;
 use crate::image::ColorAttachment;
 use crate::math::{self, Vec2};
+pub use tinyrenderer::bresenham_line::{wide_line, LineCap, LineJoin, LinePixel, LineStyle, Wu};
 
 //Bresenham
 pub fn draw_line(
@@ -51,6 +51,15 @@ impl Frustum {
     self.near
   }
 
+  /// the `w` of clip space positions on the near and on the far plane
+  pub fn clip_w_range(&self) -> (f32, f32) {
+    if cfg!(feature = "cpu") {
+      (1.0, self.far / self.near)
+    } else {
+      (self.near, self.far)
+    }
+  }
+
   pub fn contains(&self, pt: &Vec3) -> bool {
     let half_width = self.near * self.fov.tan();
     let half_height = half_width / self.aspect;
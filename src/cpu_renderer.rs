@@ -7,10 +7,11 @@ use crate::{
   image::{ColorAttachment, DepthAttachment},
   math::{Mat4, Vec2, /* Vec3, */ Vec4},
   renderer::*,
-  scanline::{self, near_plane_clip},
+  scanline,
   shader::{self, attributes_foreach, Shader, Uniforms, Vertex},
   texture::{Texture, TextureStore},
 };
+use tinyrenderer::renderer::clipping;
 
 // #[derive(RendererCommon)]
 #[renderer]
@@ -21,25 +22,7 @@ impl RendererDraw for Renderer {
     for i in 0..vertices.len() / 3_usize {
       let index = (i * 3) as usize;
       let vertices = [vertices[index], vertices[index + 1], vertices[index + 2]];
-      match self.rasterize_triangle(model, vertices, texture_store) {
-        RasterizeResult::Ok | RasterizeResult::Discard => {}
-        RasterizeResult::GenerateNewFace => {
-          for i in 0..self.cliped_triangles.len() / 3 {
-            let vs = [
-              self.cliped_triangles[i * 3],
-              self.cliped_triangles[i * 3 + 1],
-              self.cliped_triangles[i * 3 + 2],
-            ];
-            match self.rasterize_triangle(model, vs, texture_store) {
-              RasterizeResult::Ok => {}
-              RasterizeResult::Discard | RasterizeResult::GenerateNewFace => {
-                panic!("discard or generate new face for clipped face")
-              }
-            }
-          }
-          self.cliped_triangles.clear();
-        }
-      }
+      self.rasterize_triangle(model, vertices, texture_store);
     }
   }
 }
@@ -86,26 +69,25 @@ impl Renderer {
     }
 
     // frustum clip
-    if vertices
-      .iter()
-      .all(|v| !frustum.contains(&v.truncated_to_vec3()))
-    {
+    let (near_w, far_w) = frustum.clip_w_range();
+    let polygon = clipping::clip_triangle(&clip_positions(&vertices), near_w, far_w);
+    let triangles = clipped_triangles(&vertices, &polygon);
+    if triangles.is_empty() {
       return RasterizeResult::Discard;
     }
 
-    // near plane clip
-    // if vertices
-    //   .iter()
-    //   .any(|v| v.position.z > self.camera.get_frustum().near())
-    // {
-    //   let (face1, face2) = near_plane_clip(&vertices, self.camera.get_frustum().near());
-
-    //   self.cliped_triangles.extend(face1.iter());
-    //   if let Some(face) = face2 {
-    //     self.cliped_triangles.extend(face.iter());
-    //   }
-    //   return RasterizeResult::GenerateNewFace;
-    // }
+    for vertices in triangles {
+      self.rasterize_clipped_triangle(vertices, texture_store);
+    }
+    RasterizeResult::Ok
+  }
+
+  fn rasterize_clipped_triangle(
+    &mut self,
+    mut vertices: [Vertex; 3],
+    texture_store: &TextureStore,
+  ) {
+    let frustum = self.camera.get_frustum();
 
     // restore z from w (original z in 3D)
     for v in &mut vertices {
@@ -128,8 +110,10 @@ impl Renderer {
     }
 
     if self.wireframe_mode {
+      let line_style = *self.get_line_style();
       rasterize_wireframe(
         &vertices,
+        &line_style,
         &self.shader.fragment_shading,
         &self.uniforms,
         texture_store,
@@ -147,7 +131,6 @@ impl Renderer {
         self.draw_trapezoid(trap, texture_store);
       }
     }
-    RasterizeResult::Ok
 
     // let length = vertices.len();
     // for i in 0..length {
@@ -8,6 +8,7 @@ use crate::{
   shader::{vertex_rhw_init, Attributes, Shader, Uniforms, Vertex},
   texture::TextureStore,
 };
+use tinyrenderer::renderer::clipping;
 
 #[rustfmt::skip]
 /**
@@ -65,10 +66,12 @@ impl RendererDraw for Renderer {
     // texture: Option<&crate::texture::Texture>,
     texture_store: &TextureStore,
   ) {
+    let frustum = self.camera.get_frustum();
+    let (near_w, far_w) = frustum.clip_w_range();
+    let mut triangles = vec![];
     for i in 0..vertices.len() / 3_usize {
       let index = (i * 3) as usize;
       let mut vertices = [vertices[index], vertices[index + 1], vertices[index + 2]];
-      let frustum = self.camera.get_frustum();
       for v in &mut vertices {
         *v = self
           .shader
@@ -97,6 +100,12 @@ impl RendererDraw for Renderer {
         continue;
       }
 
+      let polygon = clipping::clip_triangle(&clip_positions(&vertices), near_w, far_w);
+      triangles.extend(clipped_triangles(&vertices, &polygon));
+    }
+
+    // everything past here sees only the parts inside the frustum
+    for mut vertices in triangles {
       for v in &mut vertices {
         v.position.z = -v.position.w;
       }
@@ -163,8 +172,10 @@ impl RendererDraw for Renderer {
         .min(self.color.height() as f32 - 1.0);
 
       if self.wireframe_mode {
+        let line_style = *self.get_line_style();
         rasterize_wireframe(
           &vertices,
+          &line_style,
           &self.shader.fragment_shading,
           &self.uniforms,
           texture_store,
@@ -47,6 +47,16 @@ impl PureElemImage<u8> {
     self.data[(x + y * self.w) as usize * 3 + 1] = (color.y * 255.0) as u8;
     self.data[(x + y * self.w) as usize * 3 + 2] = (color.z * 255.0) as u8;
   }
+
+  pub fn get(&self, x: u32, y: u32) -> math::Vec4 {
+    let p = (x + y * self.w) as usize * 3;
+    math::Vec4::new(
+      self.data[p] as f32 / 255.0,
+      self.data[p + 1] as f32 / 255.0,
+      self.data[p + 2] as f32 / 255.0,
+      1.0,
+    )
+  }
 }
 
 impl PureElemImage<f32> {
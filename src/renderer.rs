@@ -1,15 +1,18 @@
 use crate::{
-  bresenham_line::Bresenham,
+  bresenham_line::{wide_line, Bresenham, LinePixel, LineStyle, Wu},
   camera::Camera,
   image::{ColorAttachment, DepthAttachment},
   line::Line,
   math::{Mat4, Vec2, Vec3, Vec4},
   shader::{
-    attributes_foreach, interp_attributes, vertex_rhw_init, FragmentShading, Shader, Uniforms,
-    Vertex,
+    attributes_foreach, interp_attributes, lerp_vertex, mix_vertex, vertex_rhw_init,
+    FragmentShading, Shader, Uniforms, Vertex,
   },
   texture::{Texture, TextureStore},
 };
+use tinyrenderer::renderer::clipping::ClipVertex;
+
+pub use tinyrenderer::renderer::renderer::{FaceCull, FrontFace};
 
 pub struct Viewport {
   pub x: i32,
@@ -29,6 +32,8 @@ pub trait RendererDerive {
   fn enable_wireframe(&mut self);
   fn disable_wireframe(&mut self);
   fn toggle_wireframe(&mut self);
+  fn set_line_style(&mut self, style: LineStyle);
+  fn get_line_style(&self) -> &LineStyle;
   fn set_front_face(&mut self, face: FrontFace);
   fn get_face_cull(&self) -> FaceCull;
   fn set_face_cull(&mut self, face: FaceCull);
@@ -55,25 +60,6 @@ pub fn texture_sample(texture: &Texture, textcoord: &Vec2) -> Vec4 {
   texture.get_pixel(x, y)
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum FaceCull {
-  Front,
-  Back,
-  None,
-}
-
-#[derive(Clone, Copy, Debug)]
-pub enum FrontFace {
-  /**
-   * clockwise
-   */
-  CW,
-  /**
-   * counter clockwise
-   */
-  CCW,
-}
-
 pub(crate) fn should_cull(
   positions: &[Vec3; 3],
   view_direction: &Vec3,
@@ -93,21 +79,60 @@ pub(crate) fn should_cull(
   }
 }
 
+/// The triangle `tinyrenderer::renderer::clipping::clip_triangle` works on,
+/// the clip space positions of `vertices`.
+pub(crate) fn clip_positions(vertices: &[Vertex; 3]) -> [tinyrenderer::math::Vec4; 3] {
+  vertices.map(|v| {
+    let p = v.position;
+    tinyrenderer::math::Vec4::new(p.x, p.y, p.z, p.w)
+  })
+}
+
+/// Split the polygon left by the clipper into a fan of triangles, the corners are
+/// mixed out of `vertices` with their weights. None when it was all outside.
+pub(crate) fn clipped_triangles(
+  vertices: &[Vertex; 3],
+  polygon: &[ClipVertex],
+) -> Vec<[Vertex; 3]> {
+  let corners: Vec<_> = polygon
+    .iter()
+    .map(|corner| mix_vertex(vertices, corner.weights))
+    .collect();
+
+  (1..corners.len().saturating_sub(1))
+    .map(|i| [corners[0], corners[i], corners[i + 1]])
+    .collect()
+}
+
 pub enum RasterizeResult {
   Ok,
   Discard,
-  GenerateNewFace,
 }
 
+/// Draw the edges of the screen space triangle. One pixel wide aliased lines walk
+/// the edges with Bresenham, anti aliased ones with Wu, wider ones are covered with
+/// their joins. Partly covered pixels are mixed into `color` and keep their depth.
 pub(crate) fn rasterize_wireframe(
   vertices: &[Vertex; 3],
-  // line: Line,
+  style: &LineStyle,
   fragment_shader: &FragmentShading,
   uniforms: &Uniforms,
   texture_store: &TextureStore,
   color: &mut ColorAttachment,
   depth: &mut DepthAttachment,
 ) {
+  if style.width > 1.0 || style.anti_alias {
+    return rasterize_smooth_wireframe(
+      vertices,
+      style,
+      fragment_shader,
+      uniforms,
+      texture_store,
+      color,
+      depth,
+    );
+  }
+
   // 0-1, 1-2, 2-0
   for i in 0..3 {
     let mut v1 = vertices[i];
@@ -152,3 +177,199 @@ pub(crate) fn rasterize_wireframe(
     }
   }
 }
+
+fn rasterize_smooth_wireframe(
+  vertices: &[Vertex; 3],
+  style: &LineStyle,
+  fragment_shader: &FragmentShading,
+  uniforms: &Uniforms,
+  texture_store: &TextureStore,
+  color: &mut ColorAttachment,
+  depth: &mut DepthAttachment,
+) {
+  let mut vertices = *vertices;
+  for v in &mut vertices {
+    vertex_rhw_init(v);
+  }
+
+  // the line pixels come from tinyrenderer, in its own vectors
+  let points = vertices.map(|v| tinyrenderer::math::Vec2::new(v.position.x, v.position.y));
+  let min = tinyrenderer::math::Vec2::zero();
+  let max = tinyrenderer::math::Vec2::new(color.width() as f32 - 1.0, color.height() as f32 - 1.0);
+  let pixels: Vec<LinePixel> = if style.width > 1.0 {
+    wide_line(&points, true, style, min, max)
+  } else {
+    (0..3)
+      .flat_map(|i| {
+        Wu::new(points[i], points[(i + 1) % 3], min, max).map(move |pixel| LinePixel {
+          segment: i,
+          ..pixel
+        })
+      })
+      .collect()
+  };
+
+  for pixel in pixels {
+    let start = &vertices[pixel.segment];
+    let end = &vertices[(pixel.segment + 1) % 3];
+    let vertex = lerp_vertex(start, end, pixel.t);
+    let z = 1.0 / vertex.position.z;
+
+    let (x, y) = (pixel.x as u32, pixel.y as u32);
+    if depth.get(x, y) <= z {
+      let mut attr = vertex.attributes;
+      attributes_foreach(&mut attr, |a| a * z);
+
+      let fragment = fragment_shader(&attr, uniforms, texture_store);
+      let coverage = pixel.coverage.min(1.0);
+      color.set(
+        x,
+        y,
+        &(fragment * coverage + color.get(x, y) * (1.0 - coverage)),
+      );
+      if coverage >= 1.0 {
+        depth.set(x, y, z);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tinyrenderer::renderer::clipping;
+
+  use super::*;
+  use crate::shader::Attributes;
+
+  /// `w` doubles as the first float attribute, to follow how it is mixed
+  fn vertex(x: f32, y: f32, w: f32) -> Vertex {
+    let mut attributes = Attributes::default();
+    attributes.set_float(0, w);
+    Vertex {
+      position: Vec4::new(x * w, y * w, 0.0, w),
+      attributes,
+    }
+  }
+
+  /// what the cpu and the gpu renderer do between the projection and the rasterizer
+  fn clip(vertices: [Vertex; 3]) -> Vec<[Vertex; 3]> {
+    let polygon = clipping::clip_triangle(&clip_positions(&vertices), 1.0, 10.0);
+    clipped_triangles(&vertices, &polygon)
+  }
+
+  #[test]
+  fn triangles_inside_pass_through() {
+    let vertices = [
+      vertex(-0.5, -0.5, 2.0),
+      vertex(0.5, -0.5, 2.0),
+      vertex(0.0, 0.5, 2.0),
+    ];
+
+    let triangles = clip(vertices);
+    assert_eq!(triangles.len(), 1);
+    for (clipped, original) in triangles[0].iter().zip(vertices) {
+      assert_eq!(clipped.position, original.position);
+    }
+  }
+
+  #[test]
+  fn the_near_plane_cuts_positions_and_attributes() {
+    let triangles = clip([
+      vertex(-0.5, -0.5, 0.5),
+      vertex(0.5, -0.5, 4.0),
+      vertex(0.0, 0.5, 4.0),
+    ]);
+
+    assert_eq!(triangles.len(), 2);
+    for v in triangles.iter().flatten() {
+      assert!(v.position.w >= 1.0 - 1e-5);
+      assert!((v.attributes.float[0] - v.position.w).abs() < 1e-5);
+    }
+  }
+
+  #[test]
+  fn triangles_outside_are_dropped() {
+    let behind = [0.5, 0.6, 0.7].map(|w| vertex(0.0, 0.0, w));
+    assert!(clip(behind).is_empty());
+
+    let too_far = [11.0, 12.0, 13.0].map(|w| vertex(0.0, 0.0, w));
+    assert!(clip(too_far).is_empty());
+  }
+
+  /// a vertex after the viewport, `shade` is the gray the fragment shader gives it
+  fn screen_vertex(x: f32, y: f32, shade: f32) -> Vertex {
+    let mut attributes = Attributes::default();
+    attributes.set_float(0, shade);
+    Vertex {
+      position: Vec4::new(x, y, 1.0, 1.0),
+      attributes,
+    }
+  }
+
+  fn wireframe(vertices: [Vertex; 3], style: &LineStyle) -> ColorAttachment {
+    let shader: FragmentShading = Box::new(|attributes, _, _| {
+      let shade = attributes.float[0];
+      Vec4::new(shade, shade, shade, 1.0)
+    });
+    let mut color = ColorAttachment::new(20, 20);
+    let mut depth = DepthAttachment::new(20, 20);
+    rasterize_smooth_wireframe(
+      &vertices,
+      style,
+      &shader,
+      &Uniforms::default(),
+      &TextureStore::default(),
+      &mut color,
+      &mut depth,
+    );
+    color
+  }
+
+  #[test]
+  fn smooth_wireframes_mix_attributes_along_the_edges() {
+    let vertices = [
+      screen_vertex(2.0, 2.0, 0.0),
+      screen_vertex(12.0, 2.0, 1.0),
+      screen_vertex(2.0, 12.0, 1.0),
+    ];
+    let style = LineStyle {
+      anti_alias: true,
+      ..Default::default()
+    };
+
+    let color = wireframe(vertices, &style);
+    assert!((color.get(7, 2).x - 0.5).abs() < 0.01);
+    assert!((color.get(2, 7).x - 0.5).abs() < 0.01);
+    assert_eq!(color.get(5, 5).x, 0.0);
+
+    let color = wireframe(
+      vertices,
+      &LineStyle {
+        width: 3.0,
+        ..style
+      },
+    );
+    assert!(color.get(7, 1).x > 0.0 && color.get(7, 3).x > 0.0);
+    assert_eq!(color.get(5, 5).x, 0.0);
+  }
+
+  #[test]
+  fn smooth_wireframes_clip_edges_starting_off_screen() {
+    let vertices = [
+      screen_vertex(-5.3, 4.0, 1.0),
+      screen_vertex(12.0, 4.0, 1.0),
+      screen_vertex(-5.3, -8.0, 1.0),
+    ];
+    let style = LineStyle {
+      anti_alias: true,
+      ..Default::default()
+    };
+
+    let color = wireframe(vertices, &style);
+    // the end pixel at 12 is only half covered by each of its two edges
+    for x in 0..12 {
+      assert_eq!(color.get(x, 4).x, 1.0);
+    }
+    assert_eq!(color.get(0, 5).x, 0.0);
+  }
+}
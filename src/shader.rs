@@ -83,6 +83,17 @@ pub fn lerp_vertex(start: &Vertex, end: &Vertex, t: f32) -> Vertex {
   }
 }
 
+/// the vertex at `weights` between the three `vertices`, the weights sum to 1.0
+pub fn mix_vertex(vertices: &[Vertex; 3], weights: [f32; 3]) -> Vertex {
+  let [a, b, c] = vertices;
+  let [wa, wb, wc] = weights;
+  if wa + wb <= 0.0 {
+    return *c;
+  }
+  let ab = lerp_vertex(a, b, wb / (wa + wb));
+  lerp_vertex(&ab, c, wc)
+}
+
 pub fn interp_attributes<F>(attr1: &Attributes, attr2: &Attributes, f: F, t: f32) -> Attributes
 where
   F: Fn(f32, f32, f32) -> f32,
@@ -183,6 +194,8 @@ impl Uniforms {
 }
 
 pub type VertexShading = Box<dyn Fn(&Vertex, &Uniforms, &TextureStore) -> Vertex>;
+/// Writes the single color attachment. Multiple render targets, a writable depth and
+/// discard are left to `three`, see `three::material::shader::GlPerFragment`.
 pub type FragmentShading = Box<dyn Fn(&Attributes, &Uniforms, &TextureStore) -> Vec4>;
 
 pub struct Shader {
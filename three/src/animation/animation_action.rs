@@ -0,0 +1,283 @@
+use std::rc::Rc;
+
+use super::animation_clip::AnimationClip;
+use crate::math::lerp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LoopMode {
+  /// play once and stop at the end
+  Once,
+  #[default]
+  Repeat,
+  /// forwards, then backwards, and so on
+  PingPong,
+}
+
+#[derive(Debug, Clone)]
+struct Fade {
+  from: f32,
+  to: f32,
+  elapsed: f32,
+  duration: f32,
+}
+
+/// The playback state of a clip in an `AnimationMixer`.
+#[derive(Debug, Clone)]
+pub struct AnimationAction {
+  clip: Rc<AnimationClip>,
+  /// local time in seconds
+  pub time: f32,
+  pub time_scale: f32,
+  pub weight: f32,
+  pub loop_mode: LoopMode,
+  /// how many times `Repeat` and `PingPong` play the clip, `None` for no limit
+  pub repetitions: Option<u32>,
+  /// keep applying the last frame of a finished action instead of letting go
+  pub clamp_when_finished: bool,
+  pub paused: bool,
+  running: bool,
+  finished: bool,
+  loop_count: u32,
+  fade: Option<Fade>,
+  fade_weight: f32,
+}
+
+impl AnimationAction {
+  pub fn new(clip: Rc<AnimationClip>) -> Self {
+    Self {
+      clip,
+      time: 0.0,
+      time_scale: 1.0,
+      weight: 1.0,
+      loop_mode: Default::default(),
+      repetitions: None,
+      clamp_when_finished: false,
+      paused: false,
+      running: false,
+      finished: false,
+      loop_count: 0,
+      fade: None,
+      fade_weight: 1.0,
+    }
+  }
+
+  pub fn clip(&self) -> &Rc<AnimationClip> {
+    &self.clip
+  }
+
+  pub fn play(&mut self) -> &mut Self {
+    self.running = true;
+    self.finished = false;
+    self
+  }
+
+  /// stop and rewind
+  pub fn stop(&mut self) -> &mut Self {
+    self.running = false;
+    self.fade = None;
+    self.fade_weight = 1.0;
+    self.reset()
+  }
+
+  /// rewind without changing whether the action is playing
+  pub fn reset(&mut self) -> &mut Self {
+    self.time = 0.0;
+    self.loop_count = 0;
+    self.finished = false;
+    self
+  }
+
+  pub fn set_loop(&mut self, loop_mode: LoopMode, repetitions: Option<u32>) -> &mut Self {
+    self.loop_mode = loop_mode;
+    self.repetitions = repetitions;
+    self
+  }
+
+  pub fn is_running(&self) -> bool {
+    self.running && !self.paused
+  }
+
+  /// whether the action is applied to the objects at all
+  pub fn is_active(&self) -> bool {
+    self.running || (self.finished && self.clamp_when_finished)
+  }
+
+  /// `weight` scaled by the current fade
+  pub fn effective_weight(&self) -> f32 {
+    if self.is_active() {
+      self.weight * self.fade_weight
+    } else {
+      0.0
+    }
+  }
+
+  /// ramp the weight up from 0 over `duration` seconds
+  pub fn fade_in(&mut self, duration: f32) -> &mut Self {
+    self.fade_to(0.0, 1.0, duration)
+  }
+
+  /// ramp the weight down to 0 over `duration` seconds, then stop
+  pub fn fade_out(&mut self, duration: f32) -> &mut Self {
+    self.fade_to(self.fade_weight, 0.0, duration)
+  }
+
+  fn fade_to(&mut self, from: f32, to: f32, duration: f32) -> &mut Self {
+    self.fade_weight = from;
+    self.fade = Some(Fade {
+      from,
+      to,
+      elapsed: 0.0,
+      duration,
+    });
+    self
+  }
+
+  /// the time the clip is sampled at, which runs backwards on odd `PingPong` loops
+  pub fn sample_time(&self) -> f32 {
+    match self.loop_mode {
+      LoopMode::PingPong if self.loop_count % 2 == 1 => self.clip.duration - self.time,
+      _ => self.time,
+    }
+  }
+
+  /// advance by `delta` seconds of mixer time, `false` when there is nothing to apply
+  pub(crate) fn update(&mut self, delta: f32) -> bool {
+    if !self.is_active() {
+      return false;
+    }
+
+    if let Some(fade) = self.fade.as_mut() {
+      fade.elapsed += delta.abs();
+      let t = if fade.duration > 0.0 {
+        (fade.elapsed / fade.duration).min(1.0)
+      } else {
+        1.0
+      };
+      self.fade_weight = lerp(fade.from, fade.to, t);
+
+      if t >= 1.0 {
+        let faded_out = fade.to <= 0.0;
+        self.fade = None;
+        if faded_out {
+          self.stop();
+          return false;
+        }
+      }
+    }
+
+    if self.running && !self.paused {
+      self.advance(delta * self.time_scale);
+    }
+
+    true
+  }
+
+  fn advance(&mut self, delta: f32) {
+    let duration = self.clip.duration;
+    self.time += delta;
+
+    if duration <= 0.0 {
+      self.time = 0.0;
+      return;
+    }
+
+    match self.loop_mode {
+      LoopMode::Once => {
+        if self.time >= duration || self.time < 0.0 {
+          self.time = self.time.clamp(0.0, duration);
+          self.finish();
+        }
+      }
+      LoopMode::Repeat | LoopMode::PingPong => {
+        let loops = (self.time / duration).floor();
+        if loops == 0.0 {
+          return;
+        }
+
+        self.loop_count += loops.abs() as u32;
+        match self.repetitions {
+          Some(repetitions) if self.loop_count >= repetitions => {
+            // stay on the last frame of the last repetition
+            self.loop_count = repetitions.saturating_sub(1);
+            self.time = if delta > 0.0 { duration } else { 0.0 };
+            self.finish();
+          }
+          _ => self.time -= loops * duration,
+        }
+      }
+    }
+  }
+
+  fn finish(&mut self) {
+    self.running = false;
+    self.finished = true;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn action(loop_mode: LoopMode, repetitions: Option<u32>) -> AnimationAction {
+    let mut action = AnimationAction::new(Rc::new(AnimationClip {
+      name: "clip".into(),
+      duration: 2.0,
+      tracks: vec![],
+    }));
+    action.set_loop(loop_mode, repetitions).play();
+    action
+  }
+
+  #[test]
+  fn once_stops_at_the_end() {
+    let mut action = action(LoopMode::Once, None);
+    assert!(action.update(1.5));
+    assert_eq!(action.time, 1.5);
+
+    action.update(1.0);
+    assert_eq!(action.time, 2.0);
+    assert!(!action.is_running());
+    assert_eq!(action.effective_weight(), 0.0);
+
+    action.clamp_when_finished = true;
+    assert!(action.is_active());
+    assert_eq!(action.effective_weight(), 1.0);
+  }
+
+  #[test]
+  fn repeat_wraps_until_the_repetitions_run_out() {
+    let mut action = action(LoopMode::Repeat, Some(2));
+    action.update(2.5);
+    assert_eq!(action.time, 0.5);
+    assert!(action.is_running());
+
+    action.update(2.0);
+    assert_eq!(action.time, 2.0);
+    assert!(!action.is_running());
+  }
+
+  #[test]
+  fn ping_pong_samples_backwards_on_odd_loops() {
+    let mut action = action(LoopMode::PingPong, None);
+    action.update(0.5);
+    assert_eq!(action.sample_time(), 0.5);
+    action.update(2.0);
+    assert_eq!(action.sample_time(), 1.5);
+    action.update(2.0);
+    assert_eq!(action.sample_time(), 0.5);
+  }
+
+  #[test]
+  fn fades_scale_the_weight() {
+    let mut action = action(LoopMode::Repeat, None);
+    action.fade_in(1.0);
+    action.update(0.25);
+    assert_eq!(action.effective_weight(), 0.25);
+
+    action.fade_out(0.5);
+    action.update(0.25);
+    assert_eq!(action.effective_weight(), 0.125);
+    assert!(!action.update(0.25));
+    assert!(!action.is_active());
+  }
+}
@@ -0,0 +1,169 @@
+use super::keyframe_track::Track;
+
+/// A set of tracks played together, like a walk cycle.
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+  pub name: String,
+  /// seconds, the end of the longest track unless set otherwise
+  pub duration: f32,
+  pub tracks: Vec<Track>,
+}
+
+impl AnimationClip {
+  pub fn new(name: &str, tracks: Vec<Track>) -> Self {
+    let duration = tracks.iter().fold(0.0_f32, |max, t| max.max(t.duration()));
+    Self {
+      name: name.to_string(),
+      duration,
+      tracks,
+    }
+  }
+
+  /// the clip named `name` among `clips`
+  pub fn find_by_name<'a>(clips: &'a [AnimationClip], name: &str) -> Option<&'a AnimationClip> {
+    clips.iter().find(|clip| clip.name == name)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VectorProperty {
+  Position,
+  Scale,
+}
+
+/// What a track writes to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Property {
+  Vector(VectorProperty),
+  /// a single axis, 0 to 2, animated by a number track
+  Component(VectorProperty, usize),
+  Quaternion,
+  Visible,
+  /// the weight of a morph target, by its index or its name
+  MorphTargetInfluence(String),
+}
+
+/// A track name split into the object it targets and the property on it:
+/// `"arm.position"`, `"arm.position.x"`, `"arm.quaternion"`, `"arm.visible"`,
+/// `"face.morph_target_influences[smile]"`.
+/// An empty object name targets the root the mixer animates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropertyPath {
+  pub node_name: String,
+  pub property: Property,
+}
+
+impl PropertyPath {
+  pub fn parse(path: &str) -> Option<Self> {
+    let vector = |name: &str| match name {
+      "position" => Some(VectorProperty::Position),
+      "scale" => Some(VectorProperty::Scale),
+      _ => None,
+    };
+    // target names may contain dots too, they are taken out before splitting
+    if let Some((node_name, target)) = path
+      .strip_suffix(']')
+      .and_then(|p| p.rsplit_once(".morph_target_influences["))
+    {
+      return Some(Self {
+        node_name: node_name.to_string(),
+        property: Property::MorphTargetInfluence(target.to_string()),
+      });
+    }
+
+    let (rest, last) = path.rsplit_once('.')?;
+
+    let component = match last {
+      "x" => Some(0),
+      "y" => Some(1),
+      "z" => Some(2),
+      _ => None,
+    };
+    // names of objects may contain dots themselves, only the tail is looked at
+    if let Some(index) = component {
+      let (node_name, name) = rest.rsplit_once('.').unwrap_or(("", rest));
+      if let Some(v) = vector(name) {
+        return Some(Self {
+          node_name: node_name.to_string(),
+          property: Property::Component(v, index),
+        });
+      }
+    }
+
+    let property = match last {
+      "quaternion" => Property::Quaternion,
+      "visible" => Property::Visible,
+      name => Property::Vector(vector(name)?),
+    };
+
+    Some(Self {
+      node_name: rest.to_string(),
+      property,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::animation::keyframe_track::{KeyframeTrack, Track};
+
+  fn path(node_name: &str, property: Property) -> Option<PropertyPath> {
+    Some(PropertyPath {
+      node_name: node_name.to_string(),
+      property,
+    })
+  }
+
+  #[test]
+  fn parses_property_paths() {
+    use VectorProperty::*;
+
+    assert_eq!(
+      PropertyPath::parse("arm.position"),
+      path("arm", Property::Vector(Position))
+    );
+    assert_eq!(
+      PropertyPath::parse("left.arm.scale.y"),
+      path("left.arm", Property::Component(Scale, 1))
+    );
+    assert_eq!(
+      PropertyPath::parse(".quaternion"),
+      path("", Property::Quaternion)
+    );
+    assert_eq!(
+      PropertyPath::parse("arm.visible"),
+      path("arm", Property::Visible)
+    );
+    assert_eq!(
+      PropertyPath::parse("face.morph_target_influences[smile.wide]"),
+      path("face", Property::MorphTargetInfluence("smile.wide".into()))
+    );
+    assert_eq!(PropertyPath::parse("arm.color"), None);
+    assert_eq!(PropertyPath::parse("position"), None);
+  }
+
+  #[test]
+  fn clips_last_as_long_as_their_longest_track() {
+    let track = |end: f32| {
+      Track::Number(KeyframeTrack::new(
+        ".position.x",
+        vec![0.0, end],
+        vec![0.0, 1.0],
+        Default::default(),
+      ))
+    };
+    let clips = [
+      AnimationClip::new("walk", vec![track(1.0), track(2.5)]),
+      AnimationClip::new("idle", vec![]),
+    ];
+
+    assert_eq!(clips[0].duration, 2.5);
+    assert_eq!(clips[1].duration, 0.0);
+    assert_eq!(
+      AnimationClip::find_by_name(&clips, "idle").map(|c| c.tracks.len()),
+      Some(0)
+    );
+    assert!(AnimationClip::find_by_name(&clips, "run").is_none());
+  }
+}
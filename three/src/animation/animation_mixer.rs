@@ -0,0 +1,407 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{
+  animation_action::AnimationAction,
+  animation_clip::{AnimationClip, Property, PropertyPath, VectorProperty},
+  keyframe_track::Track,
+};
+use crate::{
+  core::object_3d::ObjectActions,
+  math::{Quaternion, Vec3},
+  objects::mesh::MorphTargetActions,
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+  Vector(Vec3),
+  Quaternion(Quaternion),
+  Number(f32),
+  Boolean(bool),
+}
+
+/// The weighted sum of every action writing to a property.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+  value: Value,
+  weight: f32,
+}
+
+impl Accumulator {
+  fn add(&mut self, value: Value, weight: f32) {
+    let total = self.weight + weight;
+    self.value = match (self.value, value) {
+      (Value::Vector(acc), Value::Vector(v)) => Value::Vector(acc + v * weight),
+      (Value::Number(acc), Value::Number(v)) => Value::Number(acc + v * weight),
+      // blending the next one in by its share of the weight so far
+      (Value::Quaternion(acc), Value::Quaternion(q)) => {
+        Value::Quaternion(acc.slerp(q, weight / total))
+      }
+      (Value::Boolean(_), Value::Boolean(b)) if weight > self.weight => Value::Boolean(b),
+      (acc, _) => acc,
+    };
+    self.weight = total;
+  }
+
+  /// the final value, filled up with `rest` when the weights don't add up to 1
+  fn resolve(&self, rest: Value) -> Value {
+    let remaining = 1.0 - self.weight;
+    match (self.value, rest) {
+      (Value::Vector(acc), Value::Vector(rest)) if remaining > 0.0 => {
+        Value::Vector(acc + rest * remaining)
+      }
+      (Value::Vector(acc), _) => Value::Vector(acc / self.weight),
+      (Value::Number(acc), Value::Number(rest)) if remaining > 0.0 => {
+        Value::Number(acc + rest * remaining)
+      }
+      (Value::Number(acc), _) => Value::Number(acc / self.weight),
+      (Value::Quaternion(acc), Value::Quaternion(rest)) if remaining > 0.0 => {
+        Value::Quaternion(rest.slerp(acc, self.weight))
+      }
+      (Value::Boolean(_), Value::Boolean(rest)) if remaining > self.weight => Value::Boolean(rest),
+      (value, _) => value,
+    }
+  }
+}
+
+/// An object a track path resolved to and the value it had before being animated.
+struct Binding {
+  object: Rc<dyn ObjectActions>,
+  rest: Value,
+}
+
+fn read(object: &dyn ObjectActions, property: &Property) -> Value {
+  let vector = |v: VectorProperty| match v {
+    VectorProperty::Position => object.position(),
+    VectorProperty::Scale => object.scale(),
+  };
+
+  match property {
+    Property::Vector(v) => Value::Vector(vector(*v)),
+    Property::Component(v, i) => {
+      let Vec3 { x, y, z } = vector(*v);
+      Value::Number([x, y, z][*i])
+    }
+    Property::Quaternion => Value::Quaternion(object.quaternion()),
+    Property::Visible => Value::Boolean(object.visible()),
+    Property::MorphTargetInfluence(target) => {
+      let influence = morph_target(object, target)
+        .and_then(|(mesh, i)| mesh.morph_target_influences().get(i).copied());
+      Value::Number(influence.unwrap_or(0.0))
+    }
+  }
+}
+
+/// the object's morph targets and the index `target` names
+fn morph_target<'a>(
+  object: &'a dyn ObjectActions,
+  target: &str,
+) -> Option<(&'a dyn MorphTargetActions, usize)> {
+  let mesh = object.as_drawable()?.morph_targets()?;
+  let index = target
+    .parse()
+    .ok()
+    .or_else(|| mesh.morph_target_index(target))?;
+  Some((mesh, index))
+}
+
+fn write(object: &dyn ObjectActions, property: &Property, value: Value) {
+  let set_vector = |v: VectorProperty, value: Vec3| match v {
+    VectorProperty::Position => object.set_position(value),
+    VectorProperty::Scale => object.set_scale(value),
+  };
+
+  match (property, value) {
+    (Property::Vector(v), Value::Vector(value)) => set_vector(*v, value),
+    (Property::Component(v, i), Value::Number(value)) => {
+      let mut vector = match read(object, &Property::Vector(*v)) {
+        Value::Vector(vector) => vector,
+        _ => return,
+      };
+      match i {
+        0 => vector.x = value,
+        1 => vector.y = value,
+        _ => vector.z = value,
+      }
+      set_vector(*v, vector);
+    }
+    (Property::Quaternion, Value::Quaternion(q)) => object.set_quaternion(q.normalize()),
+    (Property::Visible, Value::Boolean(visible)) => object.set_visible(visible),
+    (Property::MorphTargetInfluence(target), Value::Number(value)) => {
+      if let Some((mesh, i)) = morph_target(object, target) {
+        mesh.set_morph_target_influence(i, value);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// the value of `track` at `time` if it fits `property`
+fn sample(track: &Track, property: &Property, time: f32) -> Option<Value> {
+  match (track, property) {
+    (Track::Vector(track), Property::Vector(_)) => track.evaluate(time).map(Value::Vector),
+    (Track::Number(track), Property::Component(..) | Property::MorphTargetInfluence(_)) => {
+      track.evaluate(time).map(Value::Number)
+    }
+    (Track::Quaternion(track), Property::Quaternion) => track.evaluate(time).map(Value::Quaternion),
+    (Track::Boolean(track), Property::Visible) => track.evaluate(time).map(Value::Boolean),
+    _ => None,
+  }
+}
+
+/// Plays clips on the objects below `root`, blending the actions by their weights.
+pub struct AnimationMixer {
+  root: Rc<dyn ObjectActions>,
+  actions: Vec<AnimationAction>,
+  /// `None` for paths no object matched
+  bindings: HashMap<PropertyPath, Option<Binding>>,
+  pub time: f32,
+  pub time_scale: f32,
+}
+
+impl AnimationMixer {
+  pub fn new(root: Rc<dyn ObjectActions>) -> Self {
+    Self {
+      root,
+      actions: vec![],
+      bindings: Default::default(),
+      time: 0.0,
+      time_scale: 1.0,
+    }
+  }
+
+  pub fn root(&self) -> &Rc<dyn ObjectActions> {
+    &self.root
+  }
+
+  /// the id of the action playing `clip`, made on first use
+  pub fn clip_action(&mut self, clip: Rc<AnimationClip>) -> usize {
+    if let Some(id) = self
+      .actions
+      .iter()
+      .position(|a| Rc::ptr_eq(a.clip(), &clip))
+    {
+      return id;
+    }
+    self.actions.push(AnimationAction::new(clip));
+    self.actions.len() - 1
+  }
+
+  /// the action of the clip named `name`, if there is one
+  pub fn existing_action(&self, name: &str) -> Option<usize> {
+    self.actions.iter().position(|a| a.clip().name == name)
+  }
+
+  pub fn action(&self, id: usize) -> Option<&AnimationAction> {
+    self.actions.get(id)
+  }
+
+  pub fn action_mut(&mut self, id: usize) -> Option<&mut AnimationAction> {
+    self.actions.get_mut(id)
+  }
+
+  pub fn actions(&self) -> &Vec<AnimationAction> {
+    &self.actions
+  }
+
+  pub fn stop_all_action(&mut self) {
+    for action in self.actions.iter_mut() {
+      action.stop();
+    }
+  }
+
+  /// start `to` and fade it in while `from` fades out over `duration` seconds
+  pub fn cross_fade(&mut self, from: usize, to: usize, duration: f32) {
+    if let Some(action) = self.actions.get_mut(from) {
+      action.fade_out(duration);
+    }
+    if let Some(action) = self.actions.get_mut(to) {
+      action.reset().play().fade_in(duration);
+    }
+  }
+
+  /// advance every action by `delta` seconds and write the blended result to the objects
+  pub fn update(&mut self, delta: f32) {
+    let delta = delta * self.time_scale;
+    self.time += delta;
+
+    let mut accumulators: Vec<(PropertyPath, Accumulator)> = vec![];
+
+    for action in self.actions.iter_mut() {
+      if !action.update(delta) {
+        continue;
+      }
+      let weight = action.effective_weight();
+      if weight <= 0.0 {
+        continue;
+      }
+
+      let time = action.sample_time();
+      for track in action.clip().tracks.iter() {
+        let path = match PropertyPath::parse(track.name()) {
+          Some(path) => path,
+          None => continue,
+        };
+        let value = match sample(track, &path.property, time) {
+          Some(value) => value,
+          None => continue,
+        };
+
+        match accumulators.iter_mut().find(|(p, _)| *p == path) {
+          Some((_, accumulator)) => accumulator.add(value, weight),
+          None => {
+            let value = match value {
+              Value::Vector(v) => Value::Vector(v * weight),
+              Value::Number(v) => Value::Number(v * weight),
+              value => value,
+            };
+            accumulators.push((path, Accumulator { value, weight }));
+          }
+        }
+      }
+    }
+
+    for (path, accumulator) in accumulators {
+      if let Some(binding) = self.bind(&path) {
+        write(
+          binding.object.as_ref(),
+          &path.property,
+          accumulator.resolve(binding.rest),
+        );
+      }
+    }
+  }
+
+  fn bind(&mut self, path: &PropertyPath) -> Option<&Binding> {
+    let root = &self.root;
+    self
+      .bindings
+      .entry(path.clone())
+      .or_insert_with(|| {
+        let object = if path.node_name.is_empty() {
+          Some(root.clone())
+        } else {
+          root
+            .get_object_by_name(&path.node_name)
+            .or_else(|| root.get_object_by_uuid(&path.node_name))
+        }?;
+        let rest = read(object.as_ref(), &path.property);
+        Some(Binding { object, rest })
+      })
+      .as_ref()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    animation::keyframe_track::KeyframeTrack,
+    core::{buffer_attribute::F32BufferAttribute, buffer_geometry::BufferGeometry},
+    material::depth_material::MeshDepthMaterial,
+    objects::{group::Group, mesh::Mesh, scene::Scene},
+  };
+
+  fn rigged() -> (Rc<Scene>, Rc<Group>) {
+    let scene = Scene::new();
+    let arm = Group::new();
+    arm.set_name("arm");
+    scene.add(arm.clone());
+    (scene, arm)
+  }
+
+  fn hold_x(name: &str, x: f32) -> Rc<AnimationClip> {
+    let track = KeyframeTrack::new(
+      "arm.position.x",
+      vec![0.0, 1.0],
+      vec![x, x],
+      Default::default(),
+    );
+    Rc::new(AnimationClip::new(name, vec![Track::Number(track)]))
+  }
+
+  #[test]
+  fn tracks_drive_the_named_objects() {
+    let (scene, arm) = rigged();
+    let position = KeyframeTrack::new(
+      "arm.position",
+      vec![0.0, 1.0],
+      vec![Vec3::default(), Vec3::new(2.0, 0.0, 0.0)],
+      Default::default(),
+    );
+    let missing = KeyframeTrack::new("leg.visible", vec![0.0], vec![false], Default::default());
+    let clip = AnimationClip::new(
+      "swing",
+      vec![Track::Vector(position), Track::Boolean(missing)],
+    );
+
+    let mut mixer = AnimationMixer::new(scene);
+    let id = mixer.clip_action(Rc::new(clip));
+    mixer.action_mut(id).unwrap().play();
+    mixer.update(0.5);
+
+    assert_eq!(arm.position(), Vec3::new(1.0, 0.0, 0.0));
+    assert_eq!(mixer.existing_action("swing"), Some(id));
+  }
+
+  #[test]
+  fn partial_weights_blend_with_the_rest_pose() {
+    let (scene, arm) = rigged();
+    arm.set_position(Vec3::new(4.0, 0.0, 0.0));
+
+    let mut mixer = AnimationMixer::new(scene);
+    let id = mixer.clip_action(hold_x("zero", 0.0));
+    let action = mixer.action_mut(id).unwrap();
+    action.weight = 0.5;
+    action.play();
+    mixer.update(0.1);
+
+    assert_eq!(arm.position().x, 2.0);
+  }
+
+  #[test]
+  fn cross_fades_hand_over_between_actions() {
+    let (scene, arm) = rigged();
+    let mut mixer = AnimationMixer::new(scene);
+    let from = mixer.clip_action(hold_x("from", 0.0));
+    let to = mixer.clip_action(hold_x("to", 2.0));
+
+    mixer.action_mut(from).unwrap().play();
+    mixer.update(0.1);
+    assert_eq!(arm.position().x, 0.0);
+
+    mixer.cross_fade(from, to, 1.0);
+    mixer.update(0.5);
+    assert_eq!(arm.position().x, 1.0);
+
+    mixer.update(0.5);
+    assert!(!mixer.action(from).unwrap().is_active());
+    assert_eq!(arm.position().x, 2.0);
+  }
+
+  #[test]
+  fn number_tracks_drive_morph_targets_by_name() {
+    let mut geometry = BufferGeometry::default();
+    let delta = F32BufferAttribute::new(vec![0.0, 1.0, 0.0], 3, false).as_enum();
+    geometry.set_morph_attribute("position", vec![delta]);
+    geometry.set_morph_target_names(vec!["smile".to_string()]);
+    let face = Mesh::new(geometry, MeshDepthMaterial::default());
+    face.set_name("face");
+    let scene = Scene::new();
+    scene.add(face.clone());
+
+    let track = KeyframeTrack::new(
+      "face.morph_target_influences[smile]",
+      vec![0.0, 1.0],
+      vec![0.0, 1.0],
+      Default::default(),
+    );
+    let clip = AnimationClip::new("grin", vec![Track::Number(track)]);
+
+    let mut mixer = AnimationMixer::new(scene);
+    let id = mixer.clip_action(Rc::new(clip));
+    mixer.action_mut(id).unwrap().play();
+    mixer.update(0.75);
+
+    assert_eq!(face.morph_target_influences(), [0.75]);
+  }
+}
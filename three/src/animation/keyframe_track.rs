@@ -0,0 +1,192 @@
+use crate::math::{lerp, Quaternion, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Interpolation {
+  /// hold every key until the next one
+  Discrete,
+  #[default]
+  Linear,
+  /// Catmull-Rom through the keys, quaternions and booleans fall back to linear/discrete
+  Smooth,
+}
+
+/// A value keyframes can hold.
+pub trait TrackValue: Copy {
+  fn lerp(a: Self, b: Self, t: f32) -> Self;
+
+  /// the curve between `p1` and `p2`, `p0` and `p3` are the neighbouring keys
+  fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+    let _ = (p0, p3);
+    Self::lerp(p1, p2, t)
+  }
+}
+
+macro_rules! impl_track_value {
+  ($($type:ty),+) => {
+    $(
+      impl TrackValue for $type {
+        fn lerp(a: Self, b: Self, t: f32) -> Self {
+          lerp(a, b, t)
+        }
+
+        fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+          let (t2, t3) = (t * t, t * t * t);
+          (p1 * 2.0
+            + (p2 - p0) * t
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+            * 0.5
+        }
+      }
+    )+
+  };
+}
+
+impl_track_value!(f32, Vec3);
+
+impl TrackValue for Quaternion {
+  fn lerp(a: Self, b: Self, t: f32) -> Self {
+    a.slerp(b, t)
+  }
+}
+
+impl TrackValue for bool {
+  fn lerp(a: Self, _: Self, _: f32) -> Self {
+    a
+  }
+}
+
+/// Keys of a single property, `name` is the property path like `"arm.quaternion"`.
+#[derive(Debug, Clone)]
+pub struct KeyframeTrack<T: TrackValue> {
+  pub name: String,
+  /// seconds, ascending
+  pub times: Vec<f32>,
+  pub values: Vec<T>,
+  pub interpolation: Interpolation,
+}
+
+impl<T: TrackValue> KeyframeTrack<T> {
+  pub fn new(name: &str, times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Self {
+    assert_eq!(
+      times.len(),
+      values.len(),
+      "a keyframe track needs one value per key"
+    );
+    Self {
+      name: name.to_string(),
+      times,
+      values,
+      interpolation,
+    }
+  }
+
+  /// time of the last key
+  pub fn duration(&self) -> f32 {
+    self.times.last().copied().unwrap_or(0.0)
+  }
+
+  /// the value at `time`, held before the first and after the last key
+  pub fn evaluate(&self, time: f32) -> Option<T> {
+    let last = self.times.len().checked_sub(1)?;
+    if time <= self.times[0] {
+      return Some(self.values[0]);
+    }
+    if time >= self.times[last] {
+      return Some(self.values[last]);
+    }
+
+    // the key starting the segment `time` is in
+    let i = self.times.partition_point(|t| *t <= time) - 1;
+    let alpha = (time - self.times[i]) / (self.times[i + 1] - self.times[i]);
+    let v = &self.values;
+
+    Some(match self.interpolation {
+      Interpolation::Discrete => v[i],
+      Interpolation::Linear => T::lerp(v[i], v[i + 1], alpha),
+      Interpolation::Smooth => T::cubic(
+        v[i.saturating_sub(1)],
+        v[i],
+        v[i + 1],
+        v[(i + 2).min(last)],
+        alpha,
+      ),
+    })
+  }
+}
+
+/// The kinds of track a clip can hold.
+#[derive(Debug, Clone)]
+pub enum Track {
+  Vector(KeyframeTrack<Vec3>),
+  Quaternion(KeyframeTrack<Quaternion>),
+  Number(KeyframeTrack<f32>),
+  Boolean(KeyframeTrack<bool>),
+}
+
+impl Track {
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Vector(track) => &track.name,
+      Self::Quaternion(track) => &track.name,
+      Self::Number(track) => &track.name,
+      Self::Boolean(track) => &track.name,
+    }
+  }
+
+  pub fn duration(&self) -> f32 {
+    match self {
+      Self::Vector(track) => track.duration(),
+      Self::Quaternion(track) => track.duration(),
+      Self::Number(track) => track.duration(),
+      Self::Boolean(track) => track.duration(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ramp(interpolation: Interpolation) -> KeyframeTrack<f32> {
+    KeyframeTrack::new(
+      "a.position.x",
+      vec![0.0, 1.0, 2.0, 3.0],
+      vec![0.0, 1.0, 2.0, 3.0],
+      interpolation,
+    )
+  }
+
+  #[test]
+  fn values_are_held_outside_the_keys() {
+    let track = ramp(Interpolation::Linear);
+    assert_eq!(track.evaluate(-1.0), Some(0.0));
+    assert_eq!(track.evaluate(5.0), Some(3.0));
+    assert_eq!(track.duration(), 3.0);
+
+    let empty = KeyframeTrack::<f32>::new("a.visible", vec![], vec![], Default::default());
+    assert_eq!(empty.evaluate(0.0), None);
+  }
+
+  #[test]
+  fn interpolation_modes() {
+    assert_eq!(ramp(Interpolation::Discrete).evaluate(1.5), Some(1.0));
+    assert_eq!(ramp(Interpolation::Linear).evaluate(1.5), Some(1.5));
+    // Catmull-Rom through evenly spaced keys on a line stays on the line
+    let smooth = ramp(Interpolation::Smooth);
+    assert!((smooth.evaluate(1.5).unwrap() - 1.5).abs() < 1e-5);
+    assert!((smooth.evaluate(2.0).unwrap() - 2.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn booleans_switch_at_the_next_key() {
+    let track = KeyframeTrack::new(
+      "a.visible",
+      vec![0.0, 1.0],
+      vec![true, false],
+      Interpolation::Linear,
+    );
+    assert_eq!(track.evaluate(0.9), Some(true));
+    assert_eq!(track.evaluate(1.0), Some(false));
+  }
+}
@@ -0,0 +1,4 @@
+pub mod animation_action;
+pub mod animation_clip;
+pub mod animation_mixer;
+pub mod keyframe_track;
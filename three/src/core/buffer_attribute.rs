@@ -1,5 +1,5 @@
-use crate::math::Vec3;
-struct TypeBufferAttribute<T: Sized + Copy> {
+use crate::math::{Vec3, Vec4};
+pub struct TypeBufferAttribute<T: Sized + Copy> {
   data: Vec<T>,
   size: usize,
   normalized: bool,
@@ -87,12 +87,53 @@ macro_rules! typed_array {
         Some(Vec3::new(x,y,z))
       }
     }
+
+    impl ExtractRef<Vec4> for  $enum_name {
+      fn extract(&self) -> Option<Vec4> {
+        let (x,y,z,w) = match self {
+          $(
+            Self::$enum(val)=> {
+              if val.data.len() < 4{
+                return None
+              }
+              (val.data[0] as f32,val.data[1] as f32,val.data[2] as f32,val.data[3] as f32)
+            }
+          )+
+        };
+        Some(Vec4::new(x,y,z,w))
+      }
+    }
     pub enum $enum_name {
       $(
        $enum(Box<$type>),
       ) +
     }
 
+    impl $enum_name {
+      /// number of items, each item holds `size` components
+      pub fn count(&self) -> usize {
+        match self {
+          $(
+            Self::$enum(val) => val.data.len() / val.size.max(1),
+          )+
+        }
+      }
+
+      /// copy out the components of a single item, e.g. the position of one vertex
+      pub fn item(&self, index: usize) -> Self {
+        match self {
+          $(
+            Self::$enum(val) => {
+              let start = (index * val.size).min(val.data.len());
+              let end = (start + val.size).min(val.data.len());
+              let data = val.data[start..end].to_vec();
+              Self::$enum(Box::new(TypeBufferAttribute::new(data, val.size, val.normalized)))
+            }
+          )+
+        }
+      }
+    }
+
   };
 }
 
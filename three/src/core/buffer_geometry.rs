@@ -1,8 +1,13 @@
-use super::{buffer_attribute::TypeBufferEnum, marco::Extract};
+use super::{
+  buffer_attribute::{ExtractRef, F32BufferAttribute, TypeBufferEnum},
+  marco::Extract,
+};
+use crate::math::{Box3, Vec3};
 use std::collections::HashMap;
 
 pub struct BufferGeometry {
   attributes: Attribute,
+  morph_attributes: MorphAttributes,
   uuid: String,
 }
 
@@ -10,16 +15,123 @@ impl Default for BufferGeometry {
   fn default() -> Self {
     Self {
       attributes: Default::default(),
+      morph_attributes: Default::default(),
       uuid: uuid::Uuid::new_v4().to_string(),
     }
   }
 }
 
+impl BufferGeometry {
+  /// the deltas every morph target adds to the attribute `key`, like `position` or `normal`
+  pub fn set_morph_attribute(&mut self, key: &str, targets: Vec<TypeBufferEnum>) {
+    self
+      .morph_attributes
+      .attributes
+      .insert(key.to_string(), targets);
+  }
+
+  /// names of the morph targets, in the order of their deltas
+  pub fn set_morph_target_names(&mut self, names: Vec<String>) {
+    self.morph_attributes.names = names;
+  }
+}
+
 pub type Attribute = HashMap<String, TypeBufferEnum>;
 
+/// Blend shapes of a geometry.
+#[derive(Default)]
+pub struct MorphAttributes {
+  /// per attribute, the deltas of every target
+  pub attributes: HashMap<String, Vec<TypeBufferEnum>>,
+  pub names: Vec<String>,
+}
+
 pub trait GeometryActions {
   fn get_attribute(&self) -> &Attribute;
   fn set_attribute(&mut self, key: &str, val: TypeBufferEnum);
+  fn get_morph_attributes(&self) -> &MorphAttributes;
+
+  /// number of vertices, derived from the `position` attribute
+  fn count(&self) -> usize {
+    self.get_attribute().get("position").map_or(0, |v| v.count())
+  }
+
+  /// the attributes of the vertex at `index`, which is what a vertex shader reads
+  fn vertex_attribute(&self, index: usize) -> Attribute {
+    self
+      .get_attribute()
+      .iter()
+      .map(|(key, val)| (key.clone(), val.item(index)))
+      .collect()
+  }
+
+  /// the box around every vertex, grown by each morph target applied in full
+  fn compute_bounding_box(&self) -> Box3 {
+    let mut bounds = Box3::empty();
+    let positions = match self.get_attribute().get("position") {
+      Some(positions) => positions,
+      None => return bounds,
+    };
+    let targets = self.get_morph_attributes().attributes.get("position");
+
+    for index in 0..positions.count() {
+      let position: Vec3 = match positions.item(index).extract() {
+        Some(position) => position,
+        None => continue,
+      };
+      bounds.expand_by_point(position);
+
+      for target in targets.into_iter().flatten() {
+        if let Some(delta) = ExtractRef::<Vec3>::extract(&target.item(index)) {
+          bounds.expand_by_point(position + delta);
+        }
+      }
+    }
+
+    bounds
+  }
+
+  fn morph_target_count(&self) -> usize {
+    let morph = self.get_morph_attributes();
+    morph
+      .attributes
+      .values()
+      .map(|targets| targets.len())
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// `vertex_attribute` with the deltas of the morph targets added, each weighted by
+  /// its entry in `influences`
+  fn morphed_vertex_attribute(&self, index: usize, influences: &[f32]) -> Attribute {
+    let mut attribute = self.vertex_attribute(index);
+    if influences.iter().all(|w| *w == 0.0) {
+      return attribute;
+    }
+
+    for (key, targets) in self.get_morph_attributes().attributes.iter() {
+      let mut value: Vec3 = match attribute.get(key).and_then(|v| v.extract()) {
+        Some(value) => value,
+        None => continue,
+      };
+
+      for (target, influence) in targets.iter().zip(influences) {
+        if *influence == 0.0 {
+          continue;
+        }
+        if let Some(delta) = ExtractRef::<Vec3>::extract(&target.item(index)) {
+          value += delta * *influence;
+        }
+      }
+
+      attribute.insert(
+        key.clone(),
+        F32BufferAttribute::new(vec![value.x, value.y, value.z], 3, false).as_enum(),
+      );
+    }
+
+    attribute
+  }
 }
 
 impl GeometryActions for BufferGeometry {
@@ -31,6 +143,10 @@ impl GeometryActions for BufferGeometry {
   fn set_attribute(&mut self, key: &str, val: TypeBufferEnum) {
     self.attributes.insert(key.to_string(), val);
   }
+
+  fn get_morph_attributes(&self) -> &MorphAttributes {
+    &self.morph_attributes
+  }
 }
 
 macro_rules! attribute {
@@ -54,3 +170,50 @@ macro_rules! attribute {
 }
 
 pub(crate) use attribute;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn attribute(data: Vec<f32>) -> TypeBufferEnum {
+    F32BufferAttribute::new(data, 3, false).as_enum()
+  }
+
+  fn morphed_geometry() -> BufferGeometry {
+    let mut geometry = BufferGeometry::default();
+    geometry.set_attribute("position", attribute(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0]));
+    geometry.set_morph_attribute(
+      "position",
+      vec![
+        attribute(vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0]),
+        attribute(vec![0.0, 0.0, 2.0, 0.0, 0.0, 2.0]),
+      ],
+    );
+    geometry
+  }
+
+  fn position(attribute: &Attribute) -> Vec3 {
+    ExtractRef::<Vec3>::extract(&attribute["position"]).unwrap()
+  }
+
+  #[test]
+  fn targets_are_counted_per_attribute() {
+    assert_eq!(BufferGeometry::default().morph_target_count(), 0);
+    assert_eq!(morphed_geometry().morph_target_count(), 2);
+  }
+
+  #[test]
+  fn influences_weight_the_target_deltas() {
+    let geometry = morphed_geometry();
+
+    let rest = geometry.morphed_vertex_attribute(1, &[0.0, 0.0]);
+    assert_eq!(position(&rest), Vec3::new(1.0, 0.0, 0.0));
+
+    let morphed = geometry.morphed_vertex_attribute(1, &[0.5, 1.0]);
+    assert_eq!(position(&morphed), Vec3::new(1.0, 0.5, 2.0));
+
+    // missing weights leave their targets out
+    let morphed = geometry.morphed_vertex_attribute(0, &[1.0]);
+    assert_eq!(position(&morphed), Vec3::new(0.0, 1.0, 0.0));
+  }
+}
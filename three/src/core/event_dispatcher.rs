@@ -0,0 +1,72 @@
+use std::{
+  cell::{Cell, RefCell},
+  rc::Rc,
+};
+
+use super::object_3d::ObjectActions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectEventType {
+  /// the target was added to a parent
+  Added,
+  /// the target was removed from its parent
+  Removed,
+  ChildAdded,
+  ChildRemoved,
+}
+
+pub struct ObjectEvent {
+  pub event_type: ObjectEventType,
+  pub target: Rc<dyn ObjectActions>,
+  /// the child added or removed, for `ChildAdded` and `ChildRemoved`
+  pub child: Option<Rc<dyn ObjectActions>>,
+}
+
+pub type EventListener = dyn Fn(&ObjectEvent);
+
+/// The listeners of a single object.
+#[derive(Default)]
+pub struct EventDispatcher {
+  next_id: Cell<usize>,
+  listeners: RefCell<Vec<(usize, ObjectEventType, Rc<EventListener>)>>,
+}
+
+impl EventDispatcher {
+  /// the returned id removes the listener again
+  pub fn add(&self, event_type: ObjectEventType, listener: Box<EventListener>) -> usize {
+    let id = self.next_id.get();
+    self.next_id.set(id + 1);
+    self
+      .listeners
+      .borrow_mut()
+      .push((id, event_type, Rc::from(listener)));
+    id
+  }
+
+  pub fn remove(&self, id: usize) {
+    self.listeners.borrow_mut().retain(|(i, ..)| *i != id);
+  }
+
+  pub fn has(&self, event_type: ObjectEventType) -> bool {
+    self
+      .listeners
+      .borrow()
+      .iter()
+      .any(|(_, t, _)| *t == event_type)
+  }
+
+  pub fn dispatch(&self, event: &ObjectEvent) {
+    // listeners may add or remove listeners
+    let listeners: Vec<_> = self
+      .listeners
+      .borrow()
+      .iter()
+      .filter(|(_, t, _)| *t == event.event_type)
+      .map(|(_, _, listener)| listener.clone())
+      .collect();
+
+    for listener in listeners {
+      listener(event);
+    }
+  }
+}
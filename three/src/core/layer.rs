@@ -1,3 +1,4 @@
+#[derive(Debug, Clone)]
 pub struct Layers {
   pub mask: u32,
 }
@@ -1,5 +1,6 @@
 pub mod buffer_attribute;
 pub mod buffer_geometry;
+pub mod event_dispatcher;
 mod marco;
 pub mod object_3d;
 pub mod unifrom;
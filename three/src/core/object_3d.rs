@@ -6,6 +6,7 @@ pub enum ObjectType {
   Object3D,
   Camera,
   Group,
+  Bone,
   Line,
   Point,
 }
@@ -15,6 +16,15 @@ impl Default for ObjectType {
     Self::Object3D
   }
 }
+/// Called by `GlRenderer` right before or after an object is drawn.
+pub type RenderCallback = dyn Fn(
+  &mut crate::renderer::gl_renderer::GlRenderer,
+  &crate::objects::scene::Scene,
+  &dyn crate::cameras::camera::Camera,
+  &dyn crate::core::buffer_geometry::GeometryActions,
+  &dyn crate::material::material::MaterialActions,
+);
+
 pub trait ObjectActions {
   fn parent(&self) -> Option<std::rc::Rc<dyn ObjectActions>>;
   fn set_parent(&self, parent: std::rc::Rc<dyn ObjectActions>);
@@ -22,13 +32,28 @@ pub trait ObjectActions {
   fn remove(&self, uuid: &str);
   fn add(&self, val: std::rc::Rc<dyn ObjectActions>);
   fn clear(&self);
-  fn attach(&self, child: Box<dyn ObjectActions>);
+  /// like `add`, but keeps the world transform of `child`, unless the global matrix of
+  /// this object is singular
+  fn attach(&self, child: std::rc::Rc<dyn ObjectActions>);
   fn children(&self) -> std::cell::Ref<'_, Vec<std::rc::Rc<dyn ObjectActions>>>;
 
   fn look_at(&self, point: crate::math::Vec3);
   fn matrix(&self) -> crate::math::Mat4;
   fn global_matrix(&self) -> crate::math::Mat4;
-  fn update_global_matrix(&self);
+  /// update the global matrices of this object and its descendants, top-down
+  fn update_global_matrix(&self) {
+    self.propagate_global_matrix(false);
+  }
+  /// only subtrees whose local matrix changed are recomputed, unless `force`
+  fn propagate_global_matrix(&self, force: bool);
+  /// make the global matrix current without updating the whole scene,
+  /// walking up to the root first and/or down to the descendants
+  fn sync_global_matrix(&self, update_parents: bool, update_children: bool);
+  /// when off, the global matrix is left as is and only updated by hand
+  fn matrix_world_auto_update(&self) -> bool;
+  fn set_matrix_world_auto_update(&self, val: bool);
+  /// have the next `update_global_matrix` recompute this subtree
+  fn mark_global_matrix_dirty(&self);
   fn update_matrix(&self);
   fn compose(&self) -> crate::math::Mat4;
   fn decompose(&self);
@@ -51,13 +76,166 @@ pub trait ObjectActions {
   fn global_position(&self) -> crate::math::Vec3;
   fn global_rotation(&self) -> crate::math::Rotation;
 
+  fn local_to_world(&self, point: crate::math::Vec3) -> crate::math::Vec3 {
+    self.sync_global_matrix(true, false);
+    (self.global_matrix() * crate::math::Vec4::from_vec3(&point, 1.0)).truncated_to_vec3()
+  }
+
+  /// `None` when the global matrix can't be inverted, e.g. under a zero scale
+  fn world_to_local(&self, point: crate::math::Vec3) -> Option<crate::math::Vec3> {
+    self.sync_global_matrix(true, false);
+    let inverse = self.global_matrix().inverse()?;
+    Some((inverse * crate::math::Vec4::from_vec3(&point, 1.0)).truncated_to_vec3())
+  }
+
   fn layers(&self) -> std::cell::Ref<crate::core::layer::Layers>;
   fn test_layers(&self, layers: &crate::core::layer::Layers) -> bool;
 
   fn visible(&self) -> bool;
+  fn set_visible(&self, val: bool);
+
+  fn position(&self) -> crate::math::Vec3;
+  fn set_position(&self, position: crate::math::Vec3);
+  fn quaternion(&self) -> crate::math::Quaternion;
+  /// also updates the euler angles of the rotation
+  fn set_quaternion(&self, q: crate::math::Quaternion);
+  fn scale(&self) -> crate::math::Vec3;
+  fn set_scale(&self, scale: crate::math::Vec3);
   fn get_type(&self) -> ObjectType;
 
   fn uuid(&self) -> &str;
+
+  fn cast_shadow(&self) -> bool;
+  fn set_cast_shadow(&self, val: bool);
+  fn receive_shadow(&self) -> bool;
+  fn set_receive_shadow(&self, val: bool);
+
+  fn as_any(&self) -> &dyn std::any::Any;
+  /// the geometry and material the renderer draws, `None` for anything that draws nothing
+  fn as_drawable(&self) -> Option<&dyn crate::objects::Drawable> {
+    None
+  }
+  /// `None` for anything but a light
+  fn as_light(&self) -> Option<&dyn crate::lights::light::LightActions> {
+    None
+  }
+
+  fn name(&self) -> String;
+  fn set_name(&self, name: &str);
+  /// the `Rc` this object lives in
+  fn self_ref(&self) -> Option<std::rc::Rc<dyn ObjectActions>>;
+
+  /// visit this object and all of its descendants, parents before their children
+  fn traverse(&self, callback: &mut dyn FnMut(&std::rc::Rc<dyn ObjectActions>)) {
+    if let Some(me) = self.self_ref() {
+      callback(&me);
+    }
+    // the callback may change the tree, don't keep the children borrowed
+    let children = self.children().clone();
+    for child in children.iter() {
+      child.traverse(callback);
+    }
+  }
+
+  /// like `traverse`, skipping invisible objects along with their descendants
+  fn traverse_visible(&self, callback: &mut dyn FnMut(&std::rc::Rc<dyn ObjectActions>)) {
+    if !self.visible() {
+      return;
+    }
+    if let Some(me) = self.self_ref() {
+      callback(&me);
+    }
+    let children = self.children().clone();
+    for child in children.iter() {
+      child.traverse_visible(callback);
+    }
+  }
+
+  /// visit the parent, its parent and so on up to the root
+  fn traverse_ancestors(&self, callback: &mut dyn FnMut(&std::rc::Rc<dyn ObjectActions>)) {
+    let mut parent = self.parent();
+    while let Some(p) = parent {
+      callback(&p);
+      parent = p.parent();
+    }
+  }
+
+  /// the first object named `name` in this subtree, this object included
+  fn get_object_by_name(&self, name: &str) -> Option<std::rc::Rc<dyn ObjectActions>> {
+    if self.name() == name {
+      return self.self_ref();
+    }
+    let children = self.children().clone();
+    children.iter().find_map(|child| child.get_object_by_name(name))
+  }
+
+  fn get_object_by_uuid(&self, uuid: &str) -> Option<std::rc::Rc<dyn ObjectActions>> {
+    if self.uuid() == uuid {
+      return self.self_ref();
+    }
+    let children = self.children().clone();
+    children.iter().find_map(|child| child.get_object_by_uuid(uuid))
+  }
+
+  /// every object in this subtree matching `predicate`, in `traverse` order
+  fn find_all(
+    &self,
+    predicate: &dyn Fn(&dyn ObjectActions) -> bool,
+  ) -> Vec<std::rc::Rc<dyn ObjectActions>> {
+    let mut res = vec![];
+    self.traverse(&mut |object| {
+      if predicate(object.as_ref()) {
+        res.push(object.clone());
+      }
+    });
+    res
+  }
+
+  /// the returned id removes the listener again
+  fn add_event_listener(
+    &self,
+    event_type: crate::core::event_dispatcher::ObjectEventType,
+    listener: Box<crate::core::event_dispatcher::EventListener>,
+  ) -> usize;
+  fn remove_event_listener(&self, id: usize);
+  fn dispatch_event(&self, event: &crate::core::event_dispatcher::ObjectEvent);
+
+  fn set_on_before_render(&self, callback: Option<Box<RenderCallback>>);
+  fn set_on_after_render(&self, callback: Option<Box<RenderCallback>>);
+  fn on_before_render(
+    &self,
+    renderer: &mut crate::renderer::gl_renderer::GlRenderer,
+    scene: &crate::objects::scene::Scene,
+    camera: &dyn crate::cameras::camera::Camera,
+    geometry: &dyn crate::core::buffer_geometry::GeometryActions,
+    material: &dyn crate::material::material::MaterialActions,
+  );
+  fn on_after_render(
+    &self,
+    renderer: &mut crate::renderer::gl_renderer::GlRenderer,
+    scene: &crate::objects::scene::Scene,
+    camera: &dyn crate::cameras::camera::Camera,
+    geometry: &dyn crate::core::buffer_geometry::GeometryActions,
+    material: &dyn crate::material::material::MaterialActions,
+  );
+
+  /// a copy of this object alone with a fresh uuid, geometry and material are shared
+  fn clone_object(&self) -> std::rc::Rc<dyn ObjectActions>;
+
+  /// a copy of this object and its descendants, see `clone_object`
+  fn deep_clone(&self) -> std::rc::Rc<dyn ObjectActions> {
+    let copy = self.clone_object();
+    let children = self.children().clone();
+    for child in children.iter() {
+      copy.add(child.deep_clone());
+    }
+    copy
+  }
+}
+
+/// Implemented by `#[object_3d]`, a new object made from clones of the own fields.
+pub trait CloneFields {
+  fn clone_fields(&self) -> std::rc::Rc<Self>;
 }
 
 macro_rules! define_support_objects {
@@ -96,8 +274,7 @@ macro_rules! define_support_objects {
 
 macro_rules! with_default_fields {
   ($type:tt;$($val:ident),*) => {{
-
-    let mut this = std::rc::Rc::new(Self {
+    let this = std::rc::Rc::new(Self {
       $($val,)*
       parent: Default::default(),
       children: Default::default(),
@@ -105,24 +282,798 @@ macro_rules! with_default_fields {
       global_matrix: Default::default(),
       position: Default::default(),
       rotation: Default::default(),
-      scale: Default::default(),
-      visible: Default::default(),
+      scale: std::cell::RefCell::new(crate::math::Vec3::new(1.0, 1.0, 1.0)),
+      visible: std::cell::RefCell::new(true),
+      matrix_world_auto_update: std::cell::RefCell::new(true),
+      global_matrix_dirty: std::cell::RefCell::new(true),
       layers: Default::default(),
       cast_shadow: Default::default(),
-      object_type:crate::core::object_3d::ObjectType::$type,
+      object_type: crate::core::object_3d::ObjectType::$type,
       receive_shadow: Default::default(),
       user_data: Default::default(),
+      name: Default::default(),
+      events: Default::default(),
+      before_render: Default::default(),
+      after_render: Default::default(),
       _uuid: uuid::Uuid::new_v4().to_string(),
-      _self_ref: None,
+      _self_ref: Default::default(),
     });
 
-    let mut that: std::rc::Rc<dyn crate::core::object_3d::ObjectActions> = this.clone();
+    let that: std::rc::Rc<dyn crate::core::object_3d::ObjectActions> = this.clone();
 
-    this._self_ref = Some(std::rc::Rc::downgrade(&that));
+    let _ = this._self_ref.set(std::rc::Rc::downgrade(&that));
 
     this
   }};
 }
 
+/// Implement `ObjectActions` for a struct declaring the common object fields,
+/// see `#[object_3d]` for the field list. The traits after the name are the ones
+/// the object is viewed as, `Drawable` and/or `LightActions`.
+macro_rules! impl_object_actions {
+  (@view Drawable) => {
+    fn as_drawable(&self) -> Option<&dyn crate::objects::Drawable> {
+      Some(self)
+    }
+  };
+  (@view LightActions) => {
+    fn as_light(&self) -> Option<&dyn crate::lights::light::LightActions> {
+      Some(self)
+    }
+  };
+  ($name:ty $(, $view:ident)*) => {
+    impl crate::core::object_3d::ObjectActions for $name {
+      $(crate::core::object_3d::impl_object_actions!(@view $view);)*
+
+      fn parent(&self) -> Option<std::rc::Rc<dyn crate::core::object_3d::ObjectActions>> {
+        self.parent.borrow().as_ref().and_then(|p| p.upgrade())
+      }
+
+      fn set_parent(&self, parent: std::rc::Rc<dyn crate::core::object_3d::ObjectActions>) {
+        // only children keep their objects alive, a strong parent would be a cycle
+        let mut p = self.parent.borrow_mut();
+        *p = Some(std::rc::Rc::downgrade(&parent));
+      }
+
+      fn remove_from_parent(&self) {
+        let parent = self.parent.borrow_mut().take();
+
+        if let Some(parent) = parent.and_then(|p| p.upgrade()) {
+          parent.remove(&self._uuid);
+        }
+      }
+
+      fn remove(&self, uuid: &str) {
+        let removed = {
+          let mut children = self.children.borrow_mut();
+          let index = children.iter().position(|x| (*x).uuid() == uuid);
+          index.map(|index| children.remove(index))
+        };
+
+        if let Some(child) = removed {
+          // a no-op when called from the child's `remove_from_parent`
+          child.remove_from_parent();
+
+          child.dispatch_event(&crate::core::event_dispatcher::ObjectEvent {
+            event_type: crate::core::event_dispatcher::ObjectEventType::Removed,
+            target: child.clone(),
+            child: None,
+          });
+          if let Some(me) = self.self_ref() {
+            self.dispatch_event(&crate::core::event_dispatcher::ObjectEvent {
+              event_type: crate::core::event_dispatcher::ObjectEventType::ChildRemoved,
+              target: me,
+              child: Some(child),
+            });
+          }
+        }
+      }
+
+      fn add(&self, child: std::rc::Rc<dyn crate::core::object_3d::ObjectActions>) {
+        if let Some(me) = self._self_ref.get().and_then(|p| p.upgrade()) {
+          // the old parent may be this object, don't hold the children while it removes the child
+          child.remove_from_parent();
+          child.set_parent(me.clone());
+          child.mark_global_matrix_dirty();
+          self.children.borrow_mut().push(child.clone());
+
+          child.dispatch_event(&crate::core::event_dispatcher::ObjectEvent {
+            event_type: crate::core::event_dispatcher::ObjectEventType::Added,
+            target: child.clone(),
+            child: None,
+          });
+          self.dispatch_event(&crate::core::event_dispatcher::ObjectEvent {
+            event_type: crate::core::event_dispatcher::ObjectEventType::ChildAdded,
+            target: me,
+            child: Some(child),
+          });
+        }
+      }
+
+      fn clear(&self) {
+        let children = self.children.borrow().clone();
+        for child in children.iter() {
+          self.remove(child.uuid());
+        }
+      }
+
+      fn attach(&self, child: std::rc::Rc<dyn crate::core::object_3d::ObjectActions>) {
+        self.sync_global_matrix(true, false);
+
+        if let Some(mut res) = self.global_matrix().inverse() {
+          if let Some(parent) = child.parent() {
+            parent.sync_global_matrix(true, false);
+            res = res * parent.global_matrix();
+          }
+
+          child.apply_matrix(res);
+        }
+        self.add(child);
+      }
+
+      fn children(&self) -> std::cell::Ref<'_, Vec<std::rc::Rc<dyn crate::core::object_3d::ObjectActions>>> {
+        self.children.borrow()
+      }
+
+      fn look_at(&self, target: crate::math::Vec3) {
+        self.sync_global_matrix(true, false);
+
+        let position = crate::math::extract_position(*self.global_matrix.borrow());
+
+        let is_revert_z = self.object_type == crate::core::object_3d::ObjectType::Camera
+          || self.object_type == crate::core::object_3d::ObjectType::Light;
+
+        let (eye, target) = if is_revert_z {
+          // camera default looking back along -z;
+          (position, target)
+        } else {
+          (target, position)
+        };
+
+        // the basis comes as rows, the rotation of the object takes them as columns
+        let orthogonal_basis =
+          crate::math::Mat3::get_orthogonal_basis(eye, target, *crate::math::Vec3::y_axis())
+            .transpose();
+
+        let mut rotate_mat = crate::math::Mat4::identity();
+
+        for i in 0..3 {
+          let col = crate::math::Vec4::from_vec3(&orthogonal_basis.get_col(i), 0.0);
+          rotate_mat.set_col(i, col);
+        }
+
+        let mut q: crate::math::Quaternion = rotate_mat.into();
+
+        if let Some(parent) = self.parent() {
+          let (_, r, _) = crate::math::decompose(parent.global_matrix());
+
+          let q_parent: crate::math::Quaternion = r.into();
+
+          q = q_parent.inverse() * q;
+        }
+
+        {
+          let mut rotate_ref = self.rotation.borrow_mut();
+          rotate_ref.set_quaternion(q);
+        }
+      }
+
+      fn matrix(&self) -> crate::math::Mat4 {
+        *self.matrix.borrow()
+      }
+
+      fn global_matrix(&self) -> crate::math::Mat4 {
+        *self.global_matrix.borrow()
+      }
+
+      fn propagate_global_matrix(&self, force: bool) {
+        self.update_matrix();
+
+        let dirty = force || *self.global_matrix_dirty.borrow();
+        if dirty {
+          // parents are updated before their children, so the parent's global matrix is current
+          if self.matrix_world_auto_update() {
+            let next_global = match self.parent() {
+              Some(parent) => parent.global_matrix() * self.matrix(),
+              None => self.matrix(),
+            };
+            *self.global_matrix.borrow_mut() = next_global;
+          }
+          *self.global_matrix_dirty.borrow_mut() = false;
+        }
+
+        for child in std::ops::Deref::deref(&self.children.borrow()) {
+          child.propagate_global_matrix(dirty);
+        }
+      }
+
+      fn sync_global_matrix(&self, update_parents: bool, update_children: bool) {
+        if update_parents {
+          if let Some(parent) = self.parent() {
+            parent.sync_global_matrix(true, false);
+          }
+        }
+
+        self.update_matrix();
+
+        if self.matrix_world_auto_update() {
+          let next_global = match self.parent() {
+            Some(parent) => parent.global_matrix() * self.matrix(),
+            None => self.matrix(),
+          };
+          *self.global_matrix.borrow_mut() = next_global;
+        }
+        *self.global_matrix_dirty.borrow_mut() = false;
+
+        if update_children {
+          for child in std::ops::Deref::deref(&self.children.borrow()) {
+            child.propagate_global_matrix(true);
+          }
+        }
+      }
+
+      fn matrix_world_auto_update(&self) -> bool {
+        *self.matrix_world_auto_update.borrow()
+      }
+
+      fn set_matrix_world_auto_update(&self, val: bool) {
+        *self.matrix_world_auto_update.borrow_mut() = val;
+      }
+
+      fn mark_global_matrix_dirty(&self) {
+        *self.global_matrix_dirty.borrow_mut() = true;
+      }
+
+      fn update_matrix(&self) {
+        let next_matrix = self.compose();
+        let mut matrix = self.matrix.borrow_mut();
+        if *matrix != next_matrix {
+          *matrix = next_matrix;
+          *self.global_matrix_dirty.borrow_mut() = true;
+        }
+      }
+
+      fn compose(&self) -> crate::math::Mat4 {
+        let translate_matrix = crate::math::apply_translate(&self.position.borrow());
+        let rotate_matrix = (*self.rotation.borrow()).quaternion.make_rotate_matrix();
+        let scale_matrix = crate::math::apply_scale(&self.scale.borrow());
+
+        translate_matrix * rotate_matrix * scale_matrix
+      }
+      /// refer to http://facweb.cs.depaul.edu/andre/gam374/extractingTRS.pdf
+      fn decompose(&self) {
+        let mat = *self.matrix.borrow();
+        let (position, rotate_matrix, scale) = crate::math::decompose(mat);
+
+        {
+          self
+            .rotation
+            .borrow_mut()
+            .update_quaternion_from_matrix(rotate_matrix);
+        }
+
+        {
+          let mut scale_ref = self.scale.borrow_mut();
+          *scale_ref = scale;
+        }
+        {
+          let mut position_ref = self.position.borrow_mut();
+          *position_ref = position;
+        }
+      }
+
+      fn apply_matrix(&self, matrix: crate::math::Mat4) {
+        self.update_matrix();
+        let next_matrix = matrix * self.matrix();
+        *self.matrix.borrow_mut() = next_matrix;
+        *self.global_matrix_dirty.borrow_mut() = true;
+        self.decompose();
+      }
+
+      fn apply_quaternion(&self, q: crate::math::Quaternion) {
+        let next_q = (*self.rotation.borrow()).quaternion * q;
+        let mut rotation_ref = self.rotation.borrow_mut();
+        rotation_ref.set_quaternion(next_q);
+      }
+
+      fn rotate_on_world_axis(&self, axis: crate::math::Vec3, angle: f32) {
+        let mut rotate = self.rotation.borrow_mut();
+        // apply the rotation first(before any rotation occurs)
+        // means the rotate axis is defined in the world coordinate
+        rotate.quaternion_rotate(axis, angle, true)
+      }
+
+      fn rotate_on_axis(&self, axis: crate::math::Vec3, angle: f32) {
+        let mut rotate = self.rotation.borrow_mut();
+        rotate.quaternion_rotate(axis, angle, false)
+      }
+
+      fn rotate_x(&self, angle: f32) {
+        self.rotate_on_axis(*crate::math::Vec3::x_axis(), angle);
+      }
+
+      fn rotate_y(&self, angle: f32) {
+        self.rotate_on_axis(*crate::math::Vec3::y_axis(), angle);
+      }
+
+      fn rotate_z(&self, angle: f32) {
+        self.rotate_on_axis(*crate::math::Vec3::z_axis(), angle);
+      }
+
+      fn translate_on_axis(&self, axis: crate::math::Vec3, distance: f32) {
+        // `axis` is in the object's own space
+        let offset = self.rotation.borrow().quaternion.apply(axis.normalize()) * distance;
+        let mut position = self.position.borrow_mut();
+        *position = *position + offset;
+      }
+
+      fn translate_x(&self, distance: f32) {
+        self.translate_on_axis(*crate::math::Vec3::x_axis(), distance);
+      }
+
+      fn translate_y(&self, distance: f32) {
+        self.translate_on_axis(*crate::math::Vec3::y_axis(), distance);
+      }
+
+      fn translate_z(&self, distance: f32) {
+        self.translate_on_axis(*crate::math::Vec3::z_axis(), distance);
+      }
+
+      fn global_scale(&self) -> crate::math::Vec3 {
+        self.sync_global_matrix(true, false);
+        let mat = self.global_matrix();
+        let (_, _, scale) = crate::math::decompose(mat);
+        scale
+      }
+
+      fn global_position(&self) -> crate::math::Vec3 {
+        self.sync_global_matrix(true, false);
+        let mat = self.global_matrix();
+        let (position, _, _) = crate::math::decompose(mat);
+        position
+      }
+
+      fn global_rotation(&self) -> crate::math::Rotation {
+        self.sync_global_matrix(true, false);
+        let mat = self.global_matrix();
+        let (_, rotation, _) = crate::math::decompose(mat);
+
+        rotation.into()
+      }
+
+      fn test_layers(&self, layers: &crate::core::layer::Layers) -> bool {
+        self.layers.borrow().test(layers)
+      }
+
+      fn layers(&self) -> std::cell::Ref<crate::core::layer::Layers> {
+        self.layers.borrow()
+      }
+
+      fn visible(&self) -> bool {
+        *self.visible.borrow()
+      }
+
+      fn set_visible(&self, val: bool) {
+        *self.visible.borrow_mut() = val;
+      }
+
+      fn position(&self) -> crate::math::Vec3 {
+        *self.position.borrow()
+      }
+
+      fn set_position(&self, position: crate::math::Vec3) {
+        *self.position.borrow_mut() = position;
+      }
+
+      fn quaternion(&self) -> crate::math::Quaternion {
+        self.rotation.borrow().quaternion
+      }
+
+      fn set_quaternion(&self, q: crate::math::Quaternion) {
+        self.rotation.borrow_mut().set_quaternion(q);
+      }
+
+      fn scale(&self) -> crate::math::Vec3 {
+        *self.scale.borrow()
+      }
+
+      fn set_scale(&self, scale: crate::math::Vec3) {
+        *self.scale.borrow_mut() = scale;
+      }
+
+      fn get_type(&self) -> crate::core::object_3d::ObjectType {
+        self.object_type
+      }
+
+      fn uuid(&self) -> &str {
+        &self._uuid
+      }
+
+      fn cast_shadow(&self) -> bool {
+        *self.cast_shadow.borrow()
+      }
+
+      fn set_cast_shadow(&self, val: bool) {
+        *self.cast_shadow.borrow_mut() = val;
+      }
+
+      fn receive_shadow(&self) -> bool {
+        *self.receive_shadow.borrow()
+      }
+
+      fn set_receive_shadow(&self, val: bool) {
+        *self.receive_shadow.borrow_mut() = val;
+      }
+
+      fn as_any(&self) -> &dyn std::any::Any {
+        self
+      }
+
+      fn name(&self) -> String {
+        self.name.borrow().clone()
+      }
+
+      fn set_name(&self, name: &str) {
+        *self.name.borrow_mut() = name.to_string();
+      }
+
+      fn self_ref(&self) -> Option<std::rc::Rc<dyn crate::core::object_3d::ObjectActions>> {
+        self._self_ref.get().and_then(|p| p.upgrade())
+      }
+
+      fn add_event_listener(
+        &self,
+        event_type: crate::core::event_dispatcher::ObjectEventType,
+        listener: Box<crate::core::event_dispatcher::EventListener>,
+      ) -> usize {
+        self.events.add(event_type, listener)
+      }
+
+      fn remove_event_listener(&self, id: usize) {
+        self.events.remove(id)
+      }
+
+      fn dispatch_event(&self, event: &crate::core::event_dispatcher::ObjectEvent) {
+        self.events.dispatch(event)
+      }
+
+      fn set_on_before_render(&self, callback: Option<Box<crate::core::object_3d::RenderCallback>>) {
+        *self.before_render.borrow_mut() = callback.map(std::rc::Rc::from);
+      }
+
+      fn set_on_after_render(&self, callback: Option<Box<crate::core::object_3d::RenderCallback>>) {
+        *self.after_render.borrow_mut() = callback.map(std::rc::Rc::from);
+      }
+
+      fn on_before_render(
+        &self,
+        renderer: &mut crate::renderer::gl_renderer::GlRenderer,
+        scene: &crate::objects::scene::Scene,
+        camera: &dyn crate::cameras::camera::Camera,
+        geometry: &dyn crate::core::buffer_geometry::GeometryActions,
+        material: &dyn crate::material::material::MaterialActions,
+      ) {
+        // the callback may replace itself
+        let callback = self.before_render.borrow().clone();
+        if let Some(callback) = callback {
+          callback(renderer, scene, camera, geometry, material);
+        }
+      }
+
+      fn on_after_render(
+        &self,
+        renderer: &mut crate::renderer::gl_renderer::GlRenderer,
+        scene: &crate::objects::scene::Scene,
+        camera: &dyn crate::cameras::camera::Camera,
+        geometry: &dyn crate::core::buffer_geometry::GeometryActions,
+        material: &dyn crate::material::material::MaterialActions,
+      ) {
+        let callback = self.after_render.borrow().clone();
+        if let Some(callback) = callback {
+          callback(renderer, scene, camera, geometry, material);
+        }
+      }
+
+      fn clone_object(&self) -> std::rc::Rc<dyn crate::core::object_3d::ObjectActions> {
+        let copy = crate::core::object_3d::CloneFields::clone_fields(self);
+
+        *copy.position.borrow_mut() = *self.position.borrow();
+        *copy.rotation.borrow_mut() = self.rotation.borrow().clone();
+        *copy.scale.borrow_mut() = *self.scale.borrow();
+        *copy.layers.borrow_mut() = self.layers.borrow().clone();
+        *copy.visible.borrow_mut() = *self.visible.borrow();
+        *copy.cast_shadow.borrow_mut() = *self.cast_shadow.borrow();
+        *copy.receive_shadow.borrow_mut() = *self.receive_shadow.borrow();
+        *copy.matrix_world_auto_update.borrow_mut() = *self.matrix_world_auto_update.borrow();
+        *copy.name.borrow_mut() = self.name.borrow().clone();
+
+        copy
+      }
+
+    }
+  };
+}
+
 pub(crate) use define_support_objects;
+pub(crate) use impl_object_actions;
 pub(crate) use with_default_fields;
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+  };
+
+  use renderer_macro_derive::object_3d;
+
+  use super::{ObjectActions, ObjectType};
+  use crate::{
+    core::event_dispatcher::ObjectEventType,
+    math::{apply_scale, extract_position, Vec3},
+    objects::{group::Group, scene::Scene},
+  };
+
+  #[object_3d(ObjectActions, Object3D)]
+  struct Tagged {
+    tag: Cell<u32>,
+  }
+
+  fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!(
+      (actual - expected).length() < 1e-4,
+      "{actual:?} != {expected:?}"
+    );
+  }
+
+  #[test]
+  fn declared_objects_start_at_the_origin() {
+    let tagged = Tagged::with_fields(Cell::new(7));
+
+    assert_eq!(tagged.tag.get(), 7);
+    assert_eq!(tagged.get_type(), ObjectType::Object3D);
+    assert_eq!(*tagged.scale.borrow(), Vec3::new(1.0, 1.0, 1.0));
+    assert!(tagged.visible());
+    assert!(tagged.parent().is_none());
+    assert_eq!(Group::new().get_type(), ObjectType::Group);
+  }
+
+  #[test]
+  fn self_ref_points_at_the_object() {
+    let tagged = Tagged::with_fields(Cell::new(3));
+    let me = tagged.self_ref().unwrap();
+    assert_eq!(me.uuid(), tagged.uuid());
+
+    let copy = tagged.clone_object();
+    assert_eq!(copy.self_ref().unwrap().uuid(), copy.uuid());
+    let copied = copy.as_any().downcast_ref::<Tagged>().unwrap();
+    assert_eq!(copied.tag.get(), 3);
+
+    let weak = Rc::downgrade(&tagged);
+    drop((me, tagged));
+    assert!(weak.upgrade().is_none());
+  }
+
+  #[test]
+  fn update_global_matrix_reaches_moved_subtrees() {
+    let root = Group::new();
+    let parent = Group::new();
+    let child = Group::new();
+    parent.add(child.clone());
+    root.add(parent.clone());
+
+    parent.translate_x(1.0);
+    child.translate_y(2.0);
+    root.update_global_matrix();
+    assert_near(
+      extract_position(child.global_matrix()),
+      Vec3::new(1.0, 2.0, 0.0),
+    );
+
+    parent.translate_x(2.0);
+    root.update_global_matrix();
+    assert_near(
+      extract_position(child.global_matrix()),
+      Vec3::new(3.0, 2.0, 0.0),
+    );
+  }
+
+  #[test]
+  fn frozen_objects_keep_their_global_matrix() {
+    let root = Group::new();
+    let group = Group::new();
+    root.add(group.clone());
+    root.update_global_matrix();
+
+    group.set_matrix_world_auto_update(false);
+    group.translate_x(1.0);
+    root.update_global_matrix();
+    assert_near(extract_position(group.global_matrix()), Vec3::default());
+  }
+
+  #[test]
+  fn attach_keeps_the_world_transform() {
+    let root = Group::new();
+    let parent = Group::new();
+    let child = Group::new();
+    parent.apply_matrix(apply_scale(&Vec3::new(2.0, 2.0, 2.0)));
+    parent.translate_x(1.0);
+    child.translate_y(3.0);
+    root.add(parent.clone());
+    root.add(child.clone());
+
+    parent.attach(child.clone());
+
+    assert_eq!(child.parent().unwrap().uuid(), parent.uuid());
+    assert_near(extract_position(child.compose()), Vec3::new(-0.5, 1.5, 0.0));
+    assert_near(child.global_position(), Vec3::new(0.0, 3.0, 0.0));
+  }
+
+  #[test]
+  fn local_and_world_points_round_trip() {
+    let group = Group::new();
+    group.apply_matrix(apply_scale(&Vec3::new(2.0, 2.0, 2.0)));
+    group.translate_x(1.0);
+    group.translate_y(2.0);
+    group.translate_z(3.0);
+
+    let world = group.local_to_world(Vec3::new(1.0, 0.0, 0.0));
+    assert_near(world, Vec3::new(3.0, 2.0, 3.0));
+    assert_near(group.world_to_local(world).unwrap(), Vec3::new(1.0, 0.0, 0.0));
+  }
+
+  #[test]
+  fn zero_scale_parents_leave_points_and_children_alone() {
+    let parent = Group::new();
+    let child = Group::new();
+    parent.set_scale(Vec3::zero());
+    child.set_position(Vec3::new(0.0, 3.0, 0.0));
+
+    assert!(parent.world_to_local(Vec3::new(1.0, 0.0, 0.0)).is_none());
+
+    parent.attach(child.clone());
+    assert_eq!(child.parent().unwrap().uuid(), parent.uuid());
+    assert_eq!(child.position(), Vec3::new(0.0, 3.0, 0.0));
+  }
+
+  fn named_tree() -> (Rc<Scene>, Rc<Tagged>) {
+    let scene = Scene::new();
+    let a = Tagged::with_fields(Cell::new(0));
+    let b = Group::new();
+    let c = Group::new();
+    scene.set_name("scene");
+    a.set_name("a");
+    b.set_name("b");
+    c.set_name("c");
+    a.add(b.clone());
+    scene.add(a.clone());
+    scene.add(c);
+    (scene, a)
+  }
+
+  fn names(objects: &[Rc<dyn ObjectActions>]) -> Vec<String> {
+    objects.iter().map(|o| o.name()).collect()
+  }
+
+  #[test]
+  fn traverse_visits_parents_before_children() {
+    let (scene, a) = named_tree();
+
+    let mut visited = vec![];
+    scene.traverse(&mut |o| visited.push(o.clone()));
+    assert_eq!(names(&visited), ["scene", "a", "b", "c"]);
+
+    *a.visible.borrow_mut() = false;
+    let mut visible = vec![];
+    scene.traverse_visible(&mut |o| visible.push(o.clone()));
+    assert_eq!(names(&visible), ["scene", "c"]);
+
+    let b = scene.get_object_by_name("b").unwrap();
+    let mut ancestors = vec![];
+    b.traverse_ancestors(&mut |o| ancestors.push(o.clone()));
+    assert_eq!(names(&ancestors), ["a", "scene"]);
+  }
+
+  #[test]
+  fn lookups_search_the_subtree() {
+    let (scene, a) = named_tree();
+
+    assert_eq!(scene.get_object_by_name("b").unwrap().name(), "b");
+    assert!(a.get_object_by_name("c").is_none());
+    assert_eq!(scene.get_object_by_uuid(a.uuid()).unwrap().name(), "a");
+
+    let leaves = scene.find_all(&|o| o.children().is_empty());
+    assert_eq!(names(&leaves), ["b", "c"]);
+  }
+
+  #[test]
+  fn deep_clone_copies_the_subtree_with_fresh_uuids() {
+    let (_scene, a) = named_tree();
+    a.translate_x(1.0);
+    a.translate_y(2.0);
+    a.translate_z(3.0);
+
+    let copy = a.deep_clone();
+    assert_ne!(copy.uuid(), a.uuid());
+    assert!(copy.parent().is_none());
+    assert_eq!(copy.name(), "a");
+    assert_near(extract_position(copy.compose()), Vec3::new(1.0, 2.0, 3.0));
+
+    let copied_b = copy.get_object_by_name("b").unwrap();
+    assert_ne!(copied_b.uuid(), a.children()[0].uuid());
+    assert_eq!(copied_b.parent().unwrap().uuid(), copy.uuid());
+
+    assert!(a.clone_object().children().is_empty());
+  }
+
+  #[test]
+  fn children_see_their_parent() {
+    let scene = Scene::new();
+    let child = Group::new();
+    scene.add(child.clone());
+
+    assert_eq!(
+      child.parent().map(|p| p.uuid().to_string()),
+      Some(scene.uuid().to_string())
+    );
+
+    child.remove_from_parent();
+    assert!(child.parent().is_none());
+    assert!(scene.children().is_empty());
+  }
+
+  #[test]
+  fn dropping_the_scene_frees_the_hierarchy() {
+    let scene = Scene::new();
+    let group = Group::new();
+    let child = Group::new();
+    group.add(child.clone());
+    scene.add(group.clone());
+
+    let scene_ref = Rc::downgrade(&scene);
+    let child_ref = Rc::downgrade(&child);
+    drop((group, child));
+    drop(scene);
+
+    assert!(scene_ref.upgrade().is_none());
+    assert!(child_ref.upgrade().is_none());
+  }
+
+  #[test]
+  fn hierarchy_changes_dispatch_events() {
+    let scene = Scene::new();
+    let child = Group::new();
+    let log = Rc::new(RefCell::new(vec![]));
+
+    for event_type in [ObjectEventType::ChildAdded, ObjectEventType::ChildRemoved] {
+      let log = log.clone();
+      scene.add_event_listener(
+        event_type,
+        Box::new(move |e| log.borrow_mut().push((e.event_type, e.child.is_some()))),
+      );
+    }
+    let added = {
+      let log = log.clone();
+      child.add_event_listener(
+        ObjectEventType::Added,
+        Box::new(move |e| log.borrow_mut().push((e.event_type, e.child.is_some()))),
+      )
+    };
+
+    scene.add(child.clone());
+    scene.remove(child.uuid());
+    assert_eq!(
+      *log.borrow(),
+      [
+        (ObjectEventType::Added, false),
+        (ObjectEventType::ChildAdded, true),
+        (ObjectEventType::ChildRemoved, true),
+      ]
+    );
+
+    log.borrow_mut().clear();
+    child.remove_event_listener(added);
+    scene.add(child);
+    assert_eq!(*log.borrow(), [(ObjectEventType::ChildAdded, true)]);
+  }
+}
@@ -1,11 +1,16 @@
 use std::{
   collections::HashMap,
   ops::{Deref, DerefMut},
+  rc::Rc,
 };
 
-use crate::math::{Mat4, Vec2, Vec3, Vec4};
+use crate::{
+  lights::light::LightUniform,
+  math::{Mat4, Vec2, Vec3, Vec4},
+  textures::texture::{Texture, TextureHandle},
+};
 
-use super::marco::define_gl_type_enum;
+use super::marco::{define_gl_type_enum, Extract};
 
 trait SetGlType<T> {
   fn set_attribute(&mut self, key: &str, val: T);
@@ -35,11 +40,20 @@ define_uniform_trait!(
   Vec4-Vec4,
   Mat4-Mat4,
   Bool-bool,
-  Uv-u32 // uv's uid
+  Uv-u32, // uv's uid
+  Texture-TextureHandle
 );
 #[derive(Debug, Default)]
 pub struct Uniform {
   attributes: HashMap<String, UnifromTypeEnum>,
+  /// filled by the renderer, the lights affecting the object being drawn
+  lights: Vec<LightUniform>,
+  /// filled by the renderer for skinned meshes, one matrix per bone
+  bone_matrices: Vec<Mat4>,
+  /// filled by the renderer for meshes with morph targets, one weight per target
+  morph_target_influences: Vec<f32>,
+  /// the textures the `Texture` handles among the attributes refer to
+  textures: HashMap<TextureHandle, Rc<Texture>>,
 }
 
 impl Deref for Uniform {
@@ -61,6 +75,42 @@ impl Uniform {
     let res = self.attributes.get(key);
     res.map(|x| *x)
   }
+
+  pub fn lights(&self) -> &Vec<LightUniform> {
+    &self.lights
+  }
+
+  pub fn set_lights(&mut self, lights: Vec<LightUniform>) {
+    self.lights = lights;
+  }
+
+  pub fn bone_matrices(&self) -> &Vec<Mat4> {
+    &self.bone_matrices
+  }
+
+  pub fn set_bone_matrices(&mut self, bone_matrices: Vec<Mat4>) {
+    self.bone_matrices = bone_matrices;
+  }
+
+  pub fn morph_target_influences(&self) -> &Vec<f32> {
+    &self.morph_target_influences
+  }
+
+  pub fn set_morph_target_influences(&mut self, influences: Vec<f32>) {
+    self.morph_target_influences = influences;
+  }
+
+  /// set `key` to the handle of `texture` and keep the texture around for the shaders
+  pub fn bind_texture(&mut self, key: &str, texture: Rc<Texture>) {
+    self.set_attribute(key, texture.handle());
+    self.textures.insert(texture.handle(), texture);
+  }
+
+  /// the texture bound under `key`
+  pub fn texture(&self, key: &str) -> Option<&Texture> {
+    let handle: TextureHandle = self.get(key)?.extract()?;
+    self.textures.get(&handle).map(|texture| texture.as_ref())
+  }
 }
 
 macro_rules! u {
@@ -5,7 +5,45 @@ use std::{
 
 use crate::math::{Barycentric, Mat4, Vec2, Vec3, Vec4};
 
-trait DeclareGlType<T> {
+pub use renderer_macro_derive::Varyings;
+
+/// A value the rasterizer can interpolate across a triangle,
+/// `weights` are perspective correct and sum to 1.0.
+pub trait Interpolate: Copy {
+  fn interpolate(values: [Self; 3], weights: [f32; 3]) -> Self;
+}
+
+macro_rules! impl_interpolate {
+  ($($type:ty),+) => {
+    $(
+      impl Interpolate for $type {
+        fn interpolate(values: [Self; 3], weights: [f32; 3]) -> Self {
+          values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+        }
+      }
+    )+
+  };
+}
+
+impl_interpolate!(f32, Vec2, Vec3, Vec4, Mat4);
+
+/// integers aren't interpolated, like `flat` in GLSL they come from the first vertex
+impl Interpolate for i32 {
+  fn interpolate(values: [Self; 3], _: [f32; 3]) -> Self {
+    values[0]
+  }
+}
+
+/// A struct of varyings passed from the vertex to the fragment shader,
+/// usually implemented with `#[derive(Varyings)]`.
+pub trait Varyings {
+  /// blend the three vertices with perspective correct `weights`
+  fn lerp(vertices: &[Self; 3], weights: [f32; 3]) -> Self
+  where
+    Self: Sized;
+}
+
+pub trait DeclareGlType<T> {
   fn declare_attribute(&mut self, key: &str, val: T);
 }
 
@@ -80,21 +118,29 @@ pub struct Varying {
 
 impl Varying {
   pub fn lerp(&mut self, bary: &Barycentric, rhws: [f32; 3], z: f32) {
-    for key in self.declare.keys() {
-      let vec = self.declare.get(key).unwrap();
-      let length = vec.len();
-
-      match length {
-        1 => {
-          let val = vec[0];
-          self.result.insert(key.to_string(), val);
-        }
-        3 => {
-          let arr = [vec[0] * rhws[0], vec[1] * rhws[1], vec[2] * rhws[2]];
-          let lerped_val = bary.apply_weight(&arr) * z;
-          self.result.insert(key.to_string(), lerped_val);
-        }
+    self.interpolate([
+      bary.alpha() * rhws[0] * z,
+      bary.beta() * rhws[1] * z,
+      bary.gamma() * rhws[2] * z,
+    ]);
+  }
+
+  /// interpolate with perspective correct `weights`, reusing the keys of the last fragment
+  pub fn interpolate(&mut self, weights: [f32; 3]) {
+    for (key, vec) in &self.declare {
+      let val = match vec.len() {
+        1 => vec[0],
+        // the two ends of a line
+        2 => vec[0] * weights[0] + vec[1] * weights[1],
+        3 => vec[0] * weights[0] + vec[1] * weights[1] + vec[2] * weights[2],
         _ => continue,
+      };
+
+      match self.result.get_mut(key) {
+        Some(result) => *result = val,
+        None => {
+          self.result.insert(key.clone(), val);
+        }
       }
     }
   }
@@ -105,7 +151,7 @@ impl Varying {
 
 macro_rules! v {
   ($store:ident,$type:ty,$key:tt,!) => {
-    crate::next::core::Extract::<$type>::extract(
+    crate::core::Extract::<$type>::extract(
       ($store
         .get($key)
         .expect(&format!("error from getting {} from varyings", $key))),
@@ -134,3 +180,46 @@ macro_rules! add_v {
 
 pub(crate) use add_v;
 pub(crate) use v;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Varyings, Clone, Copy, Debug, PartialEq)]
+  struct TestVaryings {
+    weight: f32,
+    uv: Vec2,
+    normal: Vec3,
+    index: i32,
+    #[flat]
+    color: Vec4,
+  }
+
+  fn vertex(i: usize) -> TestVaryings {
+    let f = i as f32;
+    TestVaryings {
+      weight: f,
+      uv: Vec2::new(f, 1.0 - f),
+      normal: Vec3::new(0.0, 0.0, f),
+      index: i as i32 + 10,
+      color: Vec4::new(f, f, f, 1.0),
+    }
+  }
+
+  #[test]
+  fn derived_varyings_blend_with_the_weights() {
+    let result = Varyings::lerp(&[vertex(0), vertex(1), vertex(2)], [0.25, 0.25, 0.5]);
+
+    assert_eq!(result.weight, 1.25);
+    assert_eq!(result.uv, Vec2::new(1.25, -0.25));
+    assert_eq!(result.normal, Vec3::new(0.0, 0.0, 1.25));
+  }
+
+  #[test]
+  fn flat_and_integer_varyings_come_from_the_first_vertex() {
+    let result: TestVaryings = Varyings::lerp(&[vertex(2), vertex(1), vertex(0)], [0.0, 0.5, 0.5]);
+
+    assert_eq!(result.index, 12);
+    assert_eq!(result.color, vertex(2).color);
+  }
+}
@@ -1,3 +1,6 @@
+extern crate self as three;
+
+pub mod animation;
 pub mod cameras;
 pub mod core;
 pub mod lights;
@@ -0,0 +1,270 @@
+use std::fmt::Debug;
+
+use super::light_shadow::{ShadowCamera, ShadowMap, ShadowProjection};
+use crate::{
+  cameras::camera::Camera,
+  math::{lerp, Mat4, Vec3, Vec4},
+};
+
+/// Splits the view frustum into several directional shadow maps,
+/// near cascades cover less ground with the same resolution.
+#[derive(Debug, Clone)]
+pub struct CascadeSettings {
+  pub count: usize,
+  /// 0.0 splits the depth range uniformly, 1.0 logarithmically
+  pub split_lambda: f32,
+  /// shadows end here, 0.0 uses the camera far plane
+  pub max_distance: f32,
+  /// how far towards the light casters outside of a cascade are still rendered
+  pub caster_distance: f32,
+  /// fraction at the far end of every cascade blended into the next one, 0.0 disables it
+  pub blend: f32,
+}
+
+impl Default for CascadeSettings {
+  fn default() -> Self {
+    Self {
+      count: 4,
+      split_lambda: 0.5,
+      max_distance: 0.0,
+      caster_distance: 100.0,
+      blend: 0.1,
+    }
+  }
+}
+
+/// view depths bounding the cascades, `count + 1` values from `near` to `far`
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+  (0..=count)
+    .map(|i| {
+      let t = i as f32 / count as f32;
+      let logarithmic = near * (far / near).powf(t);
+      let uniform = near + (far - near) * t;
+      lambda * logarithmic + (1.0 - lambda) * uniform
+    })
+    .collect()
+}
+
+/// The part of a camera frustum the cascades are fitted to.
+pub struct FrustumSlices {
+  near_corners: [Vec3; 4],
+  far_corners: [Vec3; 4],
+  near: f32,
+  far: f32,
+}
+
+impl FrustumSlices {
+  pub fn new(camera: &dyn Camera) -> Option<Self> {
+    let view_matrix = camera.view_matrix();
+    let inverse = (camera.projection_matrix() * view_matrix).inverse()?;
+    let corner = |x: f32, y: f32, z: f32| {
+      let p = inverse * Vec4::new(x, y, z, 1.0);
+      (p / p.w).truncated_to_vec3()
+    };
+    let ndc = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    let near_corners = ndc.map(|(x, y)| corner(x, y, -1.0));
+    let far_corners = ndc.map(|(x, y)| corner(x, y, 1.0));
+    let depth = |p: Vec3| -(view_matrix * Vec4::from_vec3(&p, 1.0)).z;
+
+    Some(Self {
+      near: depth(near_corners[0]),
+      far: depth(far_corners[0]),
+      near_corners,
+      far_corners,
+    })
+  }
+
+  pub fn near(&self) -> f32 {
+    self.near
+  }
+
+  pub fn far(&self) -> f32 {
+    self.far
+  }
+
+  /// the 8 world space corners between the view depths `from` and `to`
+  pub fn corners(&self, from: f32, to: f32) -> [Vec3; 8] {
+    let range = self.far - self.near;
+    let t0 = (from - self.near) / range;
+    let t1 = (to - self.near) / range;
+    let mut result = [Vec3::zero(); 8];
+
+    for i in 0..4 {
+      result[i] = lerp(self.near_corners[i], self.far_corners[i], t0);
+      result[i + 4] = lerp(self.near_corners[i], self.far_corners[i], t1);
+    }
+
+    result
+  }
+}
+
+/// Fit an orthographic light camera around `corners`.
+/// The box is sized by the bounding sphere so it doesn't change when the camera turns,
+/// and its center is snapped to whole texels so shadow edges don't shimmer when it moves.
+pub fn fit_cascade(
+  corners: &[Vec3; 8],
+  direction: Vec3,
+  map_size: u32,
+  caster_distance: f32,
+) -> ShadowCamera {
+  let center = corners.iter().fold(Vec3::zero(), |sum, c| sum + *c) / 8.0;
+  let radius = corners
+    .iter()
+    .fold(0.0_f32, |max, c| max.max((*c - center).length()));
+  let radius = (radius * 16.0).ceil() / 16.0;
+
+  // rotation only, the translation lives in the projection bounds
+  let mut camera = ShadowCamera::orthographic(radius, 0.0, 1.0);
+  camera.look_at(Vec3::zero(), direction, *Vec3::y_axis());
+
+  let center = camera.view_matrix() * Vec4::from_vec3(&center, 1.0);
+  let texel = radius * 2.0 / map_size.max(1) as f32;
+  let x = (center.x / texel).floor() * texel;
+  let y = (center.y / texel).floor() * texel;
+
+  camera.projection = ShadowProjection::Orthographic {
+    left: x - radius,
+    right: x + radius,
+    bottom: y - radius,
+    top: y + radius,
+  };
+  camera.near = -(center.z + radius + caster_distance);
+  camera.far = -(center.z - radius);
+
+  camera
+}
+
+/// The maps of every cascade and the view depths they cover.
+pub struct CascadedShadowMap {
+  maps: Vec<ShadowMap>,
+  splits: Vec<f32>,
+  view_matrix: Mat4,
+  blend: f32,
+}
+
+impl CascadedShadowMap {
+  /// `splits` holds one more value than `maps`, `view_matrix` is the one of the camera split
+  pub(crate) fn new(maps: Vec<ShadowMap>, splits: Vec<f32>, view_matrix: Mat4, blend: f32) -> Self {
+    Self {
+      maps,
+      splits,
+      view_matrix,
+      blend,
+    }
+  }
+
+  pub fn cascade(&self, index: usize) -> Option<&ShadowMap> {
+    self.maps.get(index)
+  }
+
+  pub fn splits(&self) -> &Vec<f32> {
+    &self.splits
+  }
+
+  /// 1.0 when `position` is lit, 0.0 when it's in shadow
+  pub fn visibility(&self, position: Vec3, normal: Vec3) -> f32 {
+    let depth = -(self.view_matrix * Vec4::from_vec3(&position, 1.0)).z;
+
+    let index = match (0..self.maps.len()).find(|i| depth < self.splits[i + 1]) {
+      Some(index) => index,
+      None => return 1.0,
+    };
+
+    let visibility = self.maps[index].visibility(position, normal);
+
+    if self.blend <= 0.0 || index + 1 >= self.maps.len() {
+      return visibility;
+    }
+
+    let (from, to) = (self.splits[index], self.splits[index + 1]);
+    let band = (to - from) * self.blend;
+    let t = (depth - (to - band)) / band;
+
+    if t <= 0.0 {
+      visibility
+    } else {
+      lerp(visibility, self.maps[index + 1].visibility(position, normal), t)
+    }
+  }
+}
+
+impl Debug for CascadedShadowMap {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("CascadedShadowMap")
+      .field("maps", &self.maps)
+      .field("splits", &self.splits)
+      .field("blend", &self.blend)
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+      assert!((a - e).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+    }
+  }
+
+  #[test]
+  fn uniform_splits() {
+    assert_close(
+      &cascade_splits(1.0, 101.0, 4, 0.0),
+      &[1.0, 26.0, 51.0, 76.0, 101.0],
+    );
+  }
+
+  #[test]
+  fn logarithmic_splits() {
+    assert_close(
+      &cascade_splits(1.0, 1000.0, 3, 1.0),
+      &[1.0, 10.0, 100.0, 1000.0],
+    );
+  }
+
+  #[test]
+  fn blended_splits_lie_between_uniform_and_logarithmic() {
+    let uniform = cascade_splits(0.5, 200.0, 4, 0.0);
+    let logarithmic = cascade_splits(0.5, 200.0, 4, 1.0);
+    let blended = cascade_splits(0.5, 200.0, 4, 0.5);
+
+    for i in 0..blended.len() {
+      assert!(blended[i] <= uniform[i] + 1e-3 && blended[i] >= logarithmic[i] - 1e-3);
+    }
+    assert!(blended.windows(2).all(|w| w[0] < w[1]));
+  }
+
+  #[test]
+  fn fitted_cascade_contains_the_corners() {
+    let corners = [
+      Vec3::new(-1.0, -1.0, -1.0),
+      Vec3::new(1.0, -1.0, -1.0),
+      Vec3::new(1.0, 1.0, -1.0),
+      Vec3::new(-1.0, 1.0, -1.0),
+      Vec3::new(-3.0, -3.0, -8.0),
+      Vec3::new(3.0, -3.0, -8.0),
+      Vec3::new(3.0, 3.0, -8.0),
+      Vec3::new(-3.0, 3.0, -8.0),
+    ];
+    let camera = fit_cascade(&corners, Vec3::new(0.3, -1.0, -0.2), 512, 10.0);
+    let (left, right, bottom, top) = match camera.projection {
+      ShadowProjection::Orthographic {
+        left,
+        right,
+        bottom,
+        top,
+      } => (left, right, bottom, top),
+      _ => panic!("expected an orthographic cascade"),
+    };
+
+    for corner in corners {
+      let p = camera.view_matrix() * Vec4::from_vec3(&corner, 1.0);
+      assert!(p.x >= left && p.x <= right && p.y >= bottom && p.y <= top);
+      assert!(-p.z >= camera.near && -p.z <= camera.far);
+    }
+  }
+}
@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+
+use renderer_macro_derive::object_3d;
+
+use super::{
+  light::{Light, LightActions, LightUniform},
+  light_shadow::{LightShadow, ShadowCamera},
+};
+use crate::{
+  core::object_3d::ObjectActions,
+  math::{Vec3, Vec4},
+};
+
+#[object_3d(ObjectActions, Light, LightActions)]
+pub struct DirectionalLight {
+  light: RefCell<Light>,
+  /// world space point the light shines at
+  target: RefCell<Vec3>,
+  shadow: RefCell<LightShadow>,
+}
+
+impl DirectionalLight {
+  pub fn new(color: Vec4, intensity: f32) -> std::rc::Rc<Self> {
+    let light = RefCell::new(Light { color, intensity });
+    let target = Default::default();
+    let shadow = RefCell::new(LightShadow::new(ShadowCamera::orthographic(5.0, 0.5, 500.0)));
+    Self::with_fields(light, target, shadow)
+  }
+
+  pub fn target(&self) -> Vec3 {
+    *self.target.borrow()
+  }
+
+  pub fn set_target(&self, target: Vec3) {
+    *self.target.borrow_mut() = target;
+  }
+}
+
+impl LightActions for DirectionalLight {
+  fn light(&self) -> std::cell::RefMut<'_, Light> {
+    self.light.borrow_mut()
+  }
+
+  fn shadow(&self) -> Option<std::cell::RefMut<'_, LightShadow>> {
+    Some(self.shadow.borrow_mut())
+  }
+
+  fn update_shadow_camera(&self) {
+    let position = self.world_position();
+    self.shadow.borrow_mut().camera.update(position, self.target());
+  }
+
+  fn to_light_uniform(&self) -> LightUniform {
+    let direction = (self.target() - self.world_position()).normalize();
+    let shadow = self.shadow.borrow();
+    LightUniform::Directional {
+      radiance: self.light.borrow().radiance(),
+      direction,
+      shadow: shadow.map(),
+      cascaded_shadow: shadow.cascaded_map(),
+    }
+  }
+}
@@ -1,6 +1,162 @@
-use crate::math::Vec4;
+use std::rc::Rc;
 
+use super::{
+  cascaded_shadow::CascadedShadowMap,
+  light_shadow::{CubeShadowMap, LightShadow, ShadowMap},
+};
+use crate::{
+  core::object_3d::ObjectActions,
+  math::{Vec3, Vec4},
+};
+
+#[derive(Clone)]
 pub struct Light {
   pub color: Vec4,
   pub intensity: f32,
 }
+
+impl Default for Light {
+  fn default() -> Self {
+    Self {
+      color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+      intensity: 1.0,
+    }
+  }
+}
+
+impl Light {
+  pub fn radiance(&self) -> Vec3 {
+    self.color.truncated_to_vec3() * self.intensity
+  }
+}
+
+pub trait LightActions: ObjectActions {
+  fn light(&self) -> std::cell::RefMut<'_, Light>;
+  /// `None` for lights that can't cast shadows
+  fn shadow(&self) -> Option<std::cell::RefMut<'_, LightShadow>>;
+  /// lights shining in every direction render their shadow into a cube map
+  fn omnidirectional(&self) -> bool {
+    false
+  }
+  fn world_position(&self) -> Vec3 {
+    self.sync_global_matrix(true, false);
+    (self.global_matrix() * Vec4::new(0.0, 0.0, 0.0, 1.0)).truncated_to_vec3()
+  }
+  /// place the shadow camera at the light, looking at its target
+  fn update_shadow_camera(&self);
+  /// the world space light data handed to the shaders
+  fn to_light_uniform(&self) -> LightUniform;
+}
+
+/// A light as seen by the shaders, everything in world space.
+#[derive(Debug, Clone)]
+pub enum LightUniform {
+  Directional {
+    radiance: Vec3,
+    /// from the light towards its target
+    direction: Vec3,
+    shadow: Option<Rc<ShadowMap>>,
+    /// takes over from `shadow` when present
+    cascaded_shadow: Option<Rc<CascadedShadowMap>>,
+  },
+  Spot {
+    radiance: Vec3,
+    position: Vec3,
+    direction: Vec3,
+    /// cosine of the half angle of the cone
+    cone_cos: f32,
+    /// 0.0 means no fall off
+    distance: f32,
+    shadow: Option<Rc<ShadowMap>>,
+  },
+  Point {
+    radiance: Vec3,
+    position: Vec3,
+    /// 0.0 means no fall off
+    distance: f32,
+    shadow: Option<Rc<CubeShadowMap>>,
+  },
+}
+
+impl LightUniform {
+  /// the unit vector from `position` towards the light and the radiance arriving there
+  pub fn incident(&self, position: Vec3) -> (Vec3, Vec3) {
+    match self {
+      Self::Directional {
+        radiance,
+        direction,
+        ..
+      } => (direction.normalize() * -1.0, *radiance),
+      Self::Spot {
+        radiance,
+        position: light_position,
+        direction,
+        cone_cos,
+        distance,
+        ..
+      } => {
+        let to_light = *light_position - position;
+        let length = to_light.length();
+        let l = to_light / length;
+
+        if (l * -1.0).dot(&direction.normalize()) < *cone_cos {
+          return (l, Vec3::zero());
+        }
+
+        (l, *radiance * fall_off(length, *distance))
+      }
+      Self::Point {
+        radiance,
+        position: light_position,
+        distance,
+        ..
+      } => {
+        let to_light = *light_position - position;
+        let length = to_light.length();
+
+        (to_light / length, *radiance * fall_off(length, *distance))
+      }
+    }
+  }
+
+  /// shadow visibility of `position`, 1.0 without a shadow map
+  pub fn visibility(&self, position: Vec3, normal: Vec3) -> f32 {
+    match self {
+      Self::Directional {
+        cascaded_shadow: Some(map),
+        ..
+      } => map.visibility(position, normal),
+      Self::Directional { shadow, .. } | Self::Spot { shadow, .. } => shadow
+        .as_ref()
+        .map_or(1.0, |map| map.visibility(position, normal)),
+      Self::Point { shadow, .. } => shadow
+        .as_ref()
+        .map_or(1.0, |map| map.visibility(position, normal)),
+    }
+  }
+
+  pub fn without_shadow(mut self) -> Self {
+    match &mut self {
+      Self::Directional {
+        shadow,
+        cascaded_shadow,
+        ..
+      } => {
+        *shadow = None;
+        *cascaded_shadow = None;
+      }
+      Self::Spot { shadow, .. } => *shadow = None,
+      Self::Point { shadow, .. } => *shadow = None,
+    }
+    self
+  }
+}
+
+/// linear fall off reaching zero at `distance`, none when `distance` is 0.0
+fn fall_off(length: f32, distance: f32) -> f32 {
+  if distance > 0.0 {
+    (1.0 - length / distance).max(0.0)
+  } else {
+    1.0
+  }
+}
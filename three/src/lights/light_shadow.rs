@@ -0,0 +1,389 @@
+use std::fmt::Debug;
+
+use super::cascaded_shadow::{CascadeSettings, CascadedShadowMap};
+use crate::{
+  cameras::camera::Camera,
+  math::{data_array::DepthBuffer, look_at, orthographic, perspective, Mat4, Vec3, Vec4},
+};
+
+#[derive(Clone)]
+pub enum ShadowProjection {
+  Orthographic {
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+  },
+  /// `fov` is the vertical field of view in radians
+  Perspective { fov: f32, aspect: f32 },
+}
+
+/// The camera a shadow map is rendered from, placed at the light.
+#[derive(Clone)]
+pub struct ShadowCamera {
+  pub projection: ShadowProjection,
+  pub near: f32,
+  pub far: f32,
+  view_matrix: Mat4,
+}
+
+impl ShadowCamera {
+  pub fn orthographic(half_size: f32, near: f32, far: f32) -> Self {
+    Self {
+      projection: ShadowProjection::Orthographic {
+        left: -half_size,
+        right: half_size,
+        bottom: -half_size,
+        top: half_size,
+      },
+      near,
+      far,
+      view_matrix: Mat4::identity(),
+    }
+  }
+
+  pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Self {
+    Self {
+      projection: ShadowProjection::Perspective { fov, aspect },
+      near,
+      far,
+      view_matrix: Mat4::identity(),
+    }
+  }
+
+  pub fn update(&mut self, position: Vec3, target: Vec3) {
+    self.look_at(position, target, *Vec3::y_axis());
+  }
+
+  pub fn look_at(&mut self, position: Vec3, target: Vec3, up: Vec3) {
+    self.view_matrix = look_at(position, target, up);
+  }
+}
+
+impl Camera for ShadowCamera {
+  fn view_matrix(&self) -> Mat4 {
+    self.view_matrix
+  }
+
+  fn projection_matrix(&self) -> Mat4 {
+    match self.projection {
+      ShadowProjection::Orthographic {
+        left,
+        right,
+        bottom,
+        top,
+      } => orthographic(left, right, bottom, top, self.near, self.far),
+      ShadowProjection::Perspective { fov, aspect } => {
+        perspective(fov, aspect, self.near, self.far)
+      }
+    }
+  }
+
+  fn global_matrix_inverse(&self) -> Mat4 {
+    self.view_matrix
+  }
+}
+
+/// Shadow settings of a single light.
+#[derive(Clone)]
+pub struct LightShadow {
+  /// added to the depth of the shaded point before the comparison,
+  /// a small negative value fights shadow acne
+  pub bias: f32,
+  /// offsets the shaded point along its normal in world units
+  pub normal_bias: f32,
+  /// resolution of the shadow map of this light
+  pub map_size: (u32, u32),
+  pub camera: ShadowCamera,
+  /// split the view camera frustum into cascades, only used by directional lights
+  pub cascade: Option<CascadeSettings>,
+  pub(crate) map: Option<std::rc::Rc<ShadowMap>>,
+  pub(crate) cube_map: Option<std::rc::Rc<CubeShadowMap>>,
+  pub(crate) cascaded_map: Option<std::rc::Rc<CascadedShadowMap>>,
+}
+
+impl LightShadow {
+  pub fn new(camera: ShadowCamera) -> Self {
+    Self {
+      bias: 0.0,
+      normal_bias: 0.0,
+      map_size: (512, 512),
+      camera,
+      cascade: None,
+      map: None,
+      cube_map: None,
+      cascaded_map: None,
+    }
+  }
+
+  /// the map produced by the last shadow pass
+  pub fn map(&self) -> Option<std::rc::Rc<ShadowMap>> {
+    self.map.clone()
+  }
+
+  /// the cube map produced by the last shadow pass of an omnidirectional light
+  pub fn cube_map(&self) -> Option<std::rc::Rc<CubeShadowMap>> {
+    self.cube_map.clone()
+  }
+
+  /// the cascades produced by the last shadow pass when `cascade` is set
+  pub fn cascaded_map(&self) -> Option<std::rc::Rc<CascadedShadowMap>> {
+    self.cascaded_map.clone()
+  }
+}
+
+/// look direction and up vector of the six cube faces, in +x, -x, +y, -y, +z, -z order
+pub const CUBE_FACES: [(Vec3, Vec3); 6] = [
+  (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+  (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+  (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+  (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+  (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0)),
+  (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, -1.0, 0.0)),
+];
+
+/// maps NDC to shadow map coordinates, same convention as the viewport:
+/// x to [0, 1] from left, y to [0, 1] from top and z to [0, 1]
+#[rustfmt::skip]
+fn shadow_bias_matrix() -> Mat4 {
+  Mat4::from_row(&[
+    0.5, 0.0 , 0.0, 0.5,
+    0.0, -0.5, 0.0, 0.5,
+    0.0, 0.0 , 0.5, 0.5,
+    0.0, 0.0 , 0.0, 1.0,
+  ])
+}
+
+/// The depth rendered from a light, ready to be sampled by lit materials.
+pub struct ShadowMap {
+  depth: DepthBuffer,
+  matrix: Mat4,
+  bias: f32,
+  normal_bias: f32,
+}
+
+impl ShadowMap {
+  pub(crate) fn new(depth: DepthBuffer, camera: &ShadowCamera, bias: f32, normal_bias: f32) -> Self {
+    Self {
+      depth,
+      matrix: shadow_bias_matrix() * camera.projection_matrix() * camera.view_matrix(),
+      bias,
+      normal_bias,
+    }
+  }
+
+  /// world space to shadow map coordinates
+  pub fn matrix(&self) -> Mat4 {
+    self.matrix
+  }
+
+  pub fn depth(&self) -> &DepthBuffer {
+    &self.depth
+  }
+
+  /// 1.0 when `position` is lit, 0.0 when it's in shadow
+  pub fn visibility(&self, position: Vec3, normal: Vec3) -> f32 {
+    let position = position + normal.normalize() * self.normal_bias;
+    let coord = self.matrix * Vec4::from_vec3(&position, 1.0);
+    if coord.w <= 0.0 {
+      return 1.0;
+    }
+    let coord = coord / coord.w;
+
+    // outside of the light frustum nothing casts shadows
+    if coord.z > 1.0 {
+      return 1.0;
+    }
+
+    match sample_depth(&self.depth, coord.x, coord.y) {
+      Some(closest) if coord.z + self.bias > closest => 0.0,
+      _ => 1.0,
+    }
+  }
+}
+
+/// the depth stored at the normalized coordinate, `None` outside of the map
+fn sample_depth(depth: &DepthBuffer, u: f32, v: f32) -> Option<f32> {
+  let w = depth.width();
+  let h = depth.height();
+  if w == 0 || h == 0 || u < 0.0 || u > 1.0 || v < 0.0 || v > 1.0 {
+    return None;
+  }
+
+  let x = ((u * w as f32) as u32).min(w - 1);
+  let y = ((v * h as f32) as u32).min(h - 1);
+  Some(depth.data()[(x + y * w) as usize])
+}
+
+/// Six depth faces around an omnidirectional light, storing the linear distance
+/// to the light remapped from [near, far] to [0, 1].
+pub struct CubeShadowMap {
+  faces: Vec<(DepthBuffer, Mat4)>,
+  position: Vec3,
+  near: f32,
+  far: f32,
+  bias: f32,
+  normal_bias: f32,
+}
+
+impl CubeShadowMap {
+  /// `faces` in `CUBE_FACES` order, each paired with the view projection it was rendered with
+  pub(crate) fn new(
+    faces: Vec<(DepthBuffer, Mat4)>,
+    position: Vec3,
+    camera: &ShadowCamera,
+    bias: f32,
+    normal_bias: f32,
+  ) -> Self {
+    Self {
+      faces: faces
+        .into_iter()
+        .map(|(depth, view_projection)| (depth, shadow_bias_matrix() * view_projection))
+        .collect(),
+      position,
+      near: camera.near,
+      far: camera.far,
+      bias,
+      normal_bias,
+    }
+  }
+
+  pub fn face(&self, index: usize) -> Option<&DepthBuffer> {
+    self.faces.get(index).map(|(depth, _)| depth)
+  }
+
+  /// the face the direction from the light points into
+  fn face_index(direction: Vec3) -> usize {
+    let Vec3 { x, y, z } = direction;
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+    if ax >= ay && ax >= az {
+      if x > 0.0 {
+        0
+      } else {
+        1
+      }
+    } else if ay >= az {
+      if y > 0.0 {
+        2
+      } else {
+        3
+      }
+    } else if z > 0.0 {
+      4
+    } else {
+      5
+    }
+  }
+
+  /// 1.0 when `position` is lit, 0.0 when it's in shadow
+  pub fn visibility(&self, position: Vec3, normal: Vec3) -> f32 {
+    let position = position + normal.normalize() * self.normal_bias;
+    let direction = position - self.position;
+    let distance = (direction.length() - self.near) / (self.far - self.near);
+
+    if distance > 1.0 {
+      return 1.0;
+    }
+
+    let (depth, matrix) = match self.faces.get(Self::face_index(direction)) {
+      Some(face) => face,
+      None => return 1.0,
+    };
+
+    let coord = *matrix * Vec4::from_vec3(&position, 1.0);
+    if coord.w <= 0.0 {
+      return 1.0;
+    }
+    let coord = coord / coord.w;
+
+    match sample_depth(depth, coord.x, coord.y) {
+      Some(closest) if distance + self.bias > closest => 0.0,
+      _ => 1.0,
+    }
+  }
+}
+
+impl Debug for CubeShadowMap {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("CubeShadowMap")
+      .field("faces", &self.faces.len())
+      .field("position", &self.position)
+      .field("near", &self.near)
+      .field("far", &self.far)
+      .field("bias", &self.bias)
+      .field("normal_bias", &self.normal_bias)
+      .finish()
+  }
+}
+
+impl Debug for ShadowMap {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ShadowMap")
+      .field("size", &(self.depth.width(), self.depth.height()))
+      .field("matrix", &self.matrix)
+      .field("bias", &self.bias)
+      .field("normal_bias", &self.normal_bias)
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn filled_depth(value: f32) -> DepthBuffer {
+    let mut depth = DepthBuffer::new(4, 4);
+    depth.clear(value);
+    depth
+  }
+
+  #[test]
+  fn cube_faces_are_picked_by_the_major_axis() {
+    for (index, (direction, _)) in CUBE_FACES.iter().enumerate() {
+      assert_eq!(CubeShadowMap::face_index(*direction), index);
+    }
+    assert_eq!(CubeShadowMap::face_index(Vec3::new(0.2, -0.9, 0.5)), 3);
+  }
+
+  #[test]
+  fn shadow_map_shades_points_behind_the_occluder() {
+    let mut camera = ShadowCamera::orthographic(2.0, 0.5, 10.5);
+    camera.update(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+    let occluder = {
+      let matrix = shadow_bias_matrix() * camera.projection_matrix() * camera.view_matrix();
+      (matrix * Vec4::new(0.0, 0.0, -3.0, 1.0)).z
+    };
+    let map = ShadowMap::new(filled_depth(occluder), &camera, 0.0, 0.0);
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    assert_eq!(map.visibility(Vec3::new(0.0, 0.0, -6.0), normal), 0.0);
+    assert_eq!(map.visibility(Vec3::new(0.0, 0.0, -2.0), normal), 1.0);
+    // outside of the light frustum
+    assert_eq!(map.visibility(Vec3::new(5.0, 0.0, -6.0), normal), 1.0);
+    assert_eq!(map.visibility(Vec3::new(0.0, 0.0, -20.0), normal), 1.0);
+  }
+
+  #[test]
+  fn cube_shadow_map_compares_distances_to_the_light() {
+    let position = Vec3::new(1.0, 2.0, 3.0);
+    let mut camera = ShadowCamera::perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 11.0);
+    let faces = CUBE_FACES
+      .iter()
+      .map(|(direction, up)| {
+        camera.look_at(position, position + *direction, *up);
+        let view_projection = camera.projection_matrix() * camera.view_matrix();
+        // an occluder 4 units away all around the light
+        (filled_depth(0.3), view_projection)
+      })
+      .collect();
+    let map = CubeShadowMap::new(faces, position, &camera, 0.0, 0.0);
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+
+    for (direction, _) in CUBE_FACES {
+      assert_eq!(map.visibility(position + direction * 2.0, normal), 1.0);
+      assert_eq!(map.visibility(position + direction * 6.0, normal), 0.0);
+      assert_eq!(map.visibility(position + direction * 20.0, normal), 1.0);
+    }
+  }
+}
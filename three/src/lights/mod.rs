@@ -1 +1,6 @@
-pub mod light;
\ No newline at end of file
+pub mod cascaded_shadow;
+pub mod directional_light;
+pub mod light;
+pub mod light_shadow;
+pub mod point_light;
+pub mod spot_light;
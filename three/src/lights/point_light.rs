@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+
+use renderer_macro_derive::object_3d;
+
+use super::{
+  light::{Light, LightActions, LightUniform},
+  light_shadow::{LightShadow, ShadowCamera, ShadowProjection},
+};
+use crate::{core::object_3d::ObjectActions, math::Vec4};
+
+#[object_3d(ObjectActions, Light, LightActions)]
+pub struct PointLight {
+  light: RefCell<Light>,
+  /// where the light fades out, 0.0 for no limit
+  distance: RefCell<f32>,
+  shadow: RefCell<LightShadow>,
+}
+
+impl PointLight {
+  pub fn new(color: Vec4, intensity: f32, distance: f32) -> std::rc::Rc<Self> {
+    let light = RefCell::new(Light { color, intensity });
+    // one 90 degree face per cube side, near and far are tunable on `shadow().camera`
+    let far = if distance > 0.0 { distance } else { 500.0 };
+    let shadow = RefCell::new(LightShadow::new(ShadowCamera::perspective(
+      std::f32::consts::FRAC_PI_2,
+      1.0,
+      0.5,
+      far,
+    )));
+    let distance = RefCell::new(distance);
+    Self::with_fields(light, distance, shadow)
+  }
+
+  pub fn distance(&self) -> f32 {
+    *self.distance.borrow()
+  }
+
+  pub fn set_distance(&self, distance: f32) {
+    *self.distance.borrow_mut() = distance;
+  }
+}
+
+impl LightActions for PointLight {
+  fn light(&self) -> std::cell::RefMut<'_, Light> {
+    self.light.borrow_mut()
+  }
+
+  fn shadow(&self) -> Option<std::cell::RefMut<'_, LightShadow>> {
+    Some(self.shadow.borrow_mut())
+  }
+
+  fn omnidirectional(&self) -> bool {
+    true
+  }
+
+  /// the faces are aimed by the shadow pass, only the frustum is kept to a cube face here
+  fn update_shadow_camera(&self) {
+    self.shadow.borrow_mut().camera.projection = ShadowProjection::Perspective {
+      fov: std::f32::consts::FRAC_PI_2,
+      aspect: 1.0,
+    };
+  }
+
+  fn to_light_uniform(&self) -> LightUniform {
+    LightUniform::Point {
+      radiance: self.light.borrow().radiance(),
+      position: self.world_position(),
+      distance: self.distance(),
+      shadow: self.shadow.borrow().cube_map(),
+    }
+  }
+}
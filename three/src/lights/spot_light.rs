@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+
+use renderer_macro_derive::object_3d;
+
+use super::{
+  light::{Light, LightActions, LightUniform},
+  light_shadow::{LightShadow, ShadowCamera, ShadowProjection},
+};
+use crate::{
+  core::object_3d::ObjectActions,
+  math::{Vec3, Vec4},
+};
+
+#[object_3d(ObjectActions, Light, LightActions)]
+pub struct SpotLight {
+  light: RefCell<Light>,
+  /// world space point the cone is aimed at
+  target: RefCell<Vec3>,
+  /// half angle of the cone in radians
+  angle: RefCell<f32>,
+  /// where the light fades out, 0.0 for no limit
+  distance: RefCell<f32>,
+  shadow: RefCell<LightShadow>,
+}
+
+impl SpotLight {
+  pub fn new(color: Vec4, intensity: f32, angle: f32, distance: f32) -> std::rc::Rc<Self> {
+    let light = RefCell::new(Light { color, intensity });
+    let target = Default::default();
+    let far = if distance > 0.0 { distance } else { 500.0 };
+    let shadow = RefCell::new(LightShadow::new(ShadowCamera::perspective(
+      angle * 2.0,
+      1.0,
+      0.5,
+      far,
+    )));
+    let angle = RefCell::new(angle);
+    let distance = RefCell::new(distance);
+    Self::with_fields(light, target, angle, distance, shadow)
+  }
+
+  pub fn target(&self) -> Vec3 {
+    *self.target.borrow()
+  }
+
+  pub fn set_target(&self, target: Vec3) {
+    *self.target.borrow_mut() = target;
+  }
+
+  pub fn angle(&self) -> f32 {
+    *self.angle.borrow()
+  }
+
+  pub fn set_angle(&self, angle: f32) {
+    *self.angle.borrow_mut() = angle;
+  }
+
+  pub fn distance(&self) -> f32 {
+    *self.distance.borrow()
+  }
+
+  pub fn set_distance(&self, distance: f32) {
+    *self.distance.borrow_mut() = distance;
+  }
+}
+
+impl LightActions for SpotLight {
+  fn light(&self) -> std::cell::RefMut<'_, Light> {
+    self.light.borrow_mut()
+  }
+
+  fn shadow(&self) -> Option<std::cell::RefMut<'_, LightShadow>> {
+    Some(self.shadow.borrow_mut())
+  }
+
+  fn update_shadow_camera(&self) {
+    let position = self.world_position();
+    let mut shadow = self.shadow.borrow_mut();
+    let (w, h) = shadow.map_size;
+
+    // the frustum follows the cone
+    shadow.camera.projection = ShadowProjection::Perspective {
+      fov: self.angle() * 2.0,
+      aspect: w as f32 / h.max(1) as f32,
+    };
+    shadow.camera.update(position, self.target());
+  }
+
+  fn to_light_uniform(&self) -> LightUniform {
+    let position = self.world_position();
+    LightUniform::Spot {
+      radiance: self.light.borrow().radiance(),
+      position,
+      direction: (self.target() - position).normalize(),
+      cone_cos: self.angle().cos(),
+      distance: self.distance(),
+      shadow: self.shadow.borrow().map(),
+    }
+  }
+}
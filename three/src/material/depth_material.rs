@@ -1,8 +1,8 @@
-use crate::core::unifrom::{Uniform, UnifromTypeEnum};
+use crate::math::Vec4;
 
 use super::{
-  material::{BasicMaterial, ConvertUniform},
-  shader::DefineShader,
+  material::{BasicMaterial, Uniforms},
+  shader::{DefineShader, Shader},
 };
 pub enum DepthPacking {
   BasicDepthPacking = 3200,
@@ -17,29 +17,30 @@ impl Default for DepthPacking {
   }
 }
 
-struct MeshDepthAttribute {
+#[derive(Default, Uniforms)]
+pub struct MeshDepthAttribute {
+  #[uniform(skip)]
   depth_packing: DepthPacking,
   wireframe: bool,
+  #[uniform(skip)]
   wirefame_linewidth: u8,
 }
 
-impl ConvertUniform for MeshDepthAttribute {
-  fn to_uniform(&self) -> Uniform {
-    let mut res = Uniform::default();
-    res.insert(format!("wireframe"), UnifromTypeEnum::Bool(self.wireframe));
-    res
-  }
-}
-
-struct DepthShader {}
+#[derive(Default)]
+pub struct DepthShader {}
 
 impl DefineShader for DepthShader {
   fn vertex() -> super::shader::VertexShader {
-    todo!()
+    Shader::default_vertex()
   }
 
   fn fragment() -> super::shader::FragmentShader {
-    todo!()
+    Box::new(|_, _, gl| {
+      // closer is brighter
+      let depth = 1.0 - gl.gl_frag_coord().z;
+      gl.gl_frag_color(Vec4::new(depth, depth, depth, 1.0));
+      true
+    })
   }
 }
 
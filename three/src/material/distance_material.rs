@@ -0,0 +1,68 @@
+use crate::{
+  core::{
+    buffer_attribute::a,
+    unifrom::u,
+    varying::{add_v, v, DeclareGlType},
+  },
+  math::{Mat4, Vec3, Vec4},
+};
+
+use super::{
+  material::{BasicMaterial, Uniforms},
+  shader::{instance_matrix, DefineShader, FragmentShader, VertexShader},
+  skinning::skin_matrix,
+};
+
+/// Writes the linear distance to `reference_position`, remapped from
+/// [near, far] to [0, 1], as the fragment depth. Used for point light shadows.
+#[derive(Uniforms)]
+pub struct MeshDistanceAttribute {
+  pub reference_position: Vec3,
+  pub near: f32,
+  pub far: f32,
+}
+
+impl Default for MeshDistanceAttribute {
+  fn default() -> Self {
+    Self {
+      reference_position: Vec3::zero(),
+      near: 1.0,
+      far: 1000.0,
+    }
+  }
+}
+
+#[derive(Default)]
+pub struct DistanceShader {}
+
+impl DefineShader for DistanceShader {
+  fn vertex() -> VertexShader {
+    Box::new(|attribute, uniform, varying, gl| {
+      let model_matrix = u!(uniform, Mat4, "model_matrix", !);
+      let view_matrix = u!(uniform, Mat4, "view_matrix", !);
+      let projection_matrix = u!(uniform, Mat4, "projection_matrix", !);
+      let model_matrix = model_matrix * instance_matrix(uniform) * skin_matrix(attribute, uniform);
+
+      let position = model_matrix * Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
+      add_v!(varying, "world_position", position.truncated_to_vec3());
+
+      gl.gl_position(projection_matrix * view_matrix * position);
+    })
+  }
+
+  fn fragment() -> FragmentShader {
+    Box::new(|uniform, varying, gl| {
+      let reference_position = u!(uniform, Vec3, "reference_position", !);
+      let near = u!(uniform, f32, "near", !);
+      let far = u!(uniform, f32, "far", !);
+      let position = v!(varying, Vec3, "world_position", !);
+
+      let distance = ((position - reference_position).length() - near) / (far - near);
+      gl.gl_frag_depth(distance.clamp(0.0, 1.0));
+      gl.gl_frag_color(Vec4::new(distance, distance, distance, 1.0));
+      true
+    })
+  }
+}
+
+pub type MeshDistanceMaterial = BasicMaterial<MeshDistanceAttribute, DistanceShader>;
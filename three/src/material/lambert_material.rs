@@ -0,0 +1,91 @@
+use crate::{
+  core::{
+    buffer_attribute::a,
+    buffer_geometry::Attribute,
+    unifrom::{u, Uniform},
+    varying::Varyings,
+    Extract,
+  },
+  math::{Mat4, Vec3, Vec4},
+};
+
+use super::{
+  material::{BasicMaterial, Uniforms},
+  shader::{instance_matrix, DefineTypedShader, GlPerFragment, GlPerVertex, Typed},
+  skinning::skin_matrix,
+};
+
+/// Diffuse only lighting, shadowed by the lights' shadow maps
+/// when the object receives shadows.
+#[derive(Uniforms)]
+pub struct MeshLambertAttribute {
+  pub color: Vec4,
+  pub emissive: Vec4,
+}
+
+impl Default for MeshLambertAttribute {
+  fn default() -> Self {
+    Self {
+      color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+      emissive: Vec4::new(0.0, 0.0, 0.0, 1.0),
+    }
+  }
+}
+
+#[derive(Varyings, Clone, Copy)]
+pub struct LambertVaryings {
+  world_position: Vec3,
+  world_normal: Vec3,
+}
+
+#[derive(Default)]
+pub struct LambertShader {}
+
+impl DefineTypedShader for LambertShader {
+  type Varyings = LambertVaryings;
+
+  fn vertex(attribute: &Attribute, uniform: &Uniform, gl: &mut GlPerVertex) -> LambertVaryings {
+    let model_matrix = u!(uniform, Mat4, "model_matrix", !);
+    let view_matrix = u!(uniform, Mat4, "view_matrix", !);
+    let projection_matrix = u!(uniform, Mat4, "projection_matrix", !);
+    let model_matrix = model_matrix * instance_matrix(uniform) * skin_matrix(attribute, uniform);
+
+    let position = model_matrix * Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
+    let normal = model_matrix.inverse_transpose().unwrap_or(model_matrix)
+      * Vec4::from_vec3(&a!(attribute, Vec3, "normal", !), 0.0);
+
+    gl.gl_position(projection_matrix * view_matrix * position);
+
+    LambertVaryings {
+      world_position: position.truncated_to_vec3(),
+      world_normal: normal.truncated_to_vec3(),
+    }
+  }
+
+  fn fragment(uniform: &Uniform, varyings: &LambertVaryings, gl: &mut GlPerFragment) -> bool {
+    let color = u!(uniform, Vec4, "color", !);
+    let color = match u!(uniform, Vec3, "instance_color") {
+      Some(tint) => Vec4::from_vec3(&(color.truncated_to_vec3() * tint), color.w),
+      None => color,
+    };
+    let emissive = u!(uniform, Vec4, "emissive", !);
+    let position = varyings.world_position;
+    let normal = varyings.world_normal.normalize();
+
+    let mut irradiance = Vec3::zero();
+    for light in uniform.lights() {
+      let (l, radiance) = light.incident(position);
+      let n_dot_l = normal.dot(&l);
+      if n_dot_l <= 0.0 {
+        continue;
+      }
+      irradiance += radiance * n_dot_l * light.visibility(position, normal);
+    }
+
+    let rgb = color.truncated_to_vec3() * irradiance + emissive.truncated_to_vec3();
+    gl.gl_frag_color(Vec4::from_vec3(&rgb, color.w));
+    true
+  }
+}
+
+pub type MeshLambertMaterial = BasicMaterial<MeshLambertAttribute, Typed<LambertShader>>;
@@ -0,0 +1,71 @@
+use crate::{
+  core::{
+    buffer_attribute::a,
+    buffer_geometry::Attribute,
+    unifrom::{u, Uniform},
+    varying::Varyings,
+    Extract, ExtractRef,
+  },
+  math::{Mat4, Vec3, Vec4},
+};
+
+use super::{
+  material::{BasicMaterial, Uniforms},
+  shader::{instance_matrix, DefineTypedShader, GlPerFragment, GlPerVertex, Typed},
+  skinning::skin_matrix,
+};
+
+/// Unlit lines in a single color, multiplied by the `color` attribute of the
+/// geometry when it has one.
+#[derive(Uniforms)]
+pub struct LineBasicAttribute {
+  pub color: Vec4,
+}
+
+impl Default for LineBasicAttribute {
+  fn default() -> Self {
+    Self {
+      color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+    }
+  }
+}
+
+#[derive(Varyings, Clone, Copy)]
+pub struct LineBasicVaryings {
+  color: Vec3,
+}
+
+#[derive(Default)]
+pub struct LineBasicShader {}
+
+impl DefineTypedShader for LineBasicShader {
+  type Varyings = LineBasicVaryings;
+
+  fn vertex(attribute: &Attribute, uniform: &Uniform, gl: &mut GlPerVertex) -> LineBasicVaryings {
+    let model_matrix = u!(uniform, Mat4, "model_matrix", !);
+    let view_matrix = u!(uniform, Mat4, "view_matrix", !);
+    let projection_matrix = u!(uniform, Mat4, "projection_matrix", !);
+    let model_matrix = model_matrix * instance_matrix(uniform) * skin_matrix(attribute, uniform);
+
+    let position = Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
+    gl.gl_position(projection_matrix * view_matrix * model_matrix * position);
+
+    let color: Option<Vec3> = attribute.get("color").and_then(|v| v.extract());
+    LineBasicVaryings {
+      color: color.unwrap_or(Vec3::new(1.0, 1.0, 1.0)),
+    }
+  }
+
+  fn fragment(uniform: &Uniform, varyings: &LineBasicVaryings, gl: &mut GlPerFragment) -> bool {
+    let color = u!(uniform, Vec4, "color", !);
+    let tint =
+      varyings.color * u!(uniform, Vec3, "instance_color").unwrap_or(Vec3::new(1.0, 1.0, 1.0));
+    gl.gl_frag_color(Vec4::from_vec3(
+      &(color.truncated_to_vec3() * tint),
+      color.w,
+    ));
+    true
+  }
+}
+
+pub type LineBasicMaterial = BasicMaterial<LineBasicAttribute, Typed<LineBasicShader>>;
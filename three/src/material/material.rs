@@ -1,8 +1,21 @@
 use std::{any::Any, cell::RefCell, collections::HashMap, marker::PhantomData, rc::Rc};
 
-use crate::core::{buffer_geometry::Attribute, unifrom::Uniform, varying::Varying};
+use crate::{
+  core::{
+    buffer_geometry::{Attribute, GeometryActions},
+    unifrom::Uniform,
+    varying::Varying,
+  },
+  math::Mat4,
+  renderer::{
+    rasterizer::{draw_typed_geometry, draw_varying_geometry},
+    render_target::RenderTarget,
+  },
+};
 
-use super::shader::{DefineShader, GlPerFragment, GlPerVertex, Shader};
+use super::shader::{DefineShader, DefineTypedShader, GlPerFragment, GlPerVertex, Shader, Typed};
+
+pub use renderer_macro_derive::Uniforms;
 
 #[derive(Debug)]
 enum DepthFunc {
@@ -51,12 +64,31 @@ impl Default for DepthFunc {
   }
 }
 
+/// How the fragments of a material go through the depth buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+  /// fragments behind the depth buffer are dropped
+  pub test: bool,
+  /// fragments that are kept write their depth
+  pub write: bool,
+}
+
+impl Default for DepthState {
+  fn default() -> Self {
+    Self {
+      test: true,
+      write: true,
+    }
+  }
+}
+
+/// Usually implemented with `#[derive(Uniforms)]`.
 pub trait ConvertUniform {
   fn to_uniform(&self) -> Uniform;
 }
 
 #[derive(Debug, Default)]
-pub struct BasicMaterial<T: ConvertUniform, U: DefineShader> {
+pub struct BasicMaterial<T: ConvertUniform, U> {
   pub user_data: HashMap<String, Rc<dyn Any>>,
 
   pub blending: Blending,
@@ -74,12 +106,29 @@ pub struct BasicMaterial<T: ConvertUniform, U: DefineShader> {
   abstract_shader: PhantomData<U>,
 }
 
-trait RunShader {
+impl<T: ConvertUniform, U> BasicMaterial<T, U> {
+  pub fn new(attributes: T) -> Self {
+    Self {
+      user_data: Default::default(),
+      blending: Default::default(),
+      side: Default::default(),
+      opacity: 255,
+      transparent: false,
+      depth_test: true,
+      depth_func: Default::default(),
+      depth_write: true,
+      attributes: RefCell::new(Rc::new(attributes)),
+      abstract_shader: PhantomData,
+    }
+  }
+}
+
+pub trait RunShader {
   fn vertex(&self, a: &Attribute, u: &Uniform, v: &mut Varying, gl: &mut GlPerVertex);
   fn fragment(&self, u: &Uniform, v: &Varying, gl: &mut GlPerFragment) -> bool;
 }
 
-impl<T: ConvertUniform, U: DefineShader> ConvertUniform for BasicMaterial<T, U> {
+impl<T: ConvertUniform, U> ConvertUniform for BasicMaterial<T, U> {
   fn to_uniform(&self) -> Uniform {
     self.attributes.borrow().to_uniform()
   }
@@ -95,4 +144,213 @@ impl<T: ConvertUniform, U: DefineShader> RunShader for BasicMaterial<T, U> {
   }
 }
 
-pub trait MaterialActions: ConvertUniform + RunShader {}
+/// How the vertices of a geometry are put together, like the mode of `glDrawArrays`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawMode {
+  #[default]
+  Triangles,
+  /// every two vertices make a line
+  Lines,
+  /// a line through all vertices
+  LineStrip,
+  /// a line through all vertices, back to the first one
+  LineLoop,
+  Points,
+}
+
+/// Rasterize every primitive of a geometry with the material's shader.
+pub trait DrawGeometry {
+  fn draw(
+    &self,
+    geometry: &dyn GeometryActions,
+    mode: DrawMode,
+    uniform: &Uniform,
+    viewport_matrix: &Mat4,
+    target: &mut RenderTarget,
+  );
+}
+
+impl<T: ConvertUniform, U: DefineShader> DrawGeometry for BasicMaterial<T, U> {
+  fn draw(
+    &self,
+    geometry: &dyn GeometryActions,
+    mode: DrawMode,
+    uniform: &Uniform,
+    viewport_matrix: &Mat4,
+    target: &mut RenderTarget,
+  ) {
+    let depth = self.depth_state();
+    draw_varying_geometry(
+      geometry,
+      self,
+      depth,
+      mode,
+      uniform,
+      viewport_matrix,
+      target,
+    )
+  }
+}
+
+impl<T: ConvertUniform, U: DefineTypedShader> DrawGeometry for BasicMaterial<T, Typed<U>> {
+  fn draw(
+    &self,
+    geometry: &dyn GeometryActions,
+    mode: DrawMode,
+    uniform: &Uniform,
+    viewport_matrix: &Mat4,
+    target: &mut RenderTarget,
+  ) {
+    let depth = self.depth_state();
+    draw_typed_geometry::<U>(geometry, depth, mode, uniform, viewport_matrix, target)
+  }
+}
+
+pub trait MaterialActions: ConvertUniform + DrawGeometry {
+  fn depth_state(&self) -> DepthState {
+    DepthState::default()
+  }
+}
+
+impl<T: ConvertUniform, U> MaterialActions for BasicMaterial<T, U>
+where
+  Self: DrawGeometry,
+{
+  fn depth_state(&self) -> DepthState {
+    DepthState {
+      test: self.depth_test,
+      write: self.depth_write,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use image::{DynamicImage, Rgba, RgbaImage};
+
+  use super::*;
+  use crate::{
+    core::{
+      buffer_attribute::{a, F32BufferAttribute},
+      buffer_geometry::BufferGeometry,
+      unifrom::u,
+      varying::Varyings,
+      Extract,
+    },
+    math::{Vec2, Vec3, Vec4},
+    renderer::viewport::Viewport,
+    textures::{
+      texture::{Texture, TextureHandle},
+      texture_2D,
+    },
+  };
+
+  #[derive(Uniforms)]
+  struct TexturedAttribute {
+    tint: Vec4,
+    #[uniform(rename = "alpha_test")]
+    cutoff: f32,
+    #[uniform(skip)]
+    #[allow(dead_code)]
+    label: &'static str,
+    map: Rc<Texture>,
+    detail_map: Option<Rc<Texture>>,
+  }
+
+  #[derive(Varyings, Clone, Copy)]
+  struct TexturedVaryings {
+    uv: Vec2,
+  }
+
+  struct TexturedShader {}
+
+  impl DefineTypedShader for TexturedShader {
+    type Varyings = TexturedVaryings;
+
+    fn vertex(attribute: &Attribute, _: &Uniform, gl: &mut GlPerVertex) -> TexturedVaryings {
+      let position = a!(attribute, Vec3, "position", !);
+      gl.gl_position(Vec4::from_vec3(&position, 1.0));
+      TexturedVaryings {
+        uv: Vec2::new(0.5, 0.5),
+      }
+    }
+
+    fn fragment(uniform: &Uniform, varyings: &TexturedVaryings, gl: &mut GlPerFragment) -> bool {
+      let tint = u!(uniform, Vec4, "tint", !);
+      let texel = texture_2D(uniform.texture("map").unwrap(), varyings.uv);
+      gl.gl_frag_color(Vec4::new(
+        tint.x * texel.x,
+        tint.y * texel.y,
+        tint.z * texel.z,
+        1.0,
+      ));
+      true
+    }
+  }
+
+  fn texture(color: [u8; 4], id: u32) -> Rc<Texture> {
+    let image = RgbaImage::from_pixel(2, 2, Rgba(color));
+    Rc::new(Texture::from_image(DynamicImage::ImageRgba8(image), id))
+  }
+
+  fn attribute(detail_map: Option<Rc<Texture>>) -> TexturedAttribute {
+    TexturedAttribute {
+      tint: Vec4::new(1.0, 1.0, 1.0, 1.0),
+      cutoff: 0.5,
+      label: "textured",
+      map: texture([0, 255, 0, 255], 1),
+      detail_map,
+    }
+  }
+
+  #[test]
+  fn derived_uniforms_follow_the_field_attributes() {
+    let uniform = attribute(None).to_uniform();
+
+    assert_eq!(
+      u!(uniform, Vec4, "tint"),
+      Some(Vec4::new(1.0, 1.0, 1.0, 1.0))
+    );
+    assert_eq!(u!(uniform, f32, "alpha_test"), Some(0.5));
+    assert!(uniform.get("cutoff").is_none());
+    assert!(uniform.get("label").is_none());
+    assert_eq!(u!(uniform, TextureHandle, "map"), Some(TextureHandle(1)));
+    assert!(uniform.texture("map").is_some());
+    assert!(uniform.get("detail_map").is_none());
+  }
+
+  #[test]
+  fn derived_optional_textures_are_bound_when_set() {
+    let uniform = attribute(Some(texture([255, 0, 0, 255], 2))).to_uniform();
+
+    assert_eq!(
+      u!(uniform, TextureHandle, "detail_map"),
+      Some(TextureHandle(2))
+    );
+    assert!(uniform.texture("detail_map").is_some());
+  }
+
+  #[test]
+  fn renders_through_a_derived_texture_uniform() {
+    let material: BasicMaterial<_, Typed<TexturedShader>> = BasicMaterial::new(attribute(None));
+    let mut geometry = BufferGeometry::default();
+    let position = vec![-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
+    geometry.set_attribute(
+      "position",
+      F32BufferAttribute::new(position, 3, false).as_enum(),
+    );
+
+    let mut target = RenderTarget::new(8, 8);
+    let viewport = Viewport::new(0.0, 0.0, 8.0, 8.0);
+    material.draw(
+      &geometry,
+      DrawMode::Triangles,
+      &material.to_uniform(),
+      viewport.get_viewport_matrix(),
+      &mut target,
+    );
+
+    let p = (4 + 4 * 8) * 3;
+    assert_eq!(target.attachment(0).unwrap().data()[p..p + 3], [0, 255, 0]);
+  }
+}
@@ -1,3 +1,8 @@
 pub mod depth_material;
+pub mod distance_material;
+pub mod lambert_material;
+pub mod line_basic_material;
 pub mod material;
+pub mod points_material;
 pub mod shader;
+pub mod skinning;
@@ -0,0 +1,171 @@
+use std::rc::Rc;
+
+use crate::{
+  core::{
+    buffer_attribute::a,
+    buffer_geometry::Attribute,
+    unifrom::{u, Uniform},
+    varying::Varyings,
+    Extract, ExtractRef,
+  },
+  math::{Mat4, Vec2, Vec3, Vec4},
+  textures::{texture::Texture, texture_2D},
+};
+
+use super::{
+  material::{BasicMaterial, Uniforms},
+  shader::{instance_matrix, DefineTypedShader, GlPerFragment, GlPerVertex, Typed},
+  skinning::skin_matrix,
+};
+
+/// Unlit squares for `Points`, `size` pixels wide or, with `size_attenuation`,
+/// `size` world units wide under a perspective camera.
+/// `map` is sampled across every square, fragments less opaque than `alpha_test`
+/// are dropped.
+#[derive(Uniforms)]
+pub struct PointsAttribute {
+  pub color: Vec4,
+  pub size: f32,
+  pub size_attenuation: bool,
+  pub alpha_test: f32,
+  pub map: Option<Rc<Texture>>,
+}
+
+impl Default for PointsAttribute {
+  fn default() -> Self {
+    Self {
+      color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+      size: 1.0,
+      size_attenuation: true,
+      alpha_test: 0.0,
+      map: None,
+    }
+  }
+}
+
+#[derive(Varyings, Clone, Copy)]
+pub struct PointsVaryings {
+  color: Vec3,
+}
+
+#[derive(Default)]
+pub struct PointsShader {}
+
+impl DefineTypedShader for PointsShader {
+  type Varyings = PointsVaryings;
+
+  fn vertex(attribute: &Attribute, uniform: &Uniform, gl: &mut GlPerVertex) -> PointsVaryings {
+    let model_matrix = u!(uniform, Mat4, "model_matrix", !);
+    let view_matrix = u!(uniform, Mat4, "view_matrix", !);
+    let projection_matrix = u!(uniform, Mat4, "projection_matrix", !);
+    let model_matrix = model_matrix * instance_matrix(uniform) * skin_matrix(attribute, uniform);
+
+    let position = Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
+    let mv_position = view_matrix * model_matrix * position;
+    gl.gl_position(projection_matrix * mv_position);
+
+    // the last row of a perspective projection is (0, 0, -1, 0)
+    let mut size = u!(uniform, f32, "size", !);
+    let perspective = projection_matrix.get(3, 3) == 0.0;
+    if perspective && u!(uniform, bool, "size_attenuation", !) && mv_position.z < 0.0 {
+      let resolution = u!(uniform, Vec2, "resolution").unwrap_or(Vec2::new(2.0, 2.0));
+      size *= projection_matrix.get(1, 1) * resolution.y / 2.0 / -mv_position.z;
+    }
+    gl.gl_point_size(size);
+
+    let color: Option<Vec3> = attribute.get("color").and_then(|v| v.extract());
+    PointsVaryings {
+      color: color.unwrap_or(Vec3::new(1.0, 1.0, 1.0)),
+    }
+  }
+
+  fn fragment(uniform: &Uniform, varyings: &PointsVaryings, gl: &mut GlPerFragment) -> bool {
+    let color = u!(uniform, Vec4, "color", !);
+    let mut color = Vec4::from_vec3(&(color.truncated_to_vec3() * varyings.color), color.w);
+
+    if let Some(map) = uniform.texture("map") {
+      // flip y, point coordinates grow downwards like the screen
+      let coord = gl.gl_point_coord();
+      let texel = texture_2D(map, Vec2::new(coord.x, 1.0 - coord.y));
+      color = Vec4::new(
+        color.x * texel.x,
+        color.y * texel.y,
+        color.z * texel.z,
+        color.w * texel.w,
+      );
+    }
+
+    if color.w < u!(uniform, f32, "alpha_test", !) {
+      gl.discard();
+      return false;
+    }
+
+    gl.gl_frag_color(color);
+    true
+  }
+}
+
+pub type PointsMaterial = BasicMaterial<PointsAttribute, Typed<PointsShader>>;
+
+#[cfg(test)]
+mod tests {
+  use image::{DynamicImage, Rgba, RgbaImage};
+
+  use super::*;
+  use crate::{
+    core::{
+      buffer_attribute::F32BufferAttribute,
+      buffer_geometry::{BufferGeometry, GeometryActions},
+      unifrom::UnifromTypeEnum,
+    },
+    material::material::{ConvertUniform, DrawGeometry, DrawMode},
+    renderer::{render_target::RenderTarget, viewport::Viewport},
+  };
+
+  fn draw_point(attribute: PointsAttribute) -> [u8; 3] {
+    let material = PointsMaterial::new(attribute);
+    let mut geometry = BufferGeometry::default();
+    geometry.set_attribute(
+      "position",
+      F32BufferAttribute::new(vec![0.0, 0.0, 0.0], 3, false).as_enum(),
+    );
+
+    let mut uniform = material.to_uniform();
+    for key in ["model_matrix", "view_matrix", "projection_matrix"] {
+      uniform.insert(key.to_string(), UnifromTypeEnum::from(Mat4::identity()));
+    }
+
+    let mut target = RenderTarget::new(8, 8);
+    let viewport = Viewport::new(0.0, 0.0, 8.0, 8.0);
+    material.draw(
+      &geometry,
+      DrawMode::Points,
+      &uniform,
+      viewport.get_viewport_matrix(),
+      &mut target,
+    );
+
+    let p = (4 + 4 * 8) * 3;
+    let data = target.attachment(0).unwrap().data();
+    [data[p], data[p + 1], data[p + 2]]
+  }
+
+  #[test]
+  fn points_are_tinted_by_their_map() {
+    let image = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255]));
+    let map = Texture::from_image(DynamicImage::ImageRgba8(image), 1);
+
+    let plain = draw_point(PointsAttribute {
+      size: 4.0,
+      ..Default::default()
+    });
+    let mapped = draw_point(PointsAttribute {
+      size: 4.0,
+      map: Some(Rc::new(map)),
+      ..Default::default()
+    });
+
+    assert_eq!(plain, [255, 255, 255]);
+    assert_eq!(mapped, [0, 0, 255]);
+  }
+}
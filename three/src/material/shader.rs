@@ -1,12 +1,14 @@
 use crate::core::unifrom::u;
-use crate::math::{Mat4, Vec3, Vec4};
+use crate::core::Extract;
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
 
 use crate::core::buffer_attribute::a;
 use crate::core::buffer_geometry::Attribute;
 
 use crate::core::unifrom::Uniform;
-use crate::core::varying::Varying;
-use std::fmt::Debug;
+use crate::core::varying::{Varying, Varyings};
+use crate::material::skinning::skin_matrix;
+use std::{fmt::Debug, marker::PhantomData};
 
 macro_rules! define_gl_obj {
   ($name:tt, $($prop:tt:$ty:ty),+) => {
@@ -37,12 +39,92 @@ define_gl_obj!(
   gl_clip_distance: Vec<f32>
 );
 
-define_gl_obj!(
-  GlPerFragment,
-  gl_frag_color: Vec4
-);
+/// the number of color attachments a fragment can write into
+pub const MAX_DRAW_BUFFERS: usize = 4;
+
+/// Fragment built-ins. Writing `gl_frag_color` broadcasts to every color attachment
+/// of the bound render target, writing any of `gl_frag_data` routes each entry to
+/// the attachment with the same index instead.
+#[derive(Debug, Default)]
+pub struct GlPerFragment {
+  gl_frag_coord: Vec4,
+  gl_point_coord: Vec2,
+  gl_frag_color: Vec4,
+  gl_frag_data: [Vec4; MAX_DRAW_BUFFERS],
+  gl_frag_depth: f32,
+  draw_buffers: u32,
+  discarded: bool,
+}
+
+impl GlPerFragment {
+  /// `gl_frag_depth` starts as the interpolated window depth `frag_coord.z`
+  pub fn new(frag_coord: Vec4) -> Self {
+    Self {
+      gl_frag_coord: frag_coord,
+      gl_frag_depth: frag_coord.z,
+      ..Default::default()
+    }
+  }
+
+  /// for points, where the fragment is inside the point's square, from 0 to 1
+  pub(crate) fn with_point_coord(frag_coord: Vec4, point_coord: Vec2) -> Self {
+    Self {
+      gl_point_coord: point_coord,
+      ..Self::new(frag_coord)
+    }
+  }
+
+  pub fn gl_frag_coord(&self) -> Vec4 {
+    self.gl_frag_coord
+  }
+
+  pub fn gl_point_coord(&self) -> Vec2 {
+    self.gl_point_coord
+  }
+
+  pub fn gl_frag_color(&mut self, val: Vec4) {
+    self.gl_frag_color = val
+  }
+
+  /// writes past `MAX_DRAW_BUFFERS` have no attachment to land in and are ignored
+  pub fn gl_frag_data(&mut self, index: usize, val: Vec4) {
+    if index >= MAX_DRAW_BUFFERS {
+      return;
+    }
+    self.gl_frag_data[index] = val;
+    self.draw_buffers |= 1 << index;
+  }
+
+  pub fn gl_frag_depth(&mut self, val: f32) {
+    self.gl_frag_depth = val
+  }
+
+  pub fn discard(&mut self) {
+    self.discarded = true
+  }
+
+  pub fn is_discarded(&self) -> bool {
+    self.discarded
+  }
+
+  pub fn depth(&self) -> f32 {
+    self.gl_frag_depth
+  }
+
+  /// the color routed to the attachment `index`, `None` leaves the attachment untouched
+  pub fn output(&self, index: usize) -> Option<Vec4> {
+    if self.draw_buffers == 0 {
+      Some(self.gl_frag_color)
+    } else if index < MAX_DRAW_BUFFERS && self.draw_buffers & (1 << index) != 0 {
+      Some(self.gl_frag_data[index])
+    } else {
+      None
+    }
+  }
+}
 
 pub type VertexShader = Box<dyn Fn(&Attribute, &Uniform, &mut Varying, &mut GlPerVertex)>;
+/// returning `false` discards the fragment, same as calling `GlPerFragment::discard`
 pub type FragmentShader = Box<dyn Fn(&Uniform, &Varying, &mut GlPerFragment) -> bool>;
 
 pub struct Shader {
@@ -55,6 +137,26 @@ pub trait DefineShader {
   fn fragment() -> FragmentShader;
 }
 
+/// A shader handing its varyings over as a plain struct instead of through `Varying`,
+/// nothing is allocated or looked up by name per fragment.
+pub trait DefineTypedShader {
+  type Varyings: Varyings + Clone;
+
+  fn vertex(attribute: &Attribute, uniform: &Uniform, gl: &mut GlPerVertex) -> Self::Varyings;
+  /// returning `false` discards the fragment, same as calling `GlPerFragment::discard`
+  fn fragment(uniform: &Uniform, varyings: &Self::Varyings, gl: &mut GlPerFragment) -> bool;
+}
+
+/// Put a `DefineTypedShader` into a `BasicMaterial`, `BasicMaterial<T, Typed<U>>`.
+#[derive(Debug, Default)]
+pub struct Typed<U>(PhantomData<U>);
+
+/// The transform of the instance being drawn, applied after skinning and before
+/// `model_matrix`. Identity outside of an `InstancedMesh`.
+pub fn instance_matrix(uniform: &Uniform) -> Mat4 {
+  u!(uniform, Mat4, "instance_matrix").unwrap_or_else(Mat4::identity)
+}
+
 impl Debug for Shader {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("Shader")
@@ -70,6 +172,7 @@ impl Shader {
       let model_matrix = u!(unifrom, Mat4, "model_matrix", !);
       let view_matrix = u!(unifrom, Mat4, "view_matrix", !);
       let projection_matrix = u!(unifrom, Mat4, "projection_matrix", !);
+      let model_matrix = model_matrix * instance_matrix(unifrom) * skin_matrix(attribute, unifrom);
       let position = Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
       gl.gl_position = projection_matrix * view_matrix * model_matrix * position;
     });
@@ -91,3 +194,24 @@ impl Default for Shader {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn gl_frag_data_routes_to_its_own_attachment() {
+    let white = Vec4::new(1.0, 1.0, 1.0, 1.0);
+    let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+    let mut gl = GlPerFragment::new(Vec4::new(0.0, 0.0, 0.5, 1.0));
+
+    gl.gl_frag_color(white);
+    assert_eq!(gl.output(2), Some(white));
+
+    gl.gl_frag_data(1, red);
+    gl.gl_frag_data(MAX_DRAW_BUFFERS, white);
+    assert_eq!(gl.output(0), None);
+    assert_eq!(gl.output(1), Some(red));
+    assert_eq!(gl.output(MAX_DRAW_BUFFERS), None);
+  }
+}
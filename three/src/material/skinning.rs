@@ -0,0 +1,136 @@
+use crate::{
+  core::{
+    buffer_geometry::Attribute,
+    unifrom::{u, Uniform},
+    Extract, ExtractRef,
+  },
+  math::{Mat4, Vec4},
+};
+
+/// the number of bones a single vertex can follow
+pub const MAX_BONE_INFLUENCES: usize = 4;
+
+/// The transform a skinned vertex goes through before `model_matrix`: the bones in
+/// `skin_index` blended by `skin_weight`, between `bind_matrix` and `bind_matrix_inverse`.
+/// Identity for anything drawn without bones, or whose weights land on no existing bone.
+pub fn skin_matrix(attribute: &Attribute, uniform: &Uniform) -> Mat4 {
+  let bone_matrices = uniform.bone_matrices();
+  if bone_matrices.is_empty() {
+    return Mat4::identity();
+  }
+
+  let index: Option<Vec4> = attribute.get("skin_index").and_then(|v| v.extract());
+  let weight: Option<Vec4> = attribute.get("skin_weight").and_then(|v| v.extract());
+  let (index, weight) = match (index, weight) {
+    (Some(index), Some(weight)) => (index, weight),
+    _ => return Mat4::identity(),
+  };
+
+  let indices = [index.x, index.y, index.z, index.w];
+  let weights = [weight.x, weight.y, weight.z, weight.w];
+  let mut skin = Mat4::zeros();
+  let mut total = 0.0;
+  for i in 0..MAX_BONE_INFLUENCES {
+    // `as usize` would turn a negative index into bone 0
+    if weights[i] == 0.0 || indices[i] < 0.0 {
+      continue;
+    }
+    if let Some(bone) = bone_matrices.get(indices[i] as usize) {
+      skin = skin + *bone * weights[i];
+      total += weights[i];
+    }
+  }
+  // no bone left to follow, leave the vertex where it is instead of collapsing it
+  if total == 0.0 {
+    return Mat4::identity();
+  }
+
+  let bind_matrix = u!(uniform, Mat4, "bind_matrix").unwrap_or_else(Mat4::identity);
+  let bind_matrix_inverse = u!(uniform, Mat4, "bind_matrix_inverse").unwrap_or_else(Mat4::identity);
+  bind_matrix_inverse * skin * bind_matrix
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    core::{buffer_attribute::F32BufferAttribute, unifrom::UnifromTypeEnum},
+    math::{apply_scale, apply_translate, extract_position, Vec3},
+  };
+
+  fn vertex(index: [f32; 4], weight: [f32; 4]) -> Attribute {
+    let mut attribute = Attribute::new();
+    for (key, value) in [("skin_index", index), ("skin_weight", weight)] {
+      let value = F32BufferAttribute::new(value.to_vec(), 4, false).as_enum();
+      attribute.insert(key.to_string(), value);
+    }
+    attribute
+  }
+
+  fn two_bones() -> Uniform {
+    let mut uniform = Uniform::default();
+    uniform.set_bone_matrices(vec![
+      apply_translate(&Vec3::new(2.0, 0.0, 0.0)),
+      apply_translate(&Vec3::new(0.0, 4.0, 0.0)),
+    ]);
+    uniform
+  }
+
+  #[test]
+  fn unskinned_vertices_stay_put() {
+    let attribute = vertex([0.0, 1.0, 0.0, 0.0], [0.5, 0.5, 0.0, 0.0]);
+    assert_eq!(
+      skin_matrix(&attribute, &Uniform::default()),
+      Mat4::identity()
+    );
+    assert_eq!(
+      skin_matrix(&Attribute::new(), &two_bones()),
+      Mat4::identity()
+    );
+  }
+
+  #[test]
+  fn weights_blend_the_bones() {
+    let uniform = two_bones();
+
+    let attribute = vertex([0.0, 1.0, 0.0, 0.0], [0.5, 0.5, 0.0, 0.0]);
+    let skin = skin_matrix(&attribute, &uniform);
+    assert_eq!(extract_position(skin), Vec3::new(1.0, 2.0, 0.0));
+
+    let attribute = vertex([1.0, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]);
+    let skin = skin_matrix(&attribute, &uniform);
+    assert_eq!(extract_position(skin), Vec3::new(0.0, 4.0, 0.0));
+  }
+
+  #[test]
+  fn vertices_without_a_valid_bone_stay_put() {
+    let uniform = two_bones();
+
+    let attribute = vertex([0.0, 1.0, 0.0, 0.0], [0.0; 4]);
+    assert_eq!(skin_matrix(&attribute, &uniform), Mat4::identity());
+
+    let attribute = vertex([5.0, 7.0, 0.0, 0.0], [0.5, 0.5, 0.0, 0.0]);
+    assert_eq!(skin_matrix(&attribute, &uniform), Mat4::identity());
+
+    let attribute = vertex([-1.0, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]);
+    assert_eq!(skin_matrix(&attribute, &uniform), Mat4::identity());
+  }
+
+  #[test]
+  fn bones_move_in_the_bind_space() {
+    let mut uniform = two_bones();
+    let bind_matrix = apply_scale(&Vec3::new(2.0, 2.0, 2.0));
+    uniform.insert(
+      "bind_matrix".to_string(),
+      UnifromTypeEnum::from(bind_matrix),
+    );
+    uniform.insert(
+      "bind_matrix_inverse".to_string(),
+      UnifromTypeEnum::from(bind_matrix.inverse().unwrap()),
+    );
+
+    let attribute = vertex([0.0, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]);
+    let skin = skin_matrix(&attribute, &uniform);
+    assert_eq!(extract_position(skin), Vec3::new(1.0, 0.0, 0.0));
+  }
+}
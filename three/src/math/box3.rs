@@ -0,0 +1,146 @@
+use super::{Mat4, Vec3, Vec4};
+
+/// An axis aligned box, empty until a point is added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Box3 {
+  pub min: Vec3,
+  pub max: Vec3,
+}
+
+impl Default for Box3 {
+  fn default() -> Self {
+    Self::empty()
+  }
+}
+
+impl Box3 {
+  pub fn new(min: Vec3, max: Vec3) -> Self {
+    Self { min, max }
+  }
+
+  pub fn empty() -> Self {
+    Self {
+      min: Vec3::new(f32::MAX, f32::MAX, f32::MAX),
+      max: Vec3::new(f32::MIN, f32::MIN, f32::MIN),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.max.x < self.min.x || self.max.y < self.min.y || self.max.z < self.min.z
+  }
+
+  pub fn expand_by_point(&mut self, point: Vec3) {
+    self.min = Vec3::new(
+      self.min.x.min(point.x),
+      self.min.y.min(point.y),
+      self.min.z.min(point.z),
+    );
+    self.max = Vec3::new(
+      self.max.x.max(point.x),
+      self.max.y.max(point.y),
+      self.max.z.max(point.z),
+    );
+  }
+
+  pub fn union(&self, other: &Box3) -> Box3 {
+    let mut result = *self;
+    if !other.is_empty() {
+      result.expand_by_point(other.min);
+      result.expand_by_point(other.max);
+    }
+    result
+  }
+
+  pub fn corners(&self) -> [Vec3; 8] {
+    let (a, b) = (self.min, self.max);
+    [
+      Vec3::new(a.x, a.y, a.z),
+      Vec3::new(b.x, a.y, a.z),
+      Vec3::new(a.x, b.y, a.z),
+      Vec3::new(b.x, b.y, a.z),
+      Vec3::new(a.x, a.y, b.z),
+      Vec3::new(b.x, a.y, b.z),
+      Vec3::new(a.x, b.y, b.z),
+      Vec3::new(b.x, b.y, b.z),
+    ]
+  }
+
+  /// the box around this one after `matrix` moved its corners
+  pub fn apply_matrix(&self, matrix: &Mat4) -> Box3 {
+    let mut result = Box3::empty();
+    if self.is_empty() {
+      return result;
+    }
+    for corner in self.corners() {
+      result.expand_by_point((*matrix * Vec4::from_vec3(&corner, 1.0)).truncated_to_vec3());
+    }
+    result
+  }
+
+  /// `false` when the box is certainly outside the clip volume of `view_projection`,
+  /// that is all of its corners are beyond the same plane
+  pub fn intersects_clip_volume(&self, view_projection: &Mat4) -> bool {
+    if self.is_empty() {
+      return false;
+    }
+
+    let corners = self
+      .corners()
+      .map(|corner| *view_projection * Vec4::from_vec3(&corner, 1.0));
+
+    let planes: [fn(&Vec4) -> bool; 6] = [
+      |p| p.x < -p.w,
+      |p| p.x > p.w,
+      |p| p.y < -p.w,
+      |p| p.y > p.w,
+      |p| p.z < -p.w,
+      |p| p.z > p.w,
+    ];
+    !planes
+      .iter()
+      .any(|outside| corners.iter().all(|corner| outside(corner)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::math::{apply_translate, orthographic};
+
+  fn unit() -> Box3 {
+    Box3::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0))
+  }
+
+  #[test]
+  fn empty_boxes_grow_by_points_and_unions() {
+    let mut bounds = Box3::default();
+    assert!(bounds.is_empty());
+    assert_eq!(bounds.union(&Box3::empty()), bounds);
+
+    bounds.expand_by_point(Vec3::new(1.0, 2.0, 3.0));
+    assert!(!bounds.is_empty());
+    let union = bounds.union(&unit());
+    assert_eq!(union.min, Vec3::new(-1.0, -1.0, -1.0));
+    assert_eq!(union.max, Vec3::new(1.0, 2.0, 3.0));
+  }
+
+  #[test]
+  fn matrices_move_the_corners() {
+    let moved = unit().apply_matrix(&apply_translate(&Vec3::new(3.0, 0.0, 0.0)));
+    assert_eq!(moved.min, Vec3::new(2.0, -1.0, -1.0));
+    assert_eq!(moved.max, Vec3::new(4.0, 1.0, 1.0));
+    assert!(Box3::empty().apply_matrix(&Mat4::identity()).is_empty());
+  }
+
+  #[test]
+  fn boxes_beyond_a_clip_plane_are_outside() {
+    let projection = orthographic(-2.0, 2.0, -2.0, 2.0, 0.1, 10.0);
+    let at = |x: f32| unit().apply_matrix(&apply_translate(&Vec3::new(x, 0.0, -5.0)));
+
+    assert!(at(0.0).intersects_clip_volume(&projection));
+    // straddling the right plane
+    assert!(at(2.5).intersects_clip_volume(&projection));
+    assert!(!at(4.0).intersects_clip_volume(&projection));
+    assert!(!Box3::empty().intersects_clip_volume(&projection));
+  }
+}
@@ -64,7 +64,7 @@ impl DataArray<f32> {
     self.data[(x + y * self.w) as usize] = value;
   }
 
-  pub fn get(&mut self, x: u32, y: u32) -> f32 {
+  pub fn get(&self, x: u32, y: u32) -> f32 {
     self.data[(x + y * self.w) as usize]
   }
 }
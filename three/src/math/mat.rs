@@ -223,6 +223,12 @@ impl Mat3 {
 
   pub fn get_orthogonal_basis(eye: Vec3, target: Vec3, up: Vec3) -> Self {
     let z = (eye - target).normalize();
+    // fall back to another up axis when looking straight along it
+    let up = if up.normalize().cross(&z).length_square() <= f32::EPSILON {
+      Vec3::new(up.z, up.x, up.y)
+    } else {
+      up
+    };
     let x = up.cross(&z).normalize();
     let y = z.cross(&x).normalize();
     Self::from_row(&[x.x, x.y, x.z, y.x, y.y, y.z, z.x, z.y, z.z])
@@ -317,8 +323,52 @@ pub fn apply_scale(scale: &Vec3) -> Mat4 {
   ])
 }
 
+/// world to view transform of an eye at `eye` looking at `target`
+#[rustfmt::skip]
+pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+  let z = (eye - target).normalize();
+  // fall back to another up axis when looking straight along it
+  let up = if up.normalize().cross(&z).length_square() <= f32::EPSILON {
+    Vec3::new(up.z, up.x, up.y)
+  } else {
+    up
+  };
+  let x = up.cross(&z).normalize();
+  let y = z.cross(&x);
+
+  Mat4::from_row(&[
+    x.x, x.y, x.z, -x.dot(&eye),
+    y.x, y.y, y.z, -y.dot(&eye),
+    z.x, z.y, z.z, -z.dot(&eye),
+    0.0, 0.0, 0.0, 1.0,
+  ])
+}
+
+/// maps the box into the [-1, 1] cube, looking along -z
+#[rustfmt::skip]
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+  Mat4::from_row(&[
+    2.0 / (right - left), 0.0                 , 0.0                , -(right + left) / (right - left),
+    0.0                 , 2.0 / (top - bottom), 0.0                , -(top + bottom) / (top - bottom),
+    0.0                 , 0.0                 , -2.0 / (far - near), -(far + near) / (far - near),
+    0.0                 , 0.0                 , 0.0                , 1.0,
+  ])
+}
+
+/// @param fov vertical field of view in radians
+#[rustfmt::skip]
+pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+  let f = 1.0 / (fov / 2.0).tan();
+  Mat4::from_row(&[
+    f / aspect, 0.0, 0.0                        , 0.0,
+    0.0       , f  , 0.0                        , 0.0,
+    0.0       , 0.0, (far + near) / (near - far), 2.0 * far * near / (near - far),
+    0.0       , 0.0, -1.0                       , 0.0,
+  ])
+}
+
 pub fn extract_position(mat: Mat4) -> Vec3 {
-  Vec3::new(mat.get(0, 3), mat.get(1, 3), mat.get(2, 3))
+  Vec3::new(mat.get(3, 0), mat.get(3, 1), mat.get(3, 2))
 }
 
 pub fn extract_scale(mat: Mat4) -> Vec3 {
@@ -336,9 +386,9 @@ pub fn extract_scale(mat: Mat4) -> Vec3 {
 pub fn decompose(mat: Mat4) -> (Vec3, Mat4, Vec3) {
   let scale = extract_scale(mat);
   let position = extract_position(mat);
-  let mut rotate_matrix = Mat4::zeros();
+  let mut rotate_matrix = Mat4::identity();
   let scales = [scale.x, scale.y, scale.z];
-  for i in 0..2 {
+  for i in 0..3 {
     rotate_matrix.set_col(i as usize, mat.get_col(i as usize) / scales[i]);
   }
 
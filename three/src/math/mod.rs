@@ -6,6 +6,8 @@ pub use self::vec::*;
 pub use barycentric::Barycentric;
 mod boundary_box;
 pub use self::boundary_box::BoundaryBox;
+mod box3;
+pub use self::box3::Box3;
 pub mod data_array;
 pub mod euler;
 pub mod frustum;
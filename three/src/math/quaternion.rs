@@ -1,7 +1,7 @@
-use std::ops::{Div, Mul};
+use std::ops::{Add, Div, Mul};
 
 use super::{Mat4, Vec3, Vec4};
-#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Quaternion {
   /// q = w + xi + yj + zk
   pub(super) w: f32,
@@ -10,6 +10,12 @@ pub struct Quaternion {
   pub(super) z: f32,
 }
 
+impl Default for Quaternion {
+  fn default() -> Self {
+    Self::identity()
+  }
+}
+
 impl Mul<f32> for Quaternion {
   type Output = Self;
 
@@ -30,6 +36,14 @@ impl Mul<Quaternion> for Quaternion {
   }
 }
 
+impl Add for Quaternion {
+  type Output = Self;
+
+  fn add(self, rhs: Self) -> Self::Output {
+    Self::new(self.w + rhs.w, self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+  }
+}
+
 impl Div<f32> for Quaternion {
   type Output = Self;
 
@@ -69,7 +83,7 @@ impl Quaternion {
   }
 
   pub fn conjugate(&self) -> Self {
-    Self::new(-self.x, -self.y, -self.z, self.w)
+    Self::new(self.w, -self.x, -self.y, -self.z)
   }
 
   pub fn inverse(&self) -> Self {
@@ -79,6 +93,40 @@ impl Quaternion {
   pub fn identity() -> Self {
     Self::new(1.0, 0.0, 0.0, 0.0)
   }
+
+  pub fn normalize(&self) -> Self {
+    let length = self.length();
+    if length <= f32::EPSILON {
+      return Self::identity();
+    }
+    *self / length
+  }
+
+  /// spherical interpolation along the shorter arc
+  pub fn slerp(&self, other: Self, t: f32) -> Self {
+    let mut cos = self.dot(other);
+    let other = if cos < 0.0 {
+      cos = -cos;
+      other * -1.0
+    } else {
+      other
+    };
+
+    // close enough for a straight line, avoids dividing by a tiny sine
+    if cos > 0.9995 {
+      let res = *self * (1.0 - t) + other * t;
+      return res.normalize();
+    }
+
+    let angle = cos.acos();
+    let sin = angle.sin();
+    *self * (((1.0 - t) * angle).sin() / sin) + other * ((t * angle).sin() / sin)
+  }
+
+  /// rotate `v` by this unit quaternion
+  pub fn apply(&self, v: Vec3) -> Vec3 {
+    (self.make_rotate_matrix() * Vec4::from_vec3(&v, 0.0)).truncated_to_vec3()
+  }
 }
 
 impl Quaternion {
@@ -97,7 +145,7 @@ impl Quaternion {
 
     res.set_col(0, Vec4::new(1.0 - (yy + zz), xy + wz, xz - wy, 0.0));
     res.set_col(1, Vec4::new(xy - wz, 1.0 - (xx + zz), yz + wx, 0.0));
-    res.set_col(2, Vec4::new(xz + wy, yz - wx, xx + yy, 0.0));
+    res.set_col(2, Vec4::new(xz + wy, yz - wx, 1.0 - (xx + yy), 0.0));
 
     res
   }
@@ -1,5 +1,5 @@
 use super::{euler::Euler, quaternion::Quaternion, Mat4, Vec3};
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Rotation {
   pub quaternion: Quaternion,
   pub euler: Euler,
@@ -0,0 +1,13 @@
+use crate::core::object_3d::ObjectActions;
+
+use renderer_macro_derive::object_3d;
+
+/// A joint of a `Skeleton`, posed through its transform like any other object.
+#[object_3d(ObjectActions, Bone)]
+pub struct Bone {}
+
+impl Bone {
+  pub fn new() -> std::rc::Rc<Self> {
+    Self::with_fields()
+  }
+}
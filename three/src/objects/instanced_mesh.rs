@@ -0,0 +1,219 @@
+use std::{cell::RefCell, rc::Rc};
+
+use renderer_macro_derive::object_3d;
+
+use super::mesh::impl_mesh_actions;
+use crate::{
+  core::{
+    buffer_geometry::{BufferGeometry, GeometryActions},
+    object_3d::ObjectActions,
+    unifrom::{Uniform, UnifromTypeEnum},
+  },
+  material::material::MaterialActions,
+  math::{Box3, Mat4, Vec3},
+};
+
+/// One geometry and material drawn many times, each instance placed by its own
+/// matrix relative to the mesh. Instances aren't scene objects, they have no
+/// children, names or events.
+#[object_3d(ObjectActions, Mesh, Drawable)]
+pub struct InstancedMesh {
+  geometry: Rc<BufferGeometry>,
+  material: Rc<dyn MaterialActions>,
+  morph_target_influences: RefCell<Vec<f32>>,
+  instance_matrices: RefCell<Vec<Mat4>>,
+  /// `None` until the color of an instance is set
+  instance_colors: RefCell<Option<Vec<Vec3>>>,
+  /// how many of the instances are drawn
+  count: RefCell<usize>,
+}
+
+impl InstancedMesh {
+  /// `count` instances, all at the origin of the mesh
+  pub fn new(
+    geometry: BufferGeometry,
+    material: impl MaterialActions + 'static,
+    count: usize,
+  ) -> Rc<Self> {
+    let influences = vec![0.0; geometry.morph_target_count()];
+    Self::with_fields(
+      Rc::new(geometry),
+      Rc::new(material),
+      RefCell::new(influences),
+      RefCell::new(vec![Mat4::identity(); count]),
+      RefCell::new(None),
+      RefCell::new(count),
+    )
+  }
+
+  pub fn geometry(&self) -> &BufferGeometry {
+    &self.geometry
+  }
+
+  pub fn material(&self) -> &dyn MaterialActions {
+    self.material.as_ref()
+  }
+
+  /// the number of instances there is room for
+  pub fn capacity(&self) -> usize {
+    self.instance_matrices.borrow().len()
+  }
+
+  pub fn count(&self) -> usize {
+    *self.count.borrow()
+  }
+
+  /// draw only the first `count` instances, at most `capacity`
+  pub fn set_count(&self, count: usize) {
+    *self.count.borrow_mut() = count.min(self.capacity());
+  }
+
+  pub fn get_matrix_at(&self, index: usize) -> Mat4 {
+    self.instance_matrices.borrow()[index]
+  }
+
+  pub fn set_matrix_at(&self, index: usize, matrix: Mat4) {
+    self.instance_matrices.borrow_mut()[index] = matrix;
+  }
+
+  pub fn get_color_at(&self, index: usize) -> Option<Vec3> {
+    self
+      .instance_colors
+      .borrow()
+      .as_ref()
+      .map(|colors| colors[index])
+  }
+
+  /// the color is multiplied with the color of the material,
+  /// instances without one are white
+  pub fn set_color_at(&self, index: usize, color: Vec3) {
+    let capacity = self.capacity();
+    let mut colors = self.instance_colors.borrow_mut();
+    colors.get_or_insert_with(|| vec![Vec3::new(1.0, 1.0, 1.0); capacity])[index] = color;
+  }
+
+  /// the union of the geometry's bounds placed at every drawn instance, in the
+  /// space of the mesh
+  pub fn instances_bounding_box(&self) -> Box3 {
+    let bounds = self.geometry.compute_bounding_box();
+    self.instance_matrices.borrow()[..self.count()]
+      .iter()
+      .fold(Box3::empty(), |union, matrix| {
+        union.union(&bounds.apply_matrix(matrix))
+      })
+  }
+
+  /// hand `instance_matrix`, `instance_id` and `instance_color` of instance `index`
+  /// to the shaders
+  pub fn set_instance_uniform(&self, index: usize, uniform: &mut Uniform) {
+    uniform.insert(
+      "instance_matrix".to_string(),
+      UnifromTypeEnum::from(self.get_matrix_at(index)),
+    );
+    uniform.insert(
+      "instance_id".to_string(),
+      UnifromTypeEnum::from(index as i32),
+    );
+    match self.get_color_at(index) {
+      Some(color) => uniform.insert("instance_color".to_string(), UnifromTypeEnum::from(color)),
+      None => uniform.remove("instance_color"),
+    };
+  }
+}
+
+impl_mesh_actions!(InstancedMesh, {
+  fn for_each_instance(&self, uniform: &mut Uniform, draw: &mut dyn FnMut(&Uniform)) {
+    for index in 0..self.count() {
+      self.set_instance_uniform(index, uniform);
+      draw(uniform);
+    }
+  }
+
+  fn bounding_box(&self) -> Option<Box3> {
+    Some(self.instances_bounding_box())
+  }
+});
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    core::{buffer_attribute::F32BufferAttribute, unifrom::u, Extract},
+    material::depth_material::MeshDepthMaterial,
+    math::apply_translate,
+    objects::{bounding_box, mesh::Mesh, Drawable},
+  };
+
+  fn instances(count: usize) -> Rc<InstancedMesh> {
+    let mut geometry = BufferGeometry::default();
+    let position = vec![-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
+    geometry.set_attribute(
+      "position",
+      F32BufferAttribute::new(position, 3, false).as_enum(),
+    );
+    let mesh = InstancedMesh::new(geometry, MeshDepthMaterial::default(), count);
+    for i in 0..count {
+      mesh.set_matrix_at(i, apply_translate(&Vec3::new(i as f32 * 10.0, 0.0, 0.0)));
+    }
+    mesh
+  }
+
+  #[test]
+  fn bounds_cover_the_drawn_instances() {
+    let mesh = instances(3);
+    let bounds = mesh.instances_bounding_box();
+    assert_eq!(bounds.min, Vec3::new(-1.0, -1.0, 0.0));
+    assert_eq!(bounds.max, Vec3::new(21.0, 1.0, 0.0));
+
+    mesh.set_count(5);
+    assert_eq!(mesh.count(), 3);
+    mesh.set_count(1);
+    assert_eq!(mesh.instances_bounding_box().max, Vec3::new(1.0, 1.0, 0.0));
+
+    mesh.set_count(0);
+    assert!(mesh.instances_bounding_box().is_empty());
+  }
+
+  #[test]
+  fn culling_bounds_are_in_world_space() {
+    let mesh = instances(2);
+    mesh.set_position(Vec3::new(0.0, 5.0, 0.0));
+    mesh.update_global_matrix();
+
+    let bounds = bounding_box(mesh.as_ref()).unwrap();
+    assert_eq!(bounds.min, Vec3::new(-1.0, 4.0, 0.0));
+    assert_eq!(bounds.max, Vec3::new(11.0, 6.0, 0.0));
+  }
+
+  #[test]
+  fn every_instance_is_drawn_with_its_own_uniforms() {
+    let mesh = instances(2);
+    assert_eq!(mesh.get_color_at(0), None);
+    mesh.set_color_at(1, Vec3::new(1.0, 0.0, 0.0));
+    assert_eq!(mesh.get_color_at(0), Some(Vec3::new(1.0, 1.0, 1.0)));
+
+    let mut uniform = Uniform::default();
+    let mut drawn = vec![];
+    mesh.for_each_instance(&mut uniform, &mut |uniform| {
+      drawn.push((
+        u!(uniform, i32, "instance_id"),
+        u!(uniform, Vec3, "instance_color"),
+      ));
+    });
+    assert_eq!(
+      drawn,
+      [
+        (Some(0), Some(Vec3::new(1.0, 1.0, 1.0))),
+        (Some(1), Some(Vec3::new(1.0, 0.0, 0.0))),
+      ]
+    );
+
+    let mut draws = 0;
+    let plain = Mesh::new(BufferGeometry::default(), MeshDepthMaterial::default());
+    plain.for_each_instance(&mut uniform, &mut |uniform| {
+      assert!(uniform.get("instance_id").is_none());
+      draws += 1;
+    });
+    assert_eq!(draws, 1);
+  }
+}
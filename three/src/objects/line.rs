@@ -0,0 +1,67 @@
+use std::{cell::RefCell, rc::Rc};
+
+use renderer_macro_derive::object_3d;
+
+use super::mesh::impl_mesh_actions;
+use crate::{
+  core::{
+    buffer_geometry::{BufferGeometry, GeometryActions},
+    object_3d::ObjectActions,
+  },
+  material::material::{DrawMode, MaterialActions},
+};
+
+macro_rules! define_line {
+  ($(#[$doc:meta])* $name:ident, $mode:ident) => {
+    $(#[$doc])*
+    #[object_3d(ObjectActions, Line, Drawable)]
+    pub struct $name {
+      geometry: Rc<BufferGeometry>,
+      material: Rc<dyn MaterialActions>,
+      morph_target_influences: RefCell<Vec<f32>>,
+    }
+
+    impl $name {
+      pub fn new(geometry: BufferGeometry, material: impl MaterialActions + 'static) -> Rc<Self> {
+        let influences = vec![0.0; geometry.morph_target_count()];
+        Self::with_fields(
+          Rc::new(geometry),
+          Rc::new(material),
+          RefCell::new(influences),
+        )
+      }
+
+      pub fn geometry(&self) -> &BufferGeometry {
+        &self.geometry
+      }
+
+      pub fn material(&self) -> &dyn MaterialActions {
+        self.material.as_ref()
+      }
+    }
+
+    impl_mesh_actions!($name, {
+      fn draw_mode(&self) -> DrawMode {
+        DrawMode::$mode
+      }
+    });
+  };
+}
+
+define_line!(
+  /// A line through every vertex of the geometry, in order.
+  Line,
+  LineStrip
+);
+
+define_line!(
+  /// A separate line for every two vertices of the geometry.
+  LineSegments,
+  Lines
+);
+
+define_line!(
+  /// A `Line` that goes back from the last vertex to the first one.
+  LineLoop,
+  LineLoop
+);
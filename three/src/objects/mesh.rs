@@ -1,29 +1,184 @@
 use renderer_macro_derive::object_3d;
 
-use super::super::material::material::BasicMaterial;
-
-use super::super::core::buffer_geometry::BufferGeometry;
-use super::super::core::object_3d::{
-  /* define_support_objects, */ with_default_fields, ObjectActions,
+use super::super::core::{
+  buffer_geometry::{BufferGeometry, GeometryActions},
+  object_3d::ObjectActions,
 };
-// use super::group::Group;
-
-// define_support_objects!(
-//   MeshSupportChildren;
-//   Mesh:Mesh,
-//   Group:Group
-// );
-
-// #[object_3d(ObjectActions)]
-// pub struct Mesh {
-//   geometry: BufferGeometry,
-//   material: BasicMaterial,
-// }
-
-// impl Mesh {
-//   pub fn new() -> Self {
-//     let geometry = Default::default();
-//     let material = Default::default();
-//     with_default_fields!(geometry, material)
-//   }
-// }
+use super::super::material::material::MaterialActions;
+
+/// A geometry drawn with a material, what ends up in a `RenderList`.
+#[object_3d(ObjectActions, Mesh, Drawable)]
+pub struct Mesh {
+  /// shared with the clones of this mesh
+  geometry: std::rc::Rc<BufferGeometry>,
+  material: std::rc::Rc<dyn MaterialActions>,
+  morph_target_influences: std::cell::RefCell<Vec<f32>>,
+}
+
+impl Mesh {
+  pub fn new(
+    geometry: BufferGeometry,
+    material: impl MaterialActions + 'static,
+  ) -> std::rc::Rc<Self> {
+    let influences = vec![0.0; geometry.morph_target_count()];
+    Self::with_fields(
+      std::rc::Rc::new(geometry),
+      std::rc::Rc::new(material),
+      std::cell::RefCell::new(influences),
+    )
+  }
+
+  pub fn geometry(&self) -> &BufferGeometry {
+    &self.geometry
+  }
+
+  pub fn material(&self) -> &dyn MaterialActions {
+    self.material.as_ref()
+  }
+}
+
+/// The weights a mesh blends the morph targets of its geometry with.
+pub trait MorphTargetActions {
+  fn morph_target_influences(&self) -> Vec<f32>;
+  fn set_morph_target_influences(&self, influences: Vec<f32>);
+  /// set a single weight, missing ones up to `index` become 0
+  fn set_morph_target_influence(&self, index: usize, influence: f32);
+  /// the index of the target named `name` in the geometry
+  fn morph_target_index(&self, name: &str) -> Option<usize>;
+}
+
+/// Forward `GeometryActions` and the material traits of a mesh-like object to its
+/// `geometry` and `material` fields, the morph target weights are kept in
+/// `morph_target_influences`. The items in braces are added to its `Drawable` impl.
+macro_rules! impl_mesh_actions {
+  ($name:ty $(, { $($drawable:tt)* })?) => {
+    impl crate::objects::Drawable for $name {
+      fn geometry(&self) -> &dyn crate::core::buffer_geometry::GeometryActions {
+        self.geometry.as_ref()
+      }
+
+      fn material(&self) -> &dyn crate::material::material::MaterialActions {
+        self.material.as_ref()
+      }
+
+      fn morph_targets(&self) -> Option<&dyn crate::objects::mesh::MorphTargetActions> {
+        Some(self)
+      }
+
+      $($($drawable)*)?
+    }
+
+    impl crate::core::buffer_geometry::GeometryActions for $name {
+      fn get_attribute(&self) -> &crate::core::buffer_geometry::Attribute {
+        self.geometry.get_attribute()
+      }
+
+      fn get_morph_attributes(&self) -> &crate::core::buffer_geometry::MorphAttributes {
+        self.geometry.get_morph_attributes()
+      }
+
+      fn set_attribute(&mut self, key: &str, val: crate::core::buffer_attribute::TypeBufferEnum) {
+        std::rc::Rc::get_mut(&mut self.geometry)
+          .expect("the geometry is shared with a clone of the mesh")
+          .set_attribute(key, val)
+      }
+    }
+
+    impl crate::material::material::ConvertUniform for $name {
+      fn to_uniform(&self) -> crate::core::unifrom::Uniform {
+        self.material.to_uniform()
+      }
+    }
+
+    impl crate::material::material::DrawGeometry for $name {
+      fn draw(
+        &self,
+        geometry: &dyn crate::core::buffer_geometry::GeometryActions,
+        mode: crate::material::material::DrawMode,
+        uniform: &crate::core::unifrom::Uniform,
+        viewport_matrix: &crate::math::Mat4,
+        target: &mut crate::renderer::render_target::RenderTarget,
+      ) {
+        self
+          .material
+          .draw(geometry, mode, uniform, viewport_matrix, target)
+      }
+    }
+
+    impl crate::material::material::MaterialActions for $name {}
+
+    impl crate::objects::mesh::MorphTargetActions for $name {
+      fn morph_target_influences(&self) -> Vec<f32> {
+        self.morph_target_influences.borrow().clone()
+      }
+
+      fn set_morph_target_influences(&self, influences: Vec<f32>) {
+        *self.morph_target_influences.borrow_mut() = influences;
+      }
+
+      fn set_morph_target_influence(&self, index: usize, influence: f32) {
+        let mut influences = self.morph_target_influences.borrow_mut();
+        if influences.len() <= index {
+          influences.resize(index + 1, 0.0);
+        }
+        influences[index] = influence;
+      }
+
+      fn morph_target_index(&self, name: &str) -> Option<usize> {
+        let names = &self.geometry.get_morph_attributes().names;
+        names.iter().position(|n| n == name)
+      }
+    }
+  };
+}
+
+pub(crate) use impl_mesh_actions;
+
+impl_mesh_actions!(Mesh);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    core::{buffer_attribute::F32BufferAttribute, unifrom::Uniform},
+    material::depth_material::MeshDepthMaterial,
+    objects::Drawable,
+  };
+
+  fn morphed_mesh() -> std::rc::Rc<Mesh> {
+    let delta = || F32BufferAttribute::new(vec![0.0, 1.0, 0.0], 3, false).as_enum();
+    let mut geometry = BufferGeometry::default();
+    geometry.set_morph_attribute("position", vec![delta(), delta()]);
+    geometry.set_morph_target_names(vec!["open".to_string(), "smile".to_string()]);
+    Mesh::new(geometry, MeshDepthMaterial::default())
+  }
+
+  #[test]
+  fn meshes_start_with_a_zero_weight_per_target() {
+    let mesh = morphed_mesh();
+    assert_eq!(mesh.morph_target_influences(), [0.0, 0.0]);
+    assert_eq!(mesh.morph_target_index("smile"), Some(1));
+    assert_eq!(mesh.morph_target_index("frown"), None);
+
+    mesh.set_morph_target_influence(3, 0.5);
+    assert_eq!(mesh.morph_target_influences(), [0.0, 0.0, 0.0, 0.5]);
+  }
+
+  #[test]
+  fn the_weights_reach_the_vertex_stage() {
+    let mesh = morphed_mesh();
+    let drawable = mesh.as_drawable().unwrap();
+    drawable
+      .morph_targets()
+      .unwrap()
+      .set_morph_target_influences(vec![0.25, 1.0]);
+
+    let mut uniform = Uniform::default();
+    drawable.set_deform_uniform(&mut uniform);
+    assert_eq!(*uniform.morph_target_influences(), [0.25, 1.0]);
+
+    let plain = (drawable.geometry(), drawable.material());
+    plain.set_deform_uniform(&mut uniform);
+    assert!(uniform.morph_target_influences().is_empty());
+  }
+}
@@ -1,3 +1,130 @@
+pub mod bone;
 pub mod mesh;
 pub mod scene;
-pub mod group;
\ No newline at end of file
+pub mod group;
+pub mod instanced_mesh;
+pub mod line;
+pub mod points;
+pub mod skeleton;
+pub mod skeleton_helper;
+pub mod skinned_mesh;
+
+use self::mesh::MorphTargetActions;
+use crate::{
+  core::{buffer_geometry::GeometryActions, object_3d::ObjectActions, unifrom::Uniform},
+  material::material::{DrawMode, MaterialActions},
+  math::Box3,
+};
+
+/// A scene object the renderer draws, `ObjectActions::as_drawable` hands it out.
+/// The defaults fit a static mesh of triangles.
+pub trait Drawable {
+  fn geometry(&self) -> &dyn GeometryActions;
+  fn material(&self) -> &dyn MaterialActions;
+
+  /// the primitives the vertices are assembled into
+  fn draw_mode(&self) -> DrawMode {
+    DrawMode::Triangles
+  }
+
+  /// `None` for objects without morph target weights
+  fn morph_targets(&self) -> Option<&dyn MorphTargetActions> {
+    None
+  }
+
+  /// hand the bones to the vertex stage, replacing whatever the previous object left
+  fn set_skinning_uniform(&self, uniform: &mut Uniform) {
+    uniform.set_bone_matrices(vec![]);
+  }
+
+  /// the bones and morph target weights for the vertex stage
+  fn set_deform_uniform(&self, uniform: &mut Uniform) {
+    self.set_skinning_uniform(uniform);
+    let influences = self
+      .morph_targets()
+      .map_or(vec![], |m| m.morph_target_influences());
+    uniform.set_morph_target_influences(influences);
+  }
+
+  /// run `draw` once per instance with the instance's uniforms set
+  fn for_each_instance(&self, uniform: &mut Uniform, draw: &mut dyn FnMut(&Uniform)) {
+    for key in ["instance_matrix", "instance_id", "instance_color"] {
+      uniform.remove(key);
+    }
+    draw(uniform);
+  }
+
+  /// the local bounds the renderer culls with, `None` for objects that are never culled
+  /// because their vertices move in the vertex stage
+  fn bounding_box(&self) -> Option<Box3> {
+    Some(self.geometry().compute_bounding_box())
+  }
+}
+
+/// A geometry drawn with a material as is, for objects that aren't drawable themselves.
+impl Drawable for (&dyn GeometryActions, &dyn MaterialActions) {
+  fn geometry(&self) -> &dyn GeometryActions {
+    self.0
+  }
+
+  fn material(&self) -> &dyn MaterialActions {
+    self.1
+  }
+}
+
+/// the world space bounds of a drawable `object`, see `Drawable::bounding_box`
+pub fn bounding_box(object: &dyn ObjectActions) -> Option<Box3> {
+  let bounds = object.as_drawable()?.bounding_box()?;
+  Some(bounds.apply_matrix(&object.global_matrix()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    core::buffer_geometry::BufferGeometry,
+    lights::directional_light::DirectionalLight,
+    material::depth_material::MeshDepthMaterial,
+    math::Vec4,
+    objects::{
+      group::Group,
+      line::{Line, LineLoop, LineSegments},
+      mesh::Mesh,
+      points::Points,
+      skinned_mesh::SkinnedMesh,
+    },
+  };
+
+  fn draw_mode(object: &dyn ObjectActions) -> Option<DrawMode> {
+    object.as_drawable().map(|drawable| drawable.draw_mode())
+  }
+
+  #[test]
+  fn objects_are_drawn_as_they_declare() {
+    let (geometry, material) = (BufferGeometry::default, MeshDepthMaterial::default);
+    let mesh = Mesh::new(geometry(), material());
+    assert_eq!(draw_mode(mesh.as_ref()), Some(DrawMode::Triangles));
+    let line = Line::new(geometry(), material());
+    assert_eq!(draw_mode(line.as_ref()), Some(DrawMode::LineStrip));
+    let segments = LineSegments::new(geometry(), material());
+    assert_eq!(draw_mode(segments.as_ref()), Some(DrawMode::Lines));
+    let line_loop = LineLoop::new(geometry(), material());
+    assert_eq!(draw_mode(line_loop.as_ref()), Some(DrawMode::LineLoop));
+    let points = Points::new(geometry(), material());
+    assert_eq!(draw_mode(points.as_ref()), Some(DrawMode::Points));
+    assert_eq!(draw_mode(Group::new().as_ref()), None);
+
+    // skinned vertices move in the vertex stage, they are never culled
+    let skinned = SkinnedMesh::new(geometry(), material());
+    assert!(bounding_box(skinned.as_ref()).is_none());
+    assert!(bounding_box(mesh.as_ref()).is_some());
+  }
+
+  #[test]
+  fn only_lights_are_viewed_as_lights() {
+    let light = DirectionalLight::new(Vec4::new(1.0, 1.0, 1.0, 1.0), 1.0);
+    assert!(light.as_light().is_some());
+    assert!(light.as_drawable().is_none());
+    assert!(Group::new().as_light().is_none());
+  }
+}
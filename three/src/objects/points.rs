@@ -0,0 +1,46 @@
+use std::{cell::RefCell, rc::Rc};
+
+use renderer_macro_derive::object_3d;
+
+use super::mesh::impl_mesh_actions;
+use crate::{
+  core::{
+    buffer_geometry::{BufferGeometry, GeometryActions},
+    object_3d::ObjectActions,
+  },
+  material::material::{DrawMode, MaterialActions},
+};
+
+/// A square facing the camera at every vertex of the geometry,
+/// `gl_point_size` pixels wide.
+#[object_3d(ObjectActions, Point, Drawable)]
+pub struct Points {
+  geometry: Rc<BufferGeometry>,
+  material: Rc<dyn MaterialActions>,
+  morph_target_influences: RefCell<Vec<f32>>,
+}
+
+impl Points {
+  pub fn new(geometry: BufferGeometry, material: impl MaterialActions + 'static) -> Rc<Self> {
+    let influences = vec![0.0; geometry.morph_target_count()];
+    Self::with_fields(
+      Rc::new(geometry),
+      Rc::new(material),
+      RefCell::new(influences),
+    )
+  }
+
+  pub fn geometry(&self) -> &BufferGeometry {
+    &self.geometry
+  }
+
+  pub fn material(&self) -> &dyn MaterialActions {
+    self.material.as_ref()
+  }
+}
+
+impl_mesh_actions!(Points, {
+  fn draw_mode(&self) -> DrawMode {
+    DrawMode::Points
+  }
+});
@@ -1,15 +1,12 @@
 use renderer_macro_derive::object_3d;
 
-use super::{
-  super::core::object_3d::{with_default_fields, ObjectActions},
-  // group::GroupSupportChildren,
-};
+use super::super::core::object_3d::ObjectActions;
 
-// #[object_3d(ObjectActions)]
+#[object_3d(ObjectActions, Scene)]
 pub struct Scene {}
 
-// impl Scene {
-//   pub fn new() -> Self {
-//     with_default_fields![]
-//   }
-// }
+impl Scene {
+  pub fn new() -> std::rc::Rc<Self> {
+    Self::with_fields()
+  }
+}
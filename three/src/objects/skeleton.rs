@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use super::bone::Bone;
+use crate::{
+  core::object_3d::ObjectActions,
+  math::{Mat4, Quaternion, Vec3},
+};
+
+/// The bones a `SkinnedMesh` is bound to, with the inverse of each bone's global
+/// matrix at bind time.
+#[derive(Clone)]
+pub struct Skeleton {
+  bones: Vec<Rc<Bone>>,
+  bone_inverses: Vec<Mat4>,
+}
+
+impl Skeleton {
+  /// without `bone_inverses` the current pose of the bones becomes the bind pose
+  pub fn new(bones: Vec<Rc<Bone>>, bone_inverses: Option<Vec<Mat4>>) -> Self {
+    let mut skeleton = Self {
+      bones,
+      bone_inverses: vec![],
+    };
+
+    match bone_inverses {
+      Some(bone_inverses) => {
+        assert_eq!(
+          bone_inverses.len(),
+          skeleton.bones.len(),
+          "a skeleton needs one inverse bind matrix per bone"
+        );
+        skeleton.bone_inverses = bone_inverses;
+      }
+      None => skeleton.calculate_inverses(),
+    }
+
+    skeleton
+  }
+
+  pub fn bones(&self) -> &Vec<Rc<Bone>> {
+    &self.bones
+  }
+
+  pub fn bone_inverses(&self) -> &Vec<Mat4> {
+    &self.bone_inverses
+  }
+
+  pub fn get_bone_by_name(&self, name: &str) -> Option<Rc<Bone>> {
+    self.bones.iter().find(|bone| bone.name() == name).cloned()
+  }
+
+  /// take the current pose of the bones as the bind pose
+  pub fn calculate_inverses(&mut self) {
+    self.bone_inverses = self
+      .bones
+      .iter()
+      .map(|bone| {
+        bone.sync_global_matrix(true, false);
+        bone
+          .global_matrix()
+          .inverse()
+          .unwrap_or_else(Mat4::identity)
+      })
+      .collect();
+  }
+
+  /// move the bones back into the bind pose, parents have to come before their children
+  pub fn pose(&self) {
+    for (bone, inverse) in self.bones.iter().zip(self.bone_inverses.iter()) {
+      let global = inverse.inverse().unwrap_or_else(Mat4::identity);
+      let local = match bone.parent() {
+        Some(parent) if parent.as_any().is::<Bone>() => {
+          parent
+            .global_matrix()
+            .inverse()
+            .unwrap_or_else(Mat4::identity)
+            * global
+        }
+        _ => global,
+      };
+
+      bone.set_position(Vec3::zero());
+      bone.set_quaternion(Quaternion::default());
+      bone.set_scale(Vec3::new(1.0, 1.0, 1.0));
+      bone.apply_matrix(local);
+      bone.sync_global_matrix(false, false);
+    }
+  }
+
+  /// how far each bone moved from the bind pose, in world space
+  pub fn bone_matrices(&self) -> Vec<Mat4> {
+    self
+      .bones
+      .iter()
+      .zip(self.bone_inverses.iter())
+      .map(|(bone, inverse)| bone.global_matrix() * *inverse)
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::math::{extract_position, Vec4};
+
+  fn arm() -> (Rc<Bone>, Rc<Bone>, Skeleton) {
+    let shoulder = Bone::new();
+    let elbow = Bone::new();
+    shoulder.set_name("shoulder");
+    elbow.set_name("elbow");
+    shoulder.set_position(Vec3::new(0.0, 1.0, 0.0));
+    elbow.set_position(Vec3::new(0.0, 1.0, 0.0));
+    shoulder.add(elbow.clone());
+    shoulder.update_global_matrix();
+
+    let skeleton = Skeleton::new(vec![shoulder.clone(), elbow.clone()], None);
+    (shoulder, elbow, skeleton)
+  }
+
+  fn moves(matrix: Mat4, point: Vec3) -> Vec3 {
+    (matrix * Vec4::from_vec3(&point, 1.0)).truncated_to_vec3()
+  }
+
+  #[test]
+  fn the_bind_pose_does_not_move_vertices() {
+    let (_, _, skeleton) = arm();
+    let point = Vec3::new(1.0, 2.0, 3.0);
+    for matrix in skeleton.bone_matrices() {
+      assert!((moves(matrix, point) - point).length() < 1e-5);
+    }
+    assert!(skeleton.get_bone_by_name("elbow").is_some());
+    assert!(skeleton.get_bone_by_name("wrist").is_none());
+  }
+
+  #[test]
+  fn bone_matrices_follow_the_pose() {
+    let (shoulder, elbow, skeleton) = arm();
+    shoulder.set_position(Vec3::new(2.0, 1.0, 0.0));
+    elbow.set_scale(Vec3::new(2.0, 2.0, 2.0));
+    shoulder.update_global_matrix();
+
+    let matrices = skeleton.bone_matrices();
+    assert!((extract_position(matrices[0]) - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+    // scaled about the elbow at y = 2
+    let moved = moves(matrices[1], Vec3::new(0.0, 3.0, 0.0));
+    assert!((moved - Vec3::new(2.0, 4.0, 0.0)).length() < 1e-5);
+  }
+
+  #[test]
+  fn pose_restores_the_bind_pose() {
+    let (shoulder, elbow, skeleton) = arm();
+    shoulder.set_position(Vec3::new(5.0, 0.0, 0.0));
+    elbow.rotate_z(1.0);
+    shoulder.update_global_matrix();
+
+    skeleton.pose();
+
+    assert!((shoulder.position() - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+    assert!((elbow.global_position() - Vec3::new(0.0, 2.0, 0.0)).length() < 1e-5);
+    let point = Vec3::new(1.0, 2.0, 3.0);
+    assert!((moves(skeleton.bone_matrices()[1], point) - point).length() < 1e-4);
+  }
+}
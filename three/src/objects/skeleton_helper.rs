@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use renderer_macro_derive::object_3d;
+
+use super::{bone::Bone, Drawable};
+use crate::{
+  core::{
+    buffer_attribute::{a, F32BufferAttribute},
+    buffer_geometry::{Attribute, BufferGeometry, GeometryActions},
+    object_3d::ObjectActions,
+    unifrom::{u, Uniform, UnifromTypeEnum},
+    varying::Varyings,
+  },
+  material::{
+    material::{ConvertUniform, DepthState, DrawGeometry, DrawMode, MaterialActions},
+    shader::{DefineTypedShader, GlPerFragment, GlPerVertex},
+    skinning::skin_matrix,
+  },
+  math::{Box3, Mat4, Vec3, Vec4},
+  renderer::{rasterizer::draw_typed_geometry, render_target::RenderTarget},
+};
+
+/// Lines from every bone below an object to its parent bone, for checking rigs.
+/// The lines follow the bones the same way skinned vertices do.
+#[object_3d(ObjectActions, Line, Drawable)]
+pub struct SkeletonHelper {
+  bones: Vec<Rc<dyn ObjectActions>>,
+  geometry: Rc<BufferGeometry>,
+}
+
+impl SkeletonHelper {
+  pub fn new(root: &Rc<dyn ObjectActions>) -> Rc<Self> {
+    let mut bones = vec![];
+    root.traverse(&mut |object| {
+      if object.as_any().is::<Bone>() {
+        bones.push(object.clone());
+      }
+    });
+
+    // both ends of a line sit at the origin of a bone and are moved by that bone alone
+    let (mut skin_index, mut color) = (vec![], vec![]);
+    for (i, bone) in bones.iter().enumerate() {
+      let parent = match bone.parent() {
+        Some(parent) => parent,
+        None => continue,
+      };
+      if let Some(j) = bones.iter().position(|b| b.uuid() == parent.uuid()) {
+        skin_index.extend([i as f32, 0.0, 0.0, 0.0, j as f32, 0.0, 0.0, 0.0]);
+        color.extend([0.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+      }
+    }
+
+    let count = skin_index.len() / 4;
+    let mut geometry = BufferGeometry::default();
+    geometry.set_attribute(
+      "position",
+      F32BufferAttribute::new(vec![0.0; count * 3], 3, false).as_enum(),
+    );
+    geometry.set_attribute(
+      "skin_index",
+      F32BufferAttribute::new(skin_index, 4, false).as_enum(),
+    );
+    geometry.set_attribute(
+      "skin_weight",
+      F32BufferAttribute::new([1.0, 0.0, 0.0, 0.0].repeat(count), 4, false).as_enum(),
+    );
+    geometry.set_attribute("color", F32BufferAttribute::new(color, 3, false).as_enum());
+
+    Self::with_fields(bones, Rc::new(geometry))
+  }
+
+  pub fn bones(&self) -> &Vec<Rc<dyn ObjectActions>> {
+    &self.bones
+  }
+
+  pub fn geometry(&self) -> &BufferGeometry {
+    &self.geometry
+  }
+}
+
+impl Drawable for SkeletonHelper {
+  fn geometry(&self) -> &dyn GeometryActions {
+    self.geometry.as_ref()
+  }
+
+  fn material(&self) -> &dyn MaterialActions {
+    self
+  }
+
+  fn draw_mode(&self) -> DrawMode {
+    DrawMode::Lines
+  }
+
+  /// the bones are placed in world space already, the helper's own transform is undone
+  fn set_skinning_uniform(&self, uniform: &mut Uniform) {
+    uniform.insert(
+      "bind_matrix".to_string(),
+      UnifromTypeEnum::from(Mat4::identity()),
+    );
+    uniform.insert(
+      "bind_matrix_inverse".to_string(),
+      UnifromTypeEnum::from(
+        self
+          .global_matrix()
+          .inverse()
+          .unwrap_or_else(Mat4::identity),
+      ),
+    );
+    uniform.set_bone_matrices(self.bones.iter().map(|bone| bone.global_matrix()).collect());
+  }
+
+  fn bounding_box(&self) -> Option<Box3> {
+    None
+  }
+}
+
+impl ConvertUniform for SkeletonHelper {
+  fn to_uniform(&self) -> Uniform {
+    Uniform::default()
+  }
+}
+
+impl DrawGeometry for SkeletonHelper {
+  fn draw(
+    &self,
+    geometry: &dyn GeometryActions,
+    mode: DrawMode,
+    uniform: &Uniform,
+    viewport_matrix: &Mat4,
+    target: &mut RenderTarget,
+  ) {
+    draw_typed_geometry::<SkeletonHelperShader>(
+      geometry,
+      DepthState::default(),
+      mode,
+      uniform,
+      viewport_matrix,
+      target,
+    )
+  }
+}
+
+impl MaterialActions for SkeletonHelper {}
+
+#[derive(Varyings, Clone, Copy)]
+pub struct SkeletonHelperVaryings {
+  color: Vec3,
+}
+
+#[derive(Default)]
+pub struct SkeletonHelperShader {}
+
+impl DefineTypedShader for SkeletonHelperShader {
+  type Varyings = SkeletonHelperVaryings;
+
+  fn vertex(
+    attribute: &Attribute,
+    uniform: &Uniform,
+    gl: &mut GlPerVertex,
+  ) -> SkeletonHelperVaryings {
+    let model_matrix = u!(uniform, Mat4, "model_matrix", !);
+    let view_matrix = u!(uniform, Mat4, "view_matrix", !);
+    let projection_matrix = u!(uniform, Mat4, "projection_matrix", !);
+    let model_matrix = model_matrix * skin_matrix(attribute, uniform);
+
+    let position = Vec4::from_vec3(&a!(attribute, Vec3, "position", !), 1.0);
+    gl.gl_position(projection_matrix * view_matrix * model_matrix * position);
+
+    SkeletonHelperVaryings {
+      color: a!(attribute, Vec3, "color", !),
+    }
+  }
+
+  fn fragment(_: &Uniform, varyings: &SkeletonHelperVaryings, gl: &mut GlPerFragment) -> bool {
+    gl.gl_frag_color(Vec4::from_vec3(&varyings.color, 1.0));
+    true
+  }
+}
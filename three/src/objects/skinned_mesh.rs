@@ -0,0 +1,106 @@
+use std::{cell::RefCell, rc::Rc};
+
+use renderer_macro_derive::object_3d;
+
+use super::{mesh::impl_mesh_actions, skeleton::Skeleton};
+use crate::{
+  core::{
+    buffer_geometry::{BufferGeometry, GeometryActions},
+    object_3d::ObjectActions,
+    unifrom::{Uniform, UnifromTypeEnum},
+  },
+  material::material::MaterialActions,
+  math::{Box3, Mat4},
+};
+
+/// A mesh deformed by a `Skeleton`. Every vertex follows up to four bones,
+/// picked by its `skin_index` attribute and blended by `skin_weight`.
+#[object_3d(ObjectActions, Mesh, Drawable)]
+pub struct SkinnedMesh {
+  geometry: Rc<BufferGeometry>,
+  material: Rc<dyn MaterialActions>,
+  morph_target_influences: RefCell<Vec<f32>>,
+  /// shared with the clones of this mesh
+  skeleton: RefCell<Option<Rc<Skeleton>>>,
+  /// the global matrix of the mesh at bind time
+  bind_matrix: RefCell<Mat4>,
+  bind_matrix_inverse: RefCell<Mat4>,
+}
+
+impl SkinnedMesh {
+  pub fn new(geometry: BufferGeometry, material: impl MaterialActions + 'static) -> Rc<Self> {
+    let influences = vec![0.0; geometry.morph_target_count()];
+    Self::with_fields(
+      Rc::new(geometry),
+      Rc::new(material),
+      RefCell::new(influences),
+      RefCell::new(None),
+      RefCell::new(Mat4::identity()),
+      RefCell::new(Mat4::identity()),
+    )
+  }
+
+  pub fn geometry(&self) -> &BufferGeometry {
+    &self.geometry
+  }
+
+  pub fn material(&self) -> &dyn MaterialActions {
+    self.material.as_ref()
+  }
+
+  /// without `bind_matrix` the current global matrix of the mesh is used
+  pub fn bind(&self, skeleton: Rc<Skeleton>, bind_matrix: Option<Mat4>) {
+    let bind_matrix = bind_matrix.unwrap_or_else(|| {
+      self.sync_global_matrix(true, false);
+      self.global_matrix()
+    });
+
+    *self.skeleton.borrow_mut() = Some(skeleton);
+    *self.bind_matrix.borrow_mut() = bind_matrix;
+    *self.bind_matrix_inverse.borrow_mut() = bind_matrix.inverse().unwrap_or_else(Mat4::identity);
+  }
+
+  pub fn skeleton(&self) -> Option<Rc<Skeleton>> {
+    self.skeleton.borrow().clone()
+  }
+
+  pub fn bind_matrix(&self) -> Mat4 {
+    *self.bind_matrix.borrow()
+  }
+
+  pub fn bind_matrix_inverse(&self) -> Mat4 {
+    *self.bind_matrix_inverse.borrow()
+  }
+
+  /// move the skeleton back into the bind pose
+  pub fn pose(&self) {
+    if let Some(skeleton) = self.skeleton() {
+      skeleton.pose();
+    }
+  }
+}
+
+impl_mesh_actions!(SkinnedMesh, {
+  /// hand the bone matrices of the current pose to the vertex stage,
+  /// the bones' global matrices need to be up to date
+  fn set_skinning_uniform(&self, uniform: &mut Uniform) {
+    let bone_matrices = match self.skeleton() {
+      Some(skeleton) => skeleton.bone_matrices(),
+      None => vec![],
+    };
+
+    uniform.insert(
+      "bind_matrix".to_string(),
+      UnifromTypeEnum::from(self.bind_matrix()),
+    );
+    uniform.insert(
+      "bind_matrix_inverse".to_string(),
+      UnifromTypeEnum::from(self.bind_matrix_inverse()),
+    );
+    uniform.set_bone_matrices(bone_matrices);
+  }
+
+  fn bounding_box(&self) -> Option<Box3> {
+    None
+  }
+});
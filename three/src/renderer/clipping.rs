@@ -0,0 +1,243 @@
+use crate::math::Vec4;
+
+/// x and y are only clipped this many times the clip volume away from its center,
+/// primitives reaching out less than that are left to the bounds of the rasterizer
+pub(crate) const GUARD_BAND: f32 = 4.0;
+
+/// how far a clip space position is inside of each plane of the clip volume,
+/// negative outside
+const PLANES: [fn(&Vec4) -> f32; 6] = [
+  // near
+  |p| p.w + p.z,
+  // far
+  |p| p.w - p.z,
+  |p| GUARD_BAND * p.w + p.x,
+  |p| GUARD_BAND * p.w - p.x,
+  |p| GUARD_BAND * p.w + p.y,
+  |p| GUARD_BAND * p.w - p.y,
+];
+
+/// A corner of a clipped primitive, mixed from the `N` vertices of the primitive
+/// by `weights`. Mixing in clip space keeps the weights perspective correct.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClipVertex<const N: usize> {
+  pub position: Vec4,
+  pub weights: [f32; N],
+}
+
+impl<const N: usize> ClipVertex<N> {
+  fn corner(positions: &[Vec4; N], index: usize) -> Self {
+    let mut weights = [0.0; N];
+    weights[index] = 1.0;
+    Self {
+      position: positions[index],
+      weights,
+    }
+  }
+
+  fn lerp(&self, other: &Self, t: f32) -> Self {
+    let mut weights = self.weights;
+    for (weight, other) in weights.iter_mut().zip(other.weights) {
+      *weight += (other - *weight) * t;
+    }
+    Self {
+      position: self.position + (other.position - self.position) * t,
+      weights,
+    }
+  }
+}
+
+/// Weights given for the corners of a clipped primitive turned into weights of the
+/// vertices of the original one.
+pub(crate) fn unclip_weights<const M: usize, const N: usize>(
+  corners: &[ClipVertex<N>; M],
+  weights: [f32; M],
+) -> [f32; N] {
+  let mut result = [0.0; N];
+  for (corner, weight) in corners.iter().zip(weights) {
+    for (r, w) in result.iter_mut().zip(corner.weights) {
+      *r += w * weight;
+    }
+  }
+  result
+}
+
+/// Sutherland–Hodgman, cut the triangle by every plane of the clip volume in turn
+/// and fan the polygon left inside back into triangles.
+pub(crate) fn clip_triangle(positions: [Vec4; 3]) -> Vec<[ClipVertex<3>; 3]> {
+  let mut polygon: Vec<_> = (0..3).map(|i| ClipVertex::corner(&positions, i)).collect();
+
+  for plane in PLANES {
+    if polygon.is_empty() {
+      break;
+    }
+
+    let input = std::mem::take(&mut polygon);
+    for (i, current) in input.iter().enumerate() {
+      let next = &input[(i + 1) % input.len()];
+      let (d_current, d_next) = (plane(&current.position), plane(&next.position));
+
+      if d_current >= 0.0 {
+        polygon.push(*current);
+      }
+      if (d_current >= 0.0) != (d_next >= 0.0) {
+        polygon.push(current.lerp(next, d_current / (d_current - d_next)));
+      }
+    }
+  }
+
+  (1..polygon.len().saturating_sub(1))
+    .map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+    .collect()
+}
+
+/// The part of the line inside of the clip volume, `None` when there is none.
+pub(crate) fn clip_line(positions: [Vec4; 2]) -> Option<[ClipVertex<2>; 2]> {
+  let (mut t_start, mut t_end) = (0.0_f32, 1.0_f32);
+
+  for plane in PLANES {
+    let (d_start, d_end) = (plane(&positions[0]), plane(&positions[1]));
+    if d_start < 0.0 && d_end < 0.0 {
+      return None;
+    }
+    if d_start < 0.0 {
+      t_start = t_start.max(d_start / (d_start - d_end));
+    } else if d_end < 0.0 {
+      t_end = t_end.min(d_start / (d_start - d_end));
+    }
+  }
+
+  if t_start > t_end {
+    return None;
+  }
+
+  let (start, end) = (
+    ClipVertex::corner(&positions, 0),
+    ClipVertex::corner(&positions, 1),
+  );
+  Some([start.lerp(&end, t_start), start.lerp(&end, t_end)])
+}
+
+/// Points are dropped as a whole once their center leaves the depth range, their
+/// squares are cut to the screen by the rasterizer.
+pub(crate) fn is_point_clipped(position: &Vec4) -> bool {
+  PLANES[..2].iter().any(|plane| plane(position) < 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn mix<const N: usize>(positions: &[Vec4; N], weights: [f32; N]) -> Vec4 {
+    positions
+      .iter()
+      .zip(weights)
+      .fold(Vec4::zero(), |sum, (p, w)| sum + *p * w)
+  }
+
+  /// every corner lies inside of the clip volume and its weights rebuild its position
+  fn assert_clipped<const N: usize>(positions: &[Vec4; N], corners: &[ClipVertex<N>]) {
+    for corner in corners {
+      assert!(PLANES.iter().all(|plane| plane(&corner.position) >= -1e-5));
+      assert!((corner.weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+      assert!((mix(positions, corner.weights) - corner.position).length() < 1e-5);
+    }
+  }
+
+  fn triangle(z: [f32; 3]) -> [Vec4; 3] {
+    [
+      Vec4::new(-0.5, -0.5, z[0], 1.0),
+      Vec4::new(0.5, -0.5, z[1], 1.0),
+      Vec4::new(0.0, 0.5, z[2], 1.0),
+    ]
+  }
+
+  #[test]
+  fn triangle_fully_inside_is_kept() {
+    let positions = triangle([0.0, 0.2, -0.2]);
+    let triangles = clip_triangle(positions);
+
+    assert_eq!(triangles.len(), 1);
+    for (i, corner) in triangles[0].iter().enumerate() {
+      assert_eq!(corner.position, positions[i]);
+      assert_eq!(corner.weights[i], 1.0);
+    }
+  }
+
+  #[test]
+  fn triangle_fully_outside_is_dropped() {
+    assert!(clip_triangle(triangle([-2.0, -3.0, -1.5])).is_empty());
+    assert!(clip_triangle(triangle([2.0, 3.0, 1.5])).is_empty());
+
+    let beyond_the_guard_band = triangle([0.0; 3]).map(|p| p + Vec4::new(10.0, 0.0, 0.0, 0.0));
+    assert!(clip_triangle(beyond_the_guard_band).is_empty());
+  }
+
+  #[test]
+  fn triangle_crossing_the_near_plane_becomes_a_quad() {
+    let positions = triangle([-2.0, 0.0, 0.0]);
+    let triangles = clip_triangle(positions);
+
+    assert_eq!(triangles.len(), 2);
+    for corners in &triangles {
+      assert_clipped(&positions, corners);
+    }
+  }
+
+  #[test]
+  fn triangle_crossing_the_far_plane_is_cut() {
+    let positions = triangle([2.0, 2.0, 0.0]);
+    let triangles = clip_triangle(positions);
+
+    assert_eq!(triangles.len(), 1);
+    assert_clipped(&positions, &triangles[0]);
+    assert!(triangles[0]
+      .iter()
+      .any(|corner| (corner.position.z - 1.0).abs() < 1e-5));
+  }
+
+  #[test]
+  fn line_clipping() {
+    let inside = [
+      Vec4::new(-0.5, 0.0, 0.0, 1.0),
+      Vec4::new(0.5, 0.0, 0.0, 1.0),
+    ];
+    let [start, end] = clip_line(inside).unwrap();
+    assert_eq!((start.position, end.position), (inside[0], inside[1]));
+
+    let crossing = [
+      Vec4::new(0.0, 0.0, -3.0, 1.0),
+      Vec4::new(0.0, 0.0, 0.0, 1.0),
+    ];
+    let corners = clip_line(crossing).unwrap();
+    assert_clipped(&crossing, &corners);
+    assert!((corners[0].position.z + 1.0).abs() < 1e-5);
+
+    let outside = [Vec4::new(0.0, 0.0, 2.0, 1.0), Vec4::new(0.5, 0.0, 3.0, 1.0)];
+    assert!(clip_line(outside).is_none());
+  }
+
+  #[test]
+  fn unclip_weights_map_back_to_the_original_vertices() {
+    let positions = triangle([-2.0, 0.0, 0.0]);
+    let corners = clip_triangle(positions)[0];
+
+    assert_eq!(
+      unclip_weights(&corners, [1.0, 0.0, 0.0]),
+      corners[0].weights
+    );
+
+    let center = [1.0 / 3.0; 3];
+    let expected = (corners[0].position + corners[1].position + corners[2].position) / 3.0;
+    let weights = unclip_weights(&corners, center);
+    assert!((mix(&positions, weights) - expected).length() < 1e-5);
+  }
+
+  #[test]
+  fn points_are_only_clipped_by_depth() {
+    assert!(!is_point_clipped(&Vec4::new(0.0, 0.0, 0.0, 1.0)));
+    assert!(!is_point_clipped(&Vec4::new(10.0, 0.0, 0.0, 1.0)));
+    assert!(is_point_clipped(&Vec4::new(0.0, 0.0, -1.5, 1.0)));
+    assert!(is_point_clipped(&Vec4::new(0.0, 0.0, 1.5, 1.0)));
+  }
+}
@@ -1,53 +1,219 @@
 use std::{borrow::Borrow, rc::Rc};
 
-use super::super::objects::scene::Scene;
+use super::super::objects::{bounding_box, scene::Scene};
 use super::viewport::Viewport;
 use super::{super::cameras::camera::Camera, render_states::RenderStates};
+use super::{
+  rasterizer::draw_geometry, render_states::RenderList, render_target::RenderTarget,
+  shadow_map::render_shadow_map,
+};
 use crate::{
-  core::object_3d::{ObjectActions, ObjectType},
-  math::{
-    data_array::{ColorBuffer, DepthBuffer},
-    extract_normal_matrix,
+  core::{
+    buffer_geometry::GeometryActions,
+    object_3d::{ObjectActions, ObjectType},
+    unifrom::UnifromTypeEnum,
   },
+  material::material::MaterialActions,
+  math::{data_array::ColorBuffer, extract_normal_matrix, Vec2},
 };
 pub struct GlRenderer {
   viewport: Viewport,
-  color: ColorBuffer,
-  depth: DepthBuffer,
+  framebuffer: RenderTarget,
+  render_target: Option<RenderTarget>,
   render_states: RenderStates,
+  shadow_map_enabled: bool,
 }
 
 impl GlRenderer {
   pub fn new() -> Self {
     Self {
       viewport: Default::default(),
-      color: Default::default(),
-      depth: Default::default(),
+      framebuffer: Default::default(),
+      render_target: None,
       render_states: Default::default(),
+      shadow_map_enabled: false,
     }
   }
 
   pub fn set_size(&mut self, w: f32, h: f32) {
     self.viewport.set_size(w, h);
-    self.color = ColorBuffer::new(w as u32, h as u32);
-    self.depth = DepthBuffer::new(w as u32, h as u32);
+    self.framebuffer = RenderTarget::new(w as u32, h as u32);
   }
 
   // pub fn set_pixel_ratio(&mut self, r: f32) {}
 
+  /// bind an offscreen target, `None` goes back to the default framebuffer.
+  /// The previously bound target is handed back.
+  pub fn set_render_target(&mut self, target: Option<RenderTarget>) -> Option<RenderTarget> {
+    std::mem::replace(&mut self.render_target, target)
+  }
+
+  pub fn render_target(&self) -> Option<&RenderTarget> {
+    self.render_target.as_ref()
+  }
+
+  pub fn render_target_mut(&mut self) -> Option<&mut RenderTarget> {
+    self.render_target.as_mut()
+  }
+
+  pub fn set_shadow_map_enabled(&mut self, enabled: bool) {
+    self.shadow_map_enabled = enabled;
+  }
+
+  pub fn shadow_map_enabled(&self) -> bool {
+    self.shadow_map_enabled
+  }
+
+  /// gather the visible lights below `root`, the shadow casting ones also get a shadow pass
+  pub fn collect_lights(&mut self, root: Rc<dyn ObjectActions>) {
+    self.render_states.clear();
+    self.push_lights(root);
+  }
+
+  fn push_lights(&mut self, object: Rc<dyn ObjectActions>) {
+    if !object.visible() {
+      return;
+    }
+
+    if object.as_light().is_some() {
+      if object.cast_shadow() {
+        self.render_states.push_shadow(object.clone());
+      }
+      self.render_states.push_light(object.clone());
+    }
+
+    let children = object.children().clone();
+    for child in children {
+      self.push_lights(child);
+    }
+  }
+
+  /// render the shadow map of every collected shadow casting light
+  pub fn render_shadow_maps(&mut self, camera: &dyn Camera, render_list: &RenderList) {
+    if !self.shadow_map_enabled {
+      return;
+    }
+
+    for object in self.render_states.shadows() {
+      if let Some(light) = object.as_light() {
+        render_shadow_map(light, camera, render_list);
+      }
+    }
+  }
+
   fn take_color(&mut self) -> ColorBuffer {
-    let w = self.color.width();
-    let h = self.color.height();
-    self.depth.clear(std::f32::MAX);
-    std::mem::replace(&mut self.color, ColorBuffer::new(w, h))
+    self.framebuffer.clear_depth();
+    self.framebuffer.take_attachment(0).unwrap_or_default()
   }
 
-  pub fn render(&mut self, scene: Scene, camera: impl Camera + ObjectActions) -> ColorBuffer {
+  /// Draw a single geometry into the bound render target, or the default framebuffer
+  /// when none is bound.
+  pub fn render_buffer_direct(
+    &mut self,
+    camera: &dyn Camera,
+    object: &dyn ObjectActions,
+    geometry: &dyn GeometryActions,
+    material: &dyn MaterialActions,
+  ) {
+    let mut uniform = material.to_uniform();
+    uniform.insert(
+      "model_matrix".to_string(),
+      UnifromTypeEnum::from(object.global_matrix()),
+    );
+    uniform.insert(
+      "view_matrix".to_string(),
+      UnifromTypeEnum::from(camera.view_matrix()),
+    );
+    uniform.insert(
+      "projection_matrix".to_string(),
+      UnifromTypeEnum::from(camera.projection_matrix()),
+    );
+    // anything else is drawn like a static mesh of `geometry` and `material`
+    let fallback = (geometry, material);
+    let drawable = object.as_drawable().unwrap_or(&fallback);
+    drawable.set_deform_uniform(&mut uniform);
+
+    let receive_shadow = self.shadow_map_enabled && object.receive_shadow();
+    let lights = self
+      .render_states
+      .lights()
+      .iter()
+      .filter_map(|light| light.as_light())
+      .map(|light| {
+        let light_uniform = light.to_light_uniform();
+        if receive_shadow {
+          light_uniform
+        } else {
+          light_uniform.without_shadow()
+        }
+      })
+      .collect();
+    uniform.set_lights(lights);
+
+    let (target, viewport_matrix) = match self.render_target.as_mut() {
+      Some(target) => {
+        let viewport = Viewport::new(0.0, 0.0, target.width() as f32, target.height() as f32);
+        (target, *viewport.get_viewport_matrix())
+      }
+      None => (
+        &mut self.framebuffer,
+        *self.viewport.get_viewport_matrix(),
+      ),
+    };
+
+    uniform.insert(
+      "resolution".to_string(),
+      UnifromTypeEnum::from(Vec2::new(target.width() as f32, target.height() as f32)),
+    );
+
+    let mode = drawable.draw_mode();
+    drawable.for_each_instance(&mut uniform, &mut |uniform| {
+      draw_geometry(geometry, material, mode, uniform, &viewport_matrix, target)
+    });
+  }
+
+  /// Draw every visible drawable object of `scene` on the camera's layers, calling
+  /// the objects' `on_before_render`/`on_after_render` around each of them.
+  /// Objects whose bounds are outside of the camera frustum are skipped.
+  /// With shadow maps enabled the shadow casting lights render their maps first.
+  pub fn render(&mut self, scene: &Scene, camera: &(impl Camera + ObjectActions)) -> ColorBuffer {
     scene.update_global_matrix();
     camera.update_global_matrix();
 
-    let project_screen_matrix = camera.projection_matrix() * camera.global_matrix_inverse();
-    // let frustum =
+    if let Some(root) = scene.self_ref() {
+      self.collect_lights(root);
+    }
+
+    let view_projection = camera.projection_matrix() * camera.view_matrix();
+    // objects out of the camera's sight still cast shadows into it
+    let mut render_list: RenderList = vec![];
+    let mut objects = vec![];
+    scene.traverse_visible(&mut |object| {
+      if object.as_drawable().is_none() {
+        return;
+      }
+      render_list.push(object.clone());
+
+      if !object.test_layers(&camera.layers()) {
+        return;
+      }
+      let culled = bounding_box(object.as_ref()).map_or(false, |bounds| {
+        !bounds.intersects_clip_volume(&view_projection)
+      });
+      if !culled {
+        objects.push(object.clone());
+      }
+    });
+
+    self.render_shadow_maps(camera, &render_list);
+
+    for object in objects {
+      let drawable = object.as_drawable().unwrap();
+      let (geometry, material) = (drawable.geometry(), drawable.material());
+      object.on_before_render(self, scene, camera, geometry, material);
+      self.render_buffer_direct(camera, object.as_ref(), geometry, material);
+      object.on_after_render(self, scene, camera, geometry, material);
+    }
 
     self.take_color()
   }
@@ -69,12 +235,15 @@ impl GlRenderer {
 
       match object.get_type() {
         ObjectType::Light => {
+          if object.cast_shadow() {
+            self.render_states.push_shadow(object.clone());
+          }
           self.render_states.push_light(object.clone());
         }
         ObjectType::Scene => todo!(),
         ObjectType::Object3D => todo!(),
         ObjectType::Camera => todo!(),
-        ObjectType::Group => {}
+        ObjectType::Group | ObjectType::Bone => {}
         ObjectType::Mesh | ObjectType::Line | ObjectType::Point => {}
       }
     }
@@ -97,3 +266,103 @@ fn render_object(
   let mv = camera.global_matrix() * object.global_matrix();
   let normal_matrix = extract_normal_matrix(mv);
 }
+
+#[cfg(test)]
+mod tests {
+  use renderer_macro_derive::object_3d;
+
+  use super::*;
+  use crate::{
+    core::{buffer_attribute::F32BufferAttribute, buffer_geometry::BufferGeometry},
+    lights::{directional_light::DirectionalLight, light::LightActions},
+    material::line_basic_material::LineBasicMaterial,
+    math::{perspective, Mat4, Vec3, Vec4},
+    objects::mesh::Mesh,
+  };
+
+  #[object_3d(ObjectActions, Camera)]
+  struct TestCamera {}
+
+  impl Camera for TestCamera {
+    fn view_matrix(&self) -> Mat4 {
+      Mat4::identity()
+    }
+
+    fn projection_matrix(&self) -> Mat4 {
+      perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0)
+    }
+
+    fn global_matrix_inverse(&self) -> Mat4 {
+      Mat4::identity()
+    }
+  }
+
+  fn shadowed_scene() -> (Rc<Scene>, Rc<DirectionalLight>) {
+    let mut geometry = BufferGeometry::default();
+    let position = vec![-1.0, -1.0, -3.0, 1.0, -1.0, -3.0, 0.0, 1.0, -3.0];
+    geometry.set_attribute(
+      "position",
+      F32BufferAttribute::new(position, 3, false).as_enum(),
+    );
+    let mesh = Mesh::new(geometry, LineBasicMaterial::new(Default::default()));
+    mesh.set_cast_shadow(true);
+
+    let light = DirectionalLight::new(Vec4::new(1.0, 1.0, 1.0, 1.0), 1.0);
+    light.set_cast_shadow(true);
+    light.set_target(Vec3::new(0.0, 0.0, -3.0));
+
+    let scene = Scene::new();
+    scene.add(mesh);
+    scene.add(light.clone());
+    (scene, light)
+  }
+
+  fn render_with_shadows(enabled: bool) -> Rc<DirectionalLight> {
+    let (scene, light) = shadowed_scene();
+    let camera = TestCamera::with_fields();
+    let mut renderer = GlRenderer::new();
+    renderer.set_render_target(Some(RenderTarget::new(16, 16)));
+    renderer.set_shadow_map_enabled(enabled);
+    renderer.render(&scene, camera.as_ref());
+    light
+  }
+
+  #[test]
+  fn render_runs_the_shadow_pass() {
+    let light = render_with_shadows(true);
+    assert!(light.shadow().unwrap().map().is_some());
+  }
+
+  #[test]
+  fn render_skips_the_shadow_pass_when_disabled() {
+    let light = render_with_shadows(false);
+    assert!(light.shadow().unwrap().map().is_none());
+  }
+
+  #[test]
+  fn render_calls_the_object_callbacks_around_the_draw() {
+    let (scene, _) = shadowed_scene();
+    let mesh = scene.children()[0].clone();
+    let calls = Rc::new(std::cell::RefCell::new(vec![]));
+
+    let before = calls.clone();
+    mesh.set_on_before_render(Some(Box::new(move |_, _, _, _, _| {
+      before.borrow_mut().push("before")
+    })));
+    let after = calls.clone();
+    mesh.set_on_after_render(Some(Box::new(move |_, _, _, _, _| {
+      after.borrow_mut().push("after")
+    })));
+
+    let camera = TestCamera::with_fields();
+    let mut renderer = GlRenderer::new();
+    renderer.set_render_target(Some(RenderTarget::new(16, 16)));
+    renderer.render(&scene, camera.as_ref());
+    assert_eq!(calls.take(), ["before", "after"]);
+
+    mesh.set_on_before_render(None);
+    mesh.set_on_after_render(None);
+    renderer.render(&scene, camera.as_ref());
+    assert!(calls.take().is_empty());
+  }
+}
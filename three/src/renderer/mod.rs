@@ -1,3 +1,7 @@
 pub mod gl_renderer;
+pub mod render_states;
+pub mod render_target;
 pub mod viewport;
-mod render_states;
\ No newline at end of file
+pub(crate) mod clipping;
+pub(crate) mod rasterizer;
+mod shadow_map;
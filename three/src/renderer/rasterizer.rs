@@ -0,0 +1,454 @@
+use super::{
+  clipping::{clip_line, clip_triangle, is_point_clipped, unclip_weights},
+  render_target::RenderTarget,
+};
+use crate::{
+  core::{
+    buffer_geometry::GeometryActions,
+    unifrom::Uniform,
+    varying::{Varying, Varyings},
+  },
+  material::{
+    material::{DepthState, DrawMode, MaterialActions, RunShader},
+    shader::{DefineTypedShader, GlPerFragment, GlPerVertex},
+  },
+  math::{Barycentric, BoundaryBox, Mat4, Vec2, Vec4},
+};
+
+/// Run `material` over every primitive of `geometry` and rasterize into `target`.
+pub(crate) fn draw_geometry(
+  geometry: &dyn GeometryActions,
+  material: &dyn MaterialActions,
+  mode: DrawMode,
+  uniform: &Uniform,
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+) {
+  material.draw(geometry, mode, uniform, viewport_matrix, target);
+}
+
+/// The vertex indices of every line `mode` makes out of `count` vertices.
+fn line_indices(mode: DrawMode, count: usize) -> Vec<[usize; 2]> {
+  match mode {
+    DrawMode::Lines => (0..count / 2).map(|i| [i * 2, i * 2 + 1]).collect(),
+    DrawMode::LineStrip => (1..count).map(|i| [i - 1, i]).collect(),
+    DrawMode::LineLoop => {
+      let mut lines: Vec<_> = (1..count).map(|i| [i - 1, i]).collect();
+      if count > 2 {
+        lines.push([count - 1, 0]);
+      }
+      lines
+    }
+    DrawMode::Triangles | DrawMode::Points => vec![],
+  }
+}
+
+/// The string keyed path, varyings are declared through `Varying`.
+pub(crate) fn draw_varying_geometry(
+  geometry: &dyn GeometryActions,
+  material: &dyn RunShader,
+  depth: DepthState,
+  mode: DrawMode,
+  uniform: &Uniform,
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+) {
+  let count = geometry.count();
+  let influences = uniform.morph_target_influences();
+  // every vertex declares its varyings into the same `Varying`, in order
+  let run_vertex = |index: usize, varying: &mut Varying| {
+    let attribute = geometry.morphed_vertex_attribute(index, influences);
+    let mut gl = GlPerVertex::default();
+    material.vertex(&attribute, uniform, varying, &mut gl);
+    gl.read()
+  };
+
+  match mode {
+    DrawMode::Triangles => {
+      for face in 0..count / 3 {
+        let mut varying = Varying::default();
+        let positions = [0, 1, 2].map(|i| run_vertex(face * 3 + i, &mut varying).0);
+
+        rasterize_triangle(positions, viewport_matrix, target, depth, |weights, gl| {
+          varying.interpolate(weights);
+          material.fragment(uniform, &varying, gl)
+        });
+      }
+    }
+    DrawMode::Points => {
+      for index in 0..count {
+        let mut varying = Varying::default();
+        let (position, size, _) = run_vertex(index, &mut varying);
+
+        varying.interpolate([1.0, 0.0, 0.0]);
+        rasterize_point(position, size, viewport_matrix, target, depth, |gl| {
+          material.fragment(uniform, &varying, gl)
+        });
+      }
+    }
+    _ => {
+      for line in line_indices(mode, count) {
+        let mut varying = Varying::default();
+        let positions = line.map(|index| run_vertex(index, &mut varying).0);
+
+        rasterize_line(positions, viewport_matrix, target, depth, |[a, b], gl| {
+          varying.interpolate([a, b, 0.0]);
+          material.fragment(uniform, &varying, gl)
+        });
+      }
+    }
+  }
+}
+
+/// The typed path, every vertex returns its `U::Varyings`.
+pub(crate) fn draw_typed_geometry<U: DefineTypedShader>(
+  geometry: &dyn GeometryActions,
+  depth: DepthState,
+  mode: DrawMode,
+  uniform: &Uniform,
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+) {
+  let count = geometry.count();
+  let influences = uniform.morph_target_influences();
+  let run_vertex = |index: usize| {
+    let attribute = geometry.morphed_vertex_attribute(index, influences);
+    let mut gl = GlPerVertex::default();
+    let varyings = U::vertex(&attribute, uniform, &mut gl);
+    let (position, size, _) = gl.read();
+    (position, size, varyings)
+  };
+
+  match mode {
+    DrawMode::Triangles => {
+      for face in 0..count / 3 {
+        let mut positions = [Vec4::default(); 3];
+        let varyings = [0, 1, 2].map(|i| {
+          let (position, _, varyings) = run_vertex(face * 3 + i);
+          positions[i] = position;
+          varyings
+        });
+
+        rasterize_triangle(positions, viewport_matrix, target, depth, |weights, gl| {
+          let varyings = U::Varyings::lerp(&varyings, weights);
+          U::fragment(uniform, &varyings, gl)
+        });
+      }
+    }
+    DrawMode::Points => {
+      for index in 0..count {
+        let (position, size, varyings) = run_vertex(index);
+
+        rasterize_point(position, size, viewport_matrix, target, depth, |gl| {
+          U::fragment(uniform, &varyings, gl)
+        });
+      }
+    }
+    _ => {
+      for line in line_indices(mode, count) {
+        let mut positions = [Vec4::default(); 2];
+        let [start, end] = [0, 1].map(|i| {
+          let (position, _, varyings) = run_vertex(line[i]);
+          positions[i] = position;
+          varyings
+        });
+
+        // a line is a triangle with its third vertex never weighted
+        let vertices = [start, end.clone(), end];
+        rasterize_line(positions, viewport_matrix, target, depth, |[a, b], gl| {
+          let varyings = U::Varyings::lerp(&vertices, [a, b, 0.0]);
+          U::fragment(uniform, &varyings, gl)
+        });
+      }
+    }
+  }
+}
+
+/// Rasterize one triangle given in clip space into `target`, clipped to the clip volume
+/// first.
+///
+/// `fragment` runs for every covered pixel with the perspective correct weights
+/// of the three vertices, the depth test runs after the fragment so `gl_frag_depth`
+/// writes are honored.
+pub(crate) fn rasterize_triangle<F>(
+  positions: [Vec4; 3],
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+  depth_state: DepthState,
+  mut fragment: F,
+) where
+  F: FnMut([f32; 3], &mut GlPerFragment) -> bool,
+{
+  for corners in clip_triangle(positions) {
+    let positions = corners.map(|corner| corner.position);
+    rasterize_clipped_triangle(
+      positions,
+      viewport_matrix,
+      target,
+      depth_state,
+      |weights, gl| fragment(unclip_weights(&corners, weights), gl),
+    );
+  }
+}
+
+fn rasterize_clipped_triangle<F>(
+  positions: [Vec4; 3],
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+  depth_state: DepthState,
+  mut fragment: F,
+) where
+  F: FnMut([f32; 3], &mut GlPerFragment) -> bool,
+{
+  // vertices behind the eye can't be divided by w
+  if positions.iter().any(|p| p.w <= 0.0) {
+    return;
+  }
+
+  let rhws = positions.map(|p| 1.0 / p.w);
+  let screen = [0, 1, 2].map(|i| *viewport_matrix * (positions[i] * rhws[i]));
+  let vertices_2d = screen.map(|v| v.truncate_to_vec2());
+
+  let BoundaryBox {
+    x_min,
+    x_max,
+    y_min,
+    y_max,
+  } = BoundaryBox::new(&vertices_2d, target.width() as f32, target.height() as f32);
+
+  if x_max < x_min || y_max < y_min {
+    return;
+  }
+
+  for x in (x_min as u32)..(x_max as u32 + 1) {
+    for y in (y_min as u32)..(y_max as u32 + 1) {
+      let barycentric = Barycentric::new(&Vec2::new(x as f32, y as f32), &vertices_2d);
+
+      if !barycentric.is_inside() {
+        continue;
+      }
+
+      let depth = barycentric.apply_weight(&screen.map(|v| v.z));
+      // 1/w is linear in screen space, w itself is not
+      let w = 1.0 / barycentric.apply_weight(&rhws);
+
+      let weights = [
+        barycentric.alpha() * rhws[0] * w,
+        barycentric.beta() * rhws[1] * w,
+        barycentric.gamma() * rhws[2] * w,
+      ];
+
+      let mut gl = GlPerFragment::new(Vec4::new(x as f32, y as f32, depth, 1.0 / w));
+
+      if !fragment(weights, &mut gl) || gl.is_discarded() {
+        continue;
+      }
+
+      if !depth_state.test || target.depth_test(x, y, gl.depth()) {
+        target.write_fragment(x, y, &gl, depth_state.write);
+      }
+    }
+  }
+}
+
+/// Rasterize one line segment given in clip space into `target`, one pixel wide and
+/// clipped to the clip volume first.
+///
+/// `fragment` gets the perspective correct weights of both ends, fragments are
+/// depth tested like the ones of triangles.
+pub(crate) fn rasterize_line<F>(
+  positions: [Vec4; 2],
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+  depth_state: DepthState,
+  mut fragment: F,
+) where
+  F: FnMut([f32; 2], &mut GlPerFragment) -> bool,
+{
+  let corners = match clip_line(positions) {
+    Some(corners) => corners,
+    None => return,
+  };
+
+  let positions = corners.map(|corner| corner.position);
+  rasterize_clipped_line(
+    positions,
+    viewport_matrix,
+    target,
+    depth_state,
+    |weights, gl| fragment(unclip_weights(&corners, weights), gl),
+  );
+}
+
+fn rasterize_clipped_line<F>(
+  positions: [Vec4; 2],
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+  depth_state: DepthState,
+  mut fragment: F,
+) where
+  F: FnMut([f32; 2], &mut GlPerFragment) -> bool,
+{
+  if positions.iter().any(|p| p.w <= 0.0) {
+    return;
+  }
+
+  let rhws = positions.map(|p| 1.0 / p.w);
+  let [start, end] = [0, 1].map(|i| *viewport_matrix * (positions[i] * rhws[i]));
+  let steps = (end.x - start.x)
+    .abs()
+    .max((end.y - start.y).abs())
+    .ceil()
+    .max(1.0) as u32;
+  let (width, height) = (target.width() as f32, target.height() as f32);
+
+  for step in 0..=steps {
+    let t = step as f32 / steps as f32;
+    let x = start.x + (end.x - start.x) * t;
+    let y = start.y + (end.y - start.y) * t;
+    if x < 0.0 || y < 0.0 || x >= width || y >= height {
+      continue;
+    }
+
+    let depth = start.z + (end.z - start.z) * t;
+    let w = 1.0 / (rhws[0] * (1.0 - t) + rhws[1] * t);
+    let weights = [(1.0 - t) * rhws[0] * w, t * rhws[1] * w];
+
+    let (x, y) = (x as u32, y as u32);
+    let mut gl = GlPerFragment::new(Vec4::new(x as f32, y as f32, depth, 1.0 / w));
+
+    if !fragment(weights, &mut gl) || gl.is_discarded() {
+      continue;
+    }
+
+    if !depth_state.test || target.depth_test(x, y, gl.depth()) {
+      target.write_fragment(x, y, &gl, depth_state.write);
+    }
+  }
+}
+
+/// Rasterize one point given in clip space into `target`, a square `size` pixels wide
+/// centered on it.
+///
+/// `fragment` runs for every covered pixel, `gl_point_coord` tells where the pixel is
+/// inside of the square.
+pub(crate) fn rasterize_point<F>(
+  position: Vec4,
+  size: f32,
+  viewport_matrix: &Mat4,
+  target: &mut RenderTarget,
+  depth_state: DepthState,
+  mut fragment: F,
+) where
+  F: FnMut(&mut GlPerFragment) -> bool,
+{
+  if position.w <= 0.0 || is_point_clipped(&position) {
+    return;
+  }
+
+  let center = *viewport_matrix * (position * (1.0 / position.w));
+  let size = size.max(1.0);
+  let (left, top) = (center.x - size / 2.0, center.y - size / 2.0);
+
+  let x_min = left.round().max(0.0) as u32;
+  let y_min = top.round().max(0.0) as u32;
+  let x_max = ((left + size).round().min(target.width() as f32)).max(0.0) as u32;
+  let y_max = ((top + size).round().min(target.height() as f32)).max(0.0) as u32;
+
+  for x in x_min..x_max {
+    for y in y_min..y_max {
+      let point_coord = Vec2::new(
+        (x as f32 + 0.5 - left) / size,
+        (y as f32 + 0.5 - top) / size,
+      );
+      let frag_coord = Vec4::new(x as f32, y as f32, center.z, 1.0 / position.w);
+      let mut gl = GlPerFragment::with_point_coord(frag_coord, point_coord);
+
+      if !fragment(&mut gl) || gl.is_discarded() {
+        continue;
+      }
+
+      if !depth_state.test || target.depth_test(x, y, gl.depth()) {
+        target.write_fragment(x, y, &gl, depth_state.write);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::renderer::viewport::Viewport;
+
+  /// a clip space triangle covering the middle of the target at depth `z`
+  fn triangle(z: f32) -> [Vec4; 3] {
+    [
+      Vec4::new(-1.0, -1.0, z, 1.0),
+      Vec4::new(1.0, -1.0, z, 1.0),
+      Vec4::new(0.0, 1.0, z, 1.0),
+    ]
+  }
+
+  fn draw(target: &mut RenderTarget, z: f32, color: Vec4, depth: DepthState) {
+    let viewport = Viewport::new(0.0, 0.0, target.width() as f32, target.height() as f32);
+    rasterize_triangle(
+      triangle(z),
+      viewport.get_viewport_matrix(),
+      target,
+      depth,
+      |_, gl| {
+        gl.gl_frag_color(color);
+        true
+      },
+    );
+  }
+
+  fn center_color(target: &RenderTarget) -> [u8; 3] {
+    let p = (4 + 4 * target.width()) as usize * 3;
+    let data = target.attachment(0).unwrap().data();
+    [data[p], data[p + 1], data[p + 2]]
+  }
+
+  fn center_depth(target: &RenderTarget) -> f32 {
+    target.depth().unwrap().data()[(4 + 4 * target.width()) as usize]
+  }
+
+  const RED: Vec4 = Vec4::new(1.0, 0.0, 0.0, 1.0);
+  const BLUE: Vec4 = Vec4::new(0.0, 0.0, 1.0, 1.0);
+
+  #[test]
+  fn depth_test_keeps_the_closer_fragment() {
+    let mut target = RenderTarget::new(8, 8);
+    draw(&mut target, -0.5, RED, DepthState::default());
+    draw(&mut target, 0.5, BLUE, DepthState::default());
+
+    assert_eq!(center_color(&target), [255, 0, 0]);
+  }
+
+  #[test]
+  fn without_depth_test_later_fragments_win() {
+    let mut target = RenderTarget::new(8, 8);
+    draw(&mut target, -0.5, RED, DepthState::default());
+    let depth = DepthState {
+      test: false,
+      write: true,
+    };
+    draw(&mut target, 0.5, BLUE, depth);
+
+    assert_eq!(center_color(&target), [0, 0, 255]);
+  }
+
+  #[test]
+  fn without_depth_write_the_depth_buffer_is_kept() {
+    let mut target = RenderTarget::new(8, 8);
+    let depth = DepthState {
+      test: true,
+      write: false,
+    };
+    draw(&mut target, -0.5, RED, depth);
+    assert_eq!(center_depth(&target), f32::MAX);
+
+    draw(&mut target, 0.5, BLUE, DepthState::default());
+    assert_eq!(center_color(&target), [0, 0, 255]);
+  }
+}
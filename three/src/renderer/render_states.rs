@@ -1,9 +1,6 @@
 use std::rc::Rc;
 
-use crate::{
-  core::{buffer_geometry::GeometryActions, object_3d::ObjectActions},
-  material::material::MaterialActions,
-};
+use crate::core::object_3d::ObjectActions;
 #[derive(Default)]
 pub struct RenderStates {
   lights: Vec<Rc<dyn ObjectActions>>,
@@ -18,8 +15,21 @@ impl RenderStates {
   pub fn push_shadow(&mut self, light: Rc<dyn ObjectActions>) {
     self.shadows.push(light)
   }
-}
 
-trait EntityList: ObjectActions + GeometryActions + MaterialActions {}
+  pub fn lights(&self) -> &Vec<Rc<dyn ObjectActions>> {
+    &self.lights
+  }
+
+  pub fn shadows(&self) -> &Vec<Rc<dyn ObjectActions>> {
+    &self.shadows
+  }
+
+  pub fn clear(&mut self) {
+    self.lights.clear();
+    self.shadows.clear();
+  }
+}
 
-pub type RenderList = Vec<Rc<dyn EntityList>>;
+/// The drawable objects of a frame, anything `ObjectActions::as_drawable` returns
+/// a `Drawable` for.
+pub type RenderList = Vec<Rc<dyn ObjectActions>>;
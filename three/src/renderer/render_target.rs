@@ -0,0 +1,129 @@
+use crate::{
+  material::shader::{GlPerFragment, MAX_DRAW_BUFFERS},
+  math::{
+    data_array::{ColorBuffer, DepthBuffer},
+    Vec4,
+  },
+};
+
+/// An offscreen framebuffer with up to `MAX_DRAW_BUFFERS` color attachments
+/// and an optional depth attachment.
+pub struct RenderTarget {
+  width: u32,
+  height: u32,
+  color_attachments: Vec<ColorBuffer>,
+  depth_attachment: Option<DepthBuffer>,
+}
+
+impl RenderTarget {
+  /// a single color attachment with depth, like the default framebuffer
+  pub fn new(w: u32, h: u32) -> Self {
+    Self::with_attachments(w, h, 1, true)
+  }
+
+  pub fn with_attachments(w: u32, h: u32, count: usize, depth: bool) -> Self {
+    assert!(
+      count <= MAX_DRAW_BUFFERS,
+      "a render target holds at most {} color attachments",
+      MAX_DRAW_BUFFERS
+    );
+
+    let mut depth_attachment = if depth {
+      Some(DepthBuffer::new(w, h))
+    } else {
+      None
+    };
+
+    if let Some(depth) = depth_attachment.as_mut() {
+      depth.clear(std::f32::MAX);
+    }
+
+    Self {
+      width: w,
+      height: h,
+      color_attachments: (0..count).map(|_| ColorBuffer::new(w, h)).collect(),
+      depth_attachment,
+    }
+  }
+
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  pub fn attachment_count(&self) -> usize {
+    self.color_attachments.len()
+  }
+
+  pub fn attachment(&self, index: usize) -> Option<&ColorBuffer> {
+    self.color_attachments.get(index)
+  }
+
+  pub fn depth(&self) -> Option<&DepthBuffer> {
+    self.depth_attachment.as_ref()
+  }
+
+  /// move the attachment out and leave a cleared one in place
+  pub fn take_attachment(&mut self, index: usize) -> Option<ColorBuffer> {
+    let (w, h) = (self.width, self.height);
+    self
+      .color_attachments
+      .get_mut(index)
+      .map(|attachment| std::mem::replace(attachment, ColorBuffer::new(w, h)))
+  }
+
+  /// move the depth attachment out, e.g. to sample it as a shadow map
+  pub fn take_depth(&mut self) -> Option<DepthBuffer> {
+    let (w, h) = (self.width, self.height);
+    self.depth_attachment.as_mut().map(|depth| {
+      let mut next = DepthBuffer::new(w, h);
+      next.clear(std::f32::MAX);
+      std::mem::replace(depth, next)
+    })
+  }
+
+  pub fn clear(&mut self, color: &Vec4) {
+    for attachment in &mut self.color_attachments {
+      attachment.clear(color);
+    }
+    self.clear_depth();
+  }
+
+  pub fn clear_depth(&mut self) {
+    if let Some(depth) = self.depth_attachment.as_mut() {
+      depth.clear(std::f32::MAX);
+    }
+  }
+
+  /// less-equal depth test, always passes without a depth attachment
+  pub fn depth_test(&self, x: u32, y: u32, depth: f32) -> bool {
+    match self.depth_attachment.as_ref() {
+      Some(buffer) => depth <= buffer.get(x, y),
+      None => true,
+    }
+  }
+
+  /// route every fragment output to the attachment with the same index
+  pub fn write_fragment(&mut self, x: u32, y: u32, fragment: &GlPerFragment, depth_write: bool) {
+    for (index, attachment) in self.color_attachments.iter_mut().enumerate() {
+      if let Some(color) = fragment.output(index) {
+        attachment.set(x, y, &color);
+      }
+    }
+
+    if depth_write {
+      if let Some(depth) = self.depth_attachment.as_mut() {
+        depth.set(x, y, fragment.depth());
+      }
+    }
+  }
+}
+
+impl Default for RenderTarget {
+  fn default() -> Self {
+    Self::with_attachments(0, 0, 1, true)
+  }
+}
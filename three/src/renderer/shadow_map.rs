@@ -0,0 +1,209 @@
+use std::rc::Rc;
+
+use super::{
+  rasterizer::draw_geometry, render_states::RenderList, render_target::RenderTarget,
+  viewport::Viewport,
+};
+use crate::{
+  cameras::camera::Camera,
+  core::unifrom::{Uniform, UnifromTypeEnum},
+  lights::{
+    cascaded_shadow::{cascade_splits, fit_cascade, CascadedShadowMap, FrustumSlices},
+    light::{LightActions, LightUniform},
+    light_shadow::{CubeShadowMap, ShadowCamera, ShadowMap, ShadowProjection, CUBE_FACES},
+  },
+  material::{
+    depth_material::MeshDepthMaterial,
+    distance_material::{MeshDistanceAttribute, MeshDistanceMaterial},
+    material::{ConvertUniform, MaterialActions},
+  },
+  math::data_array::DepthBuffer,
+};
+
+/// Render the depth of every shadow casting entity as seen from `light`
+/// and keep the result on the light's shadow. `camera` is the one the scene
+/// is viewed from, cascades are fitted to its frustum.
+pub(crate) fn render_shadow_map(
+  light: &dyn LightActions,
+  camera: &dyn Camera,
+  render_list: &RenderList,
+) {
+  light.update_shadow_camera();
+
+  if light.omnidirectional() {
+    render_cube_shadow_map(light, render_list);
+    return;
+  }
+
+  let mut shadow = match light.shadow() {
+    Some(shadow) => shadow,
+    None => return,
+  };
+
+  let cascaded = shadow.cascade.is_some()
+    && matches!(shadow.camera.projection, ShadowProjection::Orthographic { .. });
+  if cascaded {
+    drop(shadow);
+    render_cascaded_shadow_map(light, camera, render_list);
+    return;
+  }
+
+  let material = MeshDepthMaterial::default();
+  let depth = render_depth(
+    &shadow.camera,
+    shadow.map_size,
+    &material,
+    material.to_uniform(),
+    render_list,
+  );
+
+  let map = ShadowMap::new(depth, &shadow.camera, shadow.bias, shadow.normal_bias);
+  shadow.map = Some(Rc::new(map));
+}
+
+/// six square faces, `map_size.0` texels wide, storing the linear distance to the light
+fn render_cube_shadow_map(light: &dyn LightActions, render_list: &RenderList) {
+  let position = light.world_position();
+
+  let mut shadow = match light.shadow() {
+    Some(shadow) => shadow,
+    None => return,
+  };
+
+  let size = shadow.map_size.0;
+  let material = MeshDistanceMaterial::new(MeshDistanceAttribute {
+    reference_position: position,
+    near: shadow.camera.near,
+    far: shadow.camera.far,
+  });
+
+  let mut faces = vec![];
+  for (direction, up) in CUBE_FACES {
+    shadow.camera.look_at(position, position + direction, up);
+    let depth = render_depth(
+      &shadow.camera,
+      (size, size),
+      &material,
+      material.to_uniform(),
+      render_list,
+    );
+    let view_projection = shadow.camera.projection_matrix() * shadow.camera.view_matrix();
+    faces.push((depth, view_projection));
+  }
+
+  let map = CubeShadowMap::new(
+    faces,
+    position,
+    &shadow.camera,
+    shadow.bias,
+    shadow.normal_bias,
+  );
+  shadow.cube_map = Some(Rc::new(map));
+}
+
+/// one orthographic map per slice of the view frustum, all `map_size.0` texels wide
+fn render_cascaded_shadow_map(
+  light: &dyn LightActions,
+  camera: &dyn Camera,
+  render_list: &RenderList,
+) {
+  let direction = match light.to_light_uniform() {
+    LightUniform::Directional { direction, .. } => direction,
+    _ => return,
+  };
+
+  let mut shadow = match light.shadow() {
+    Some(shadow) => shadow,
+    None => return,
+  };
+  let settings = match shadow.cascade.clone() {
+    Some(settings) if settings.count > 0 => settings,
+    _ => return,
+  };
+  let slices = match FrustumSlices::new(camera) {
+    Some(slices) => slices,
+    None => return,
+  };
+
+  let far = if settings.max_distance > 0.0 {
+    settings.max_distance.min(slices.far())
+  } else {
+    slices.far()
+  };
+  let splits = cascade_splits(slices.near(), far, settings.count, settings.split_lambda);
+
+  let size = shadow.map_size.0;
+  let material = MeshDepthMaterial::default();
+  let mut maps = vec![];
+
+  for i in 0..settings.count {
+    let corners = slices.corners(splits[i], splits[i + 1]);
+    let cascade_camera = fit_cascade(&corners, direction, size, settings.caster_distance);
+    let depth = render_depth(
+      &cascade_camera,
+      (size, size),
+      &material,
+      material.to_uniform(),
+      render_list,
+    );
+    maps.push(ShadowMap::new(
+      depth,
+      &cascade_camera,
+      shadow.bias,
+      shadow.normal_bias,
+    ));
+  }
+
+  let map = CascadedShadowMap::new(maps, splits, camera.view_matrix(), settings.blend);
+  shadow.cascaded_map = Some(Rc::new(map));
+}
+
+fn render_depth(
+  camera: &ShadowCamera,
+  (w, h): (u32, u32),
+  material: &dyn MaterialActions,
+  mut uniform: Uniform,
+  render_list: &RenderList,
+) -> DepthBuffer {
+  let mut target = RenderTarget::with_attachments(w, h, 0, true);
+  let viewport = Viewport::new(0.0, 0.0, w as f32, h as f32);
+
+  uniform.insert(
+    "view_matrix".to_string(),
+    UnifromTypeEnum::from(camera.view_matrix()),
+  );
+  uniform.insert(
+    "projection_matrix".to_string(),
+    UnifromTypeEnum::from(camera.projection_matrix()),
+  );
+
+  for entity in render_list.iter() {
+    if !entity.visible() || !entity.cast_shadow() {
+      continue;
+    }
+    let drawable = match entity.as_drawable() {
+      Some(drawable) => drawable,
+      None => continue,
+    };
+    let geometry = drawable.geometry();
+
+    uniform.insert(
+      "model_matrix".to_string(),
+      UnifromTypeEnum::from(entity.global_matrix()),
+    );
+    drawable.set_deform_uniform(&mut uniform);
+    let mode = drawable.draw_mode();
+    drawable.for_each_instance(&mut uniform, &mut |uniform| {
+      draw_geometry(
+        geometry,
+        material,
+        mode,
+        uniform,
+        viewport.get_viewport_matrix(),
+        &mut target,
+      )
+    });
+  }
+
+  target.take_depth().unwrap_or_default()
+}
@@ -9,7 +9,24 @@ pub struct Texture {
   pub path: String,
 }
 
+/// Refers to a loaded texture by its id, what gets passed to the shaders as a uniform.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub u32);
+
 impl Texture {
+  pub fn handle(&self) -> TextureHandle {
+    TextureHandle(self.id)
+  }
+
+  /// a texture sampling an image already in memory
+  pub fn from_image(image: DynamicImage, id: u32) -> Self {
+    Self {
+      id,
+      image: Some(image),
+      path: Default::default(),
+    }
+  }
+
   pub fn load(path: &str, id: u32) -> Result<Self, ImageError> {
     let image_data = open(path).ok();
 
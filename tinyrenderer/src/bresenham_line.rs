@@ -1,6 +1,6 @@
 use crate::{
-  data_array::ColorBuffer,
-  math::{Vec2, Vec4},
+  data_array::{ColorBuffer, DepthBuffer},
+  math::{Vec2, Vec3, Vec4},
 };
 
 pub fn line(pt0: Vec2, pt1: Vec2, color_buffer: &mut ColorBuffer) {
@@ -48,3 +48,629 @@ pub fn line(pt0: Vec2, pt1: Vec2, color_buffer: &mut ColorBuffer) {
 
   // let Vec2 { 1.0, y } = pt0;
 }
+
+/// How the open ends of a wide line are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+  /// the line stops right at its end point
+  #[default]
+  Butt,
+  /// the line goes on for half of its width
+  Square,
+  Round,
+}
+
+/// How a wide line turns a corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+  /// the outer edges meet in a point, cut to a bevel past `miter_limit`
+  #[default]
+  Miter,
+  Bevel,
+  Round,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle {
+  /// in pixels
+  pub width: f32,
+  pub anti_alias: bool,
+  pub cap: LineCap,
+  pub join: LineJoin,
+  /// how many times half of the width a miter may reach out before it becomes a bevel
+  pub miter_limit: f32,
+}
+
+impl Default for LineStyle {
+  fn default() -> Self {
+    Self {
+      width: 1.0,
+      anti_alias: false,
+      cap: Default::default(),
+      join: Default::default(),
+      miter_limit: 4.0,
+    }
+  }
+}
+
+/// Mix `color` into the pixel by `coverage` when `depth` passes the depth test,
+/// only fully covered pixels write their depth.
+fn plot(
+  x: i32,
+  y: i32,
+  depth: f32,
+  coverage: f32,
+  color: &Vec4,
+  color_buffer: &mut ColorBuffer,
+  depth_buffer: &mut DepthBuffer,
+) {
+  if coverage <= 0.0 || !color_buffer.in_box(x, y) || !depth_buffer.in_box(x, y) {
+    return;
+  }
+
+  let (x, y) = (x as u32, y as u32);
+  if depth_buffer.get(x, y) < depth {
+    return;
+  }
+
+  let coverage = coverage.min(1.0);
+  let blended = *color * coverage + color_buffer.get(x, y) * (1.0 - coverage);
+  color_buffer.set(x, y, &blended);
+  if coverage >= 1.0 {
+    depth_buffer.set(x, y, depth);
+  }
+}
+
+/// A pixel of a line, `t` tells how far along the line's `segment` it lies.
+#[derive(Debug, Clone, Copy)]
+pub struct LinePixel {
+  pub x: i32,
+  pub y: i32,
+  pub coverage: f32,
+  pub segment: usize,
+  pub t: f32,
+}
+
+/// Xiaolin Wu's line, every step covers the two pixels nearest to the line by how
+/// close each of them is. Pixels outside of `min`..`max` are skipped.
+pub struct Wu {
+  p0: Vec2,
+  p1: Vec2,
+  steep: bool,
+  /// `p0` and `p1` were swapped to walk from left to right
+  reversed: bool,
+  gradient: f32,
+  x: f32,
+  x_start: f32,
+  x_end: f32,
+  min: Vec2,
+  max: Vec2,
+  pending: Vec<LinePixel>,
+}
+
+impl Wu {
+  pub fn new(pt1: Vec2, pt2: Vec2, min: Vec2, max: Vec2) -> Self {
+    let (mut p0, mut p1) = (pt1, pt2);
+
+    let steep = (p1.y - p0.y).abs() > (p1.x - p0.x).abs();
+    if steep {
+      std::mem::swap(&mut p0.x, &mut p0.y);
+      std::mem::swap(&mut p1.x, &mut p1.y);
+    }
+    let reversed = p0.x > p1.x;
+    if reversed {
+      std::mem::swap(&mut p0, &mut p1);
+    }
+
+    let dx = p1.x - p0.x;
+    Self {
+      p0,
+      p1,
+      steep,
+      reversed,
+      gradient: if dx == 0.0 { 1.0 } else { (p1.y - p0.y) / dx },
+      x: p0.x.round(),
+      x_start: p0.x.round(),
+      x_end: p1.x.round(),
+      min,
+      max,
+      pending: vec![],
+    }
+  }
+
+  fn step(&mut self) {
+    let x = self.x;
+    // the end pixels are only covered as far as the line reaches into them
+    // `fract` would go negative left of zero
+    let gap = if x == self.x_start {
+      let start = self.p0.x + 0.5;
+      1.0 - (start - start.floor())
+    } else if x == self.x_end {
+      let end = self.p1.x + 0.5;
+      end - end.floor()
+    } else {
+      1.0
+    };
+    let dx = self.p1.x - self.p0.x;
+    let t = if dx == 0.0 {
+      0.0
+    } else {
+      ((x - self.p0.x) / dx).clamp(0.0, 1.0)
+    };
+    let y = self.p0.y + self.gradient * (x - self.p0.x);
+
+    let y_floor = y.floor();
+    let fract = y - y_floor;
+    for (y, coverage) in [(y_floor + 1.0, fract), (y_floor, 1.0 - fract)] {
+      let (xx, yy) = if self.steep { (y, x) } else { (x, y) };
+      self.pending.push(LinePixel {
+        x: xx as i32,
+        y: yy as i32,
+        coverage: coverage * gap,
+        segment: 0,
+        t: if self.reversed { 1.0 - t } else { t },
+      });
+    }
+
+    self.x += 1.0;
+  }
+}
+
+impl Iterator for Wu {
+  type Item = LinePixel;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let pixel = match self.pending.pop() {
+        Some(pixel) => pixel,
+        None if self.x > self.x_end => return None,
+        None => {
+          self.step();
+          continue;
+        }
+      };
+
+      let (x, y) = (pixel.x as f32, pixel.y as f32);
+      let inside = x >= self.min.x && x <= self.max.x && y >= self.min.y && y <= self.max.y;
+      if pixel.coverage > 0.0 && inside {
+        return Some(pixel);
+      }
+    }
+  }
+}
+
+/// Xiaolin Wu's line through `Wu`. `z` of the points is the screen depth, tested
+/// against `depth_buffer`.
+pub fn line_aa(
+  pt0: Vec3,
+  pt1: Vec3,
+  color: &Vec4,
+  color_buffer: &mut ColorBuffer,
+  depth_buffer: &mut DepthBuffer,
+) {
+  let max = Vec2::new(
+    color_buffer.width() as f32 - 1.0,
+    color_buffer.height() as f32 - 1.0,
+  );
+  let line = Wu::new(
+    pt0.truncate_to_vec2(),
+    pt1.truncate_to_vec2(),
+    Vec2::zero(),
+    max,
+  );
+  for pixel in line {
+    let z = pt0.z + (pt1.z - pt0.z) * pixel.t;
+    plot(
+      pixel.x,
+      pixel.y,
+      z,
+      pixel.coverage,
+      color,
+      color_buffer,
+      depth_buffer,
+    );
+  }
+}
+
+/// The outline of one part of a wide line, the part of `segment` from `from` to `to`.
+struct Piece {
+  outline: Outline,
+  segment: usize,
+  from: f32,
+  to: f32,
+}
+
+enum Outline {
+  /// convex, in either winding
+  Polygon(Vec<Vec2>),
+  Disc(Vec2, f32),
+}
+
+impl Piece {
+  /// negative inside, exact near the edges which is all anti aliasing needs
+  fn signed_distance(&self, p: Vec2) -> f32 {
+    match &self.outline {
+      Outline::Disc(center, radius) => (p - *center).length() - radius,
+      Outline::Polygon(points) => {
+        let n = points.len();
+        let area: f32 = (0..n).map(|i| points[i].cross(&points[(i + 1) % n])).sum();
+        let winding = if area < 0.0 { -1.0 } else { 1.0 };
+        (0..n)
+          .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            let edge = b - a;
+            let length = edge.length();
+            if length == 0.0 {
+              return f32::MIN;
+            }
+            -winding * edge.cross(&(p - a)) / length
+          })
+          .fold(f32::MIN, f32::max)
+      }
+    }
+  }
+}
+
+fn line_pieces(points: &[Vec2], closed: bool, style: &LineStyle) -> Vec<Piece> {
+  let half = style.width.max(1.0) / 2.0;
+  let count = points.len();
+  let segment_count = if closed { count } else { count - 1 };
+  let direction = |i: usize| {
+    let d = points[(i + 1) % count] - points[i % count];
+    if d.length_square() == 0.0 {
+      None
+    } else {
+      Some(d.normalize())
+    }
+  };
+  let normal = |d: Vec2| Vec2::new(-d.y, d.x);
+
+  let mut pieces = vec![];
+  for i in 0..segment_count {
+    let (a, b) = (points[i], points[(i + 1) % count]);
+    let d = match direction(i) {
+      Some(d) => d,
+      None => continue,
+    };
+    let n = normal(d) * half;
+
+    let square = !closed && style.cap == LineCap::Square;
+    let start = if square && i == 0 { half } else { 0.0 };
+    let end = if square && i == segment_count - 1 {
+      half
+    } else {
+      0.0
+    };
+    let (a, b) = (a - d * start, b + d * end);
+    pieces.push(Piece {
+      outline: Outline::Polygon(vec![a + n, b + n, b - n, a - n]),
+      segment: i,
+      from: 0.0,
+      to: 1.0,
+    });
+  }
+
+  if !closed && style.cap == LineCap::Round {
+    pieces.push(Piece {
+      outline: Outline::Disc(points[0], half),
+      segment: 0,
+      from: 0.0,
+      to: 0.0,
+    });
+    pieces.push(Piece {
+      outline: Outline::Disc(points[count - 1], half),
+      segment: segment_count - 1,
+      from: 1.0,
+      to: 1.0,
+    });
+  }
+
+  let joints = if closed { 0..count } else { 1..count - 1 };
+  for i in joints {
+    let center = points[i];
+    let joint = |outline| Piece {
+      outline,
+      segment: i,
+      from: 0.0,
+      to: 0.0,
+    };
+    if style.join == LineJoin::Round {
+      pieces.push(joint(Outline::Disc(center, half)));
+      continue;
+    }
+
+    let (d0, d1) = match (direction(i + count - 1), direction(i)) {
+      (Some(d0), Some(d1)) => (d0, d1),
+      _ => continue,
+    };
+
+    // the segments only touch along the joint, this piece covers it from the inside
+    let turn = d0.cross(&d1);
+    if turn.abs() < 1e-6 {
+      let (along, across) = (d0 * half, normal(d0) * half);
+      pieces.push(joint(Outline::Polygon(vec![
+        center - along - across,
+        center + along - across,
+        center + along + across,
+        center - along + across,
+      ])));
+      continue;
+    }
+
+    // the gap opens on the outside of the turn
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let (n0, n1) = (normal(d0) * side, normal(d1) * side);
+    let (corner0, corner1) = (center + n0 * half, center + n1 * half);
+
+    let miter = (n0 + n1).normalize();
+    let cos = miter.dot(&n0);
+    let inner = center - miter * (half * cos);
+    let outline = if style.join == LineJoin::Miter && cos > 0.0 && 1.0 / cos <= style.miter_limit {
+      vec![inner, corner0, center + miter * (half / cos), corner1]
+    } else {
+      vec![inner, corner0, corner1]
+    };
+    pieces.push(joint(Outline::Polygon(outline)));
+  }
+
+  pieces
+}
+
+/// Every pixel covered by a line `style.width` pixels wide through `points`, back to
+/// the first one when `closed`, with its joins and caps. Pixels outside of `min`..`max`
+/// are skipped, each pixel is covered once where the parts of the line overlap.
+pub fn wide_line(
+  points: &[Vec2],
+  closed: bool,
+  style: &LineStyle,
+  min: Vec2,
+  max: Vec2,
+) -> Vec<LinePixel> {
+  if points.len() < 2 {
+    return vec![];
+  }
+
+  let pieces = line_pieces(points, closed, style);
+  let reach = style.width.max(1.0) / 2.0 * style.miter_limit.max(1.0) + 1.0;
+  let (mut x_min, mut y_min, mut x_max, mut y_max) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+  for p in points {
+    x_min = x_min.min(p.x - reach);
+    y_min = y_min.min(p.y - reach);
+    x_max = x_max.max(p.x + reach);
+    y_max = y_max.max(p.y + reach);
+  }
+
+  let mut pixels = vec![];
+  for x in (x_min.max(min.x).floor() as i32)..=(x_max.min(max.x).ceil() as i32) {
+    for y in (y_min.max(min.y).floor() as i32)..=(y_max.min(max.y).ceil() as i32) {
+      let p = Vec2::new(x as f32, y as f32);
+
+      // the most covering piece wins
+      let mut best: Option<(f32, &Piece)> = None;
+      for piece in &pieces {
+        let distance = piece.signed_distance(p);
+        let coverage = if style.anti_alias {
+          (0.5 - distance).clamp(0.0, 1.0)
+        } else if distance <= 0.0 {
+          1.0
+        } else {
+          0.0
+        };
+        if coverage > 0.0 && !matches!(best, Some((c, _)) if c >= coverage) {
+          best = Some((coverage, piece));
+        }
+      }
+
+      if let Some((coverage, piece)) = best {
+        // where on the segment the pixel is, from the nearest point of the piece
+        let (a, b) = (
+          points[piece.segment],
+          points[(piece.segment + 1) % points.len()],
+        );
+        let ab = b - a;
+        let t = if ab.length_square() == 0.0 {
+          0.0
+        } else {
+          ((p - a).dot(&ab) / ab.length_square()).clamp(piece.from, piece.to)
+        };
+        pixels.push(LinePixel {
+          x,
+          y,
+          coverage,
+          segment: piece.segment,
+          t,
+        });
+      }
+    }
+  }
+
+  pixels
+}
+
+/// Draw a line through `points`, back to the first one when `closed`. `z` of the points
+/// is the screen depth, tested against `depth_buffer`.
+///
+/// One pixel wide anti aliased lines go through `line_aa`, everything else through
+/// `wide_line`.
+pub fn polyline(
+  points: &[Vec3],
+  closed: bool,
+  style: &LineStyle,
+  color: &Vec4,
+  color_buffer: &mut ColorBuffer,
+  depth_buffer: &mut DepthBuffer,
+) {
+  if points.len() < 2 {
+    return;
+  }
+
+  if style.anti_alias && style.width <= 1.0 {
+    let segment_count = if closed {
+      points.len()
+    } else {
+      points.len() - 1
+    };
+    for i in 0..segment_count {
+      let (a, b) = (points[i], points[(i + 1) % points.len()]);
+      line_aa(a, b, color, color_buffer, depth_buffer);
+    }
+    return;
+  }
+
+  let points_2d: Vec<Vec2> = points.iter().map(|p| p.truncate_to_vec2()).collect();
+  let max = Vec2::new(
+    color_buffer.width() as f32 - 1.0,
+    color_buffer.height() as f32 - 1.0,
+  );
+  for pixel in wide_line(&points_2d, closed, style, Vec2::zero(), max) {
+    let (a, b) = (
+      points[pixel.segment],
+      points[(pixel.segment + 1) % points.len()],
+    );
+    let depth = a.z + (b.z - a.z) * pixel.t;
+    plot(
+      pixel.x,
+      pixel.y,
+      depth,
+      pixel.coverage,
+      color,
+      color_buffer,
+      depth_buffer,
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const WHITE: Vec4 = Vec4 {
+    x: 1.0,
+    y: 1.0,
+    z: 1.0,
+    w: 1.0,
+  };
+
+  fn draw(points: &[Vec3], closed: bool, style: &LineStyle) -> (ColorBuffer, DepthBuffer) {
+    let mut color = ColorBuffer::new(20, 20);
+    let mut depth = DepthBuffer::new(20, 20);
+    depth.clear(f32::MAX);
+    polyline(points, closed, style, &WHITE, &mut color, &mut depth);
+    (color, depth)
+  }
+
+  fn lit(color: &ColorBuffer, x: u32, y: u32) -> bool {
+    color.get(x, y).x > 0.0
+  }
+
+  fn wide(width: f32, cap: LineCap, join: LineJoin) -> LineStyle {
+    LineStyle {
+      width,
+      cap,
+      join,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn wide_lines_cover_their_width_and_caps() {
+    let points = [Vec3::new(2.0, 8.0, 0.5), Vec3::new(13.0, 8.0, 0.5)];
+
+    let (color, mut depth) = draw(&points, false, &wide(4.0, LineCap::Butt, LineJoin::Miter));
+    assert!(lit(&color, 7, 6) && lit(&color, 7, 10));
+    assert!(!lit(&color, 7, 11));
+    assert!(!lit(&color, 1, 8));
+    assert_eq!(depth.get(7, 8), 0.5);
+
+    let (color, _) = draw(&points, false, &wide(4.0, LineCap::Square, LineJoin::Miter));
+    assert!(lit(&color, 0, 8) && lit(&color, 0, 6));
+
+    let (color, _) = draw(&points, false, &wide(4.0, LineCap::Round, LineJoin::Miter));
+    assert!(lit(&color, 0, 8));
+    assert!(!lit(&color, 0, 6));
+  }
+
+  #[test]
+  fn joins_fill_the_outside_of_corners() {
+    let points = [
+      Vec3::new(4.0, 4.0, 0.5),
+      Vec3::new(12.0, 4.0, 0.5),
+      Vec3::new(12.0, 12.0, 0.5),
+    ];
+    let corner = |style: LineStyle| lit(&draw(&points, false, &style).0, 14, 2);
+
+    assert!(corner(wide(6.0, LineCap::Butt, LineJoin::Miter)));
+    assert!(corner(wide(6.0, LineCap::Butt, LineJoin::Round)));
+    assert!(!corner(wide(6.0, LineCap::Butt, LineJoin::Bevel)));
+    // a right angle needs a limit of at least sqrt(2)
+    assert!(!corner(LineStyle {
+      miter_limit: 1.2,
+      ..wide(6.0, LineCap::Butt, LineJoin::Miter)
+    }));
+  }
+
+  #[test]
+  fn closed_lines_return_to_the_first_point() {
+    let points = [
+      Vec3::new(2.0, 2.0, 0.5),
+      Vec3::new(16.0, 2.0, 0.5),
+      Vec3::new(16.0, 16.0, 0.5),
+    ];
+    let style = wide(2.0, LineCap::Butt, LineJoin::Miter);
+    assert!(!lit(&draw(&points, false, &style).0, 9, 9));
+    assert!(lit(&draw(&points, true, &style).0, 9, 9));
+    assert!(!lit(&draw(&points[..1], true, &style).0, 2, 2));
+  }
+
+  #[test]
+  fn anti_aliased_lines_share_pixels_by_coverage() {
+    let points = [Vec3::new(2.0, 5.5, 0.5), Vec3::new(12.0, 5.5, 0.5)];
+    let style = LineStyle {
+      anti_alias: true,
+      ..Default::default()
+    };
+    let (color, mut depth) = draw(&points, false, &style);
+
+    for y in [5, 6] {
+      assert!((color.get(6, y).x - 0.5).abs() < 0.01);
+      // partly covered pixels leave the depth to what is behind them
+      assert_eq!(depth.get(6, y), f32::MAX);
+    }
+    assert!(!lit(&color, 6, 4));
+  }
+
+  #[test]
+  fn lines_are_depth_tested() {
+    let mut color = ColorBuffer::new(20, 20);
+    let mut depth = DepthBuffer::new(20, 20);
+    depth.clear(f32::MAX);
+    depth.set(6, 8, 0.1);
+
+    let points = [Vec3::new(2.0, 8.0, 0.5), Vec3::new(12.0, 8.0, 0.5)];
+    let style = wide(2.0, LineCap::Butt, LineJoin::Miter);
+    polyline(&points, false, &style, &WHITE, &mut color, &mut depth);
+
+    assert!(!lit(&color, 6, 8));
+    assert!(lit(&color, 7, 8));
+    assert_eq!(depth.get(6, 8), 0.1);
+  }
+
+  #[test]
+  fn wu_lines_may_start_off_screen() {
+    let (p0, p1) = (Vec2::new(-3.7, 2.0), Vec2::new(4.0, 2.0));
+
+    let pixels: Vec<LinePixel> = Wu::new(p0, p1, Vec2::zero(), Vec2::new(9.0, 9.0)).collect();
+    assert_eq!(
+      pixels.iter().map(|p| p.x).collect::<Vec<_>>(),
+      [0, 1, 2, 3, 4]
+    );
+    assert!(pixels.iter().all(|p| p.y == 2 && p.t > 0.0 && p.t <= 1.0));
+
+    // the start pixel left of zero is covered by as much as the line reaches into it
+    let start = Wu::new(p0, p1, Vec2::new(-10.0, -10.0), Vec2::new(9.0, 9.0))
+      .next()
+      .unwrap();
+    assert_eq!(start.x, -4);
+    assert!((start.coverage - 0.2).abs() < 1e-5);
+  }
+}
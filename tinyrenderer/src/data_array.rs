@@ -53,6 +53,19 @@ impl DataArray<u8> {
     self.data[(x + y * self.w) as usize * 3 + 1] = (color.y * 255.0) as u8;
     self.data[(x + y * self.w) as usize * 3 + 2] = (color.z * 255.0) as u8;
   }
+
+  pub fn get(&self, x: u32, y: u32) -> math::Vec4 {
+    let p = (x + y * self.w) as usize * 3;
+    if p + 2 >= self.data.len() {
+      return math::Vec4::new(0.0, 0.0, 0.0, 1.0);
+    }
+    math::Vec4::new(
+      self.data[p] as f32 / 255.0,
+      self.data[p + 1] as f32 / 255.0,
+      self.data[p + 2] as f32 / 255.0,
+      1.0,
+    )
+  }
 }
 
 impl DataArray<f32> {
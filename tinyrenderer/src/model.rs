@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use crate::{
-  math::{Vec2, Vec3, Vec4},
+  math::{Mat4, Vec2, Vec3, Vec4},
   obj_loader::{
     defines::ParserError,
     load_obj,
@@ -57,6 +59,9 @@ pub struct Vertex {
   pub position: Vec4,
   pub normal: Option<Vec3>,
   pub texture: Option<Vec2>,
+  /// xyz along +u of the texture, w is the sign of the bitangent,
+  /// only for vertices with a normal and a texture coordinate
+  pub tangent: Option<Vec4>,
   // pub material: Option<VertexMaterial>,
   pub rhw: f32,
 }
@@ -67,11 +72,18 @@ impl Vertex {
       position: pos,
       normal: norm,
       texture: text,
+      tangent: None,
       // material: None,
       rhw: 1.0,
     }
   }
 
+  /// along +v of the texture, from the normal and the tangent
+  pub fn bitangent(&self) -> Option<Vec3> {
+    let (n, t) = (self.normal?, self.tangent?);
+    Some(n.cross(&t.truncated_to_vec3()) * t.w)
+  }
+
   pub fn from_vertex_index(
     v: &VertexIndex,
     scene: &Scene, /* , material: Option<&Material> */
@@ -96,6 +108,7 @@ impl Vertex {
       position,
       normal,
       texture,
+      tangent: None,
       // material: vertex_material,
       rhw: 1.0,
     }
@@ -123,10 +136,26 @@ pub struct Model {
   material: Option<u32>,
 }
 impl Model {
+  pub fn new(name: &str, vertices: Vec<Vertex>, material: Option<u32>) -> Self {
+    Self {
+      name: name.to_string(),
+      vertices,
+      material,
+    }
+  }
+
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
   pub fn get_material(&self) -> Option<u32> {
     self.material
   }
 
+  pub fn set_material(&mut self, material: Option<u32>) {
+    self.material = material;
+  }
+
   pub fn from_obj_model(obj_model: &ObjModel, scene: &Scene) -> Self {
     let name = obj_model.name.clone();
     let mut vertices = vec![];
@@ -141,9 +170,13 @@ impl Model {
       None
     };
 
+    let tangents = compute_tangents(obj_model, scene);
+
     for obj_face in &obj_model.faces {
       for v in &obj_face.vertices {
-        vertices.push(Vertex::from_vertex_index(v, scene));
+        let mut vertex = Vertex::from_vertex_index(v, scene);
+        vertex.tangent = tangents.get(&tangent_key(v)).copied();
+        vertices.push(vertex);
       }
     }
 
@@ -155,9 +188,94 @@ impl Model {
   }
 }
 
-#[derive(Debug)]
+type TangentKey = (u32, Option<u32>, Option<u32>);
+
+fn tangent_key(v: &VertexIndex) -> TangentKey {
+  (v.position_index, v.normal_index, v.texture_index)
+}
+
+/// Darboux frames of the vertices, averaged over the faces sharing a vertex and made
+/// orthogonal to its normal. Faces with degenerate texture coordinates don't contribute,
+/// a vertex left without any gets an arbitrary tangent around its normal.
+fn compute_tangents(obj_model: &ObjModel, scene: &Scene) -> HashMap<TangentKey, Vec4> {
+  let mut sums: HashMap<TangentKey, (Vec3, Vec3, Vec3)> = HashMap::new();
+
+  for face in &obj_model.faces {
+    let [v0, v1, v2] = face.vertices.map(|v| Vertex::from_vertex_index(&v, scene));
+    let (Some(uv0), Some(uv1), Some(uv2)) = (v0.texture, v1.texture, v2.texture) else {
+      continue;
+    };
+
+    let e1 = (v1.position - v0.position).truncated_to_vec3();
+    let e2 = (v2.position - v0.position).truncated_to_vec3();
+    let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+    let det = d1.x * d2.y - d2.x * d1.y;
+
+    let (t, b) = if det.abs() > 1e-8 {
+      let r = 1.0 / det;
+      ((e1 * d2.y - e2 * d1.y) * r, (e2 * d1.x - e1 * d2.x) * r)
+    } else {
+      (Vec3::zero(), Vec3::zero())
+    };
+
+    for (index, vertex) in face.vertices.iter().zip([v0, v1, v2]) {
+      let Some(normal) = vertex.normal else {
+        continue;
+      };
+      let sum = sums
+        .entry(tangent_key(index))
+        .or_insert((Vec3::zero(), Vec3::zero(), normal));
+      sum.0 += t;
+      sum.1 += b;
+    }
+  }
+
+  sums
+    .into_iter()
+    .map(|(key, (t, b, n))| {
+      // a zero normal has no direction to orthogonalize against
+      let n = if n.length() > 1e-6 { n.normalize() } else { n };
+      // Gram-Schmidt against the normal
+      let t = t - n * n.dot(&t);
+      let t = if t.length() > 1e-6 {
+        t.normalize()
+      } else {
+        perpendicular(n)
+      };
+      let w = if n.cross(&t).dot(&b) < 0.0 { -1.0 } else { 1.0 };
+
+      (key, Vec4::from_vec3(&t, w))
+    })
+    .collect()
+}
+
+/// any unit vector perpendicular to `n`, the x axis when `n` is zero
+fn perpendicular(n: Vec3) -> Vec3 {
+  let axis = if n.x.abs() < 0.9 {
+    *Vec3::x_axis()
+  } else {
+    *Vec3::y_axis()
+  };
+  let t = n.cross(&axis);
+  if t.length() > 1e-6 {
+    t.normalize()
+  } else {
+    *Vec3::x_axis()
+  }
+}
+
+/// One placement of a model in the scene, many instances can share a model.
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+  /// index into `Scene::models`
+  pub model: usize,
+  pub matrix: Mat4,
+}
+
+#[derive(Debug, Default)]
 pub struct Scene {
   pub models: Vec<Model>,
+  pub instances: Vec<Instance>,
   pub vertices: Vec<Vec3>,
   pub normals: Vec<Vec3>,
   pub texture_coordinates: Vec<Vec2>,
@@ -173,6 +291,7 @@ impl Scene {
 
     Self {
       models: Default::default(),
+      instances: Default::default(),
       vertices,
       normals,
       texture_coordinates,
@@ -180,8 +299,60 @@ impl Scene {
     }
   }
 
-  pub fn add_model(&mut self, model: Model) {
-    
+  /// add `model` with a single instance at the origin, returns the index of the model
+  pub fn add_model(&mut self, model: Model) -> usize {
+    let index = self.models.len();
+    self.models.push(model);
+    self.add_instance(index, Mat4::identity());
+    index
+  }
+
+  /// place the model at `model` once more, returns the index of the instance
+  pub fn add_instance(&mut self, model: usize, matrix: Mat4) -> usize {
+    assert!(model < self.models.len(), "no model at index {}", model);
+    self.instances.push(Instance { model, matrix });
+    self.instances.len() - 1
+  }
+
+  /// every instance with the model it places
+  pub fn instances(&self) -> impl Iterator<Item = (&Model, Mat4)> {
+    self
+      .instances
+      .iter()
+      .map(|instance| (&self.models[instance.model], instance.matrix))
+  }
+
+  /// Move everything of `other` into this scene, the pools are appended, the models and
+  /// instances are reindexed and the materials and textures are merged, renaming the ones
+  /// whose names are already taken. Returns the new indices of the models of `other`.
+  pub fn merge(&mut self, mut other: Scene) -> Vec<usize> {
+    self.vertices.append(&mut other.vertices);
+    self.normals.append(&mut other.normals);
+    self
+      .texture_coordinates
+      .append(&mut other.texture_coordinates);
+
+    let material_ids: HashMap<u32, u32> = self.stores.merge(other.stores);
+
+    let offset = self.models.len();
+    for mut model in other.models {
+      model.material = model.material.and_then(|id| material_ids.get(&id).copied());
+      self.models.push(model);
+    }
+    for instance in other.instances {
+      self.instances.push(Instance {
+        model: instance.model + offset,
+        matrix: instance.matrix,
+      });
+    }
+
+    (offset..self.models.len()).collect()
+  }
+
+  /// load another OBJ into this scene, see `merge`
+  pub fn add_obj(&mut self, relative_path: &str) -> Result<Vec<usize>, ParserError> {
+    let scene = from_obj_path(relative_path)?;
+    Ok(self.merge(scene))
   }
 }
 
@@ -191,7 +362,152 @@ pub fn from_obj_path(relative_path: &str) -> Result<Scene, ParserError> {
 
   let mut scene = Scene::from_obj_scene(obj_scene);
   for obj_model in &obj_scene.models {
-    scene.models.push(Model::from_obj_model(obj_model, &scene));
+    let model = Model::from_obj_model(obj_model, &scene);
+    scene.add_model(model);
   }
   Ok(scene)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{math::apply_translate, obj_loader::Face};
+
+  /// a unit square in the xy plane facing +z, `uv` maps its corners to texture space
+  fn square(uv: [Vec2; 4]) -> (ObjModel, Scene) {
+    let scene = Scene {
+      vertices: vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      normals: vec![*Vec3::z_axis()],
+      texture_coordinates: uv.to_vec(),
+      ..Default::default()
+    };
+    let vertex = |i: u32| VertexIndex::new(i, Some(0), Some(i));
+    let mut model = ObjModel::new("square".to_string());
+    model.faces = vec![
+      Face {
+        vertices: [vertex(0), vertex(1), vertex(2)],
+      },
+      Face {
+        vertices: [vertex(0), vertex(2), vertex(3)],
+      },
+    ];
+
+    (model, scene)
+  }
+
+  fn assert_tangents(tangents: &HashMap<TangentKey, Vec4>, expected: Vec4) {
+    assert_eq!(tangents.len(), 4);
+    for tangent in tangents.values() {
+      assert!((*tangent - expected).length() < 1e-5, "{:?}", tangent);
+    }
+  }
+
+  #[test]
+  fn tangent_follows_u() {
+    let (model, scene) = square([
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.0, 1.0),
+    ]);
+
+    assert_tangents(
+      &compute_tangents(&model, &scene),
+      Vec4::new(1.0, 0.0, 0.0, 1.0),
+    );
+  }
+
+  #[test]
+  fn mirrored_uv_flips_the_handedness() {
+    let (model, scene) = square([
+      Vec2::new(1.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+    ]);
+
+    assert_tangents(
+      &compute_tangents(&model, &scene),
+      Vec4::new(-1.0, 0.0, 0.0, -1.0),
+    );
+  }
+
+  #[test]
+  fn degenerate_uv_falls_back_to_a_perpendicular_tangent() {
+    let (model, scene) = square([Vec2::new(0.5, 0.5); 4]);
+    let tangents = compute_tangents(&model, &scene);
+
+    assert_eq!(tangents.len(), 4);
+    for tangent in tangents.values() {
+      let t = tangent.truncated_to_vec3();
+      assert!((t.length() - 1.0).abs() < 1e-5);
+      assert!(t.dot(Vec3::z_axis()).abs() < 1e-5);
+    }
+  }
+
+  #[test]
+  fn zero_normals_still_get_unit_tangents() {
+    let (model, mut scene) = square([
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.0, 1.0),
+    ]);
+    scene.normals = vec![Vec3::zero()];
+    assert_tangents(
+      &compute_tangents(&model, &scene),
+      Vec4::new(1.0, 0.0, 0.0, 1.0),
+    );
+
+    let (model, mut scene) = square([Vec2::new(0.5, 0.5); 4]);
+    scene.normals = vec![Vec3::zero()];
+    assert_tangents(
+      &compute_tangents(&model, &scene),
+      Vec4::new(1.0, 0.0, 0.0, 1.0),
+    );
+  }
+
+  fn scene_with_material(name: &str) -> Scene {
+    let mut scene = Scene::default();
+    scene.stores.materials.new_material(name);
+    scene.vertices.push(Vec3::zero());
+    let model = scene.add_model(Model::new(name, vec![], Some(0)));
+    scene.add_instance(model, apply_translate(&Vec3::new(2.0, 0.0, 0.0)));
+    scene
+  }
+
+  #[test]
+  fn merged_scenes_reindex_models_instances_and_materials() {
+    let mut scene = scene_with_material("stone");
+    let models = scene.merge(scene_with_material("stone"));
+
+    assert_eq!(models, vec![1]);
+    assert_eq!(scene.vertices.len(), 2);
+    assert_eq!(scene.instances.len(), 4);
+    assert_eq!(
+      scene.instances.iter().map(|i| i.model).collect::<Vec<_>>(),
+      vec![0, 0, 1, 1]
+    );
+
+    let material = scene.models[1].get_material().unwrap();
+    assert_eq!(material, 1);
+    let material = scene.stores.materials.get_material_by_id(material).unwrap();
+    assert_eq!(material.name, "stone#1");
+  }
+
+  #[test]
+  fn instances_place_their_model() {
+    let mut scene = Scene::default();
+    let model = scene.add_model(Model::new("a", vec![], None));
+    let translate = apply_translate(&Vec3::new(0.0, 3.0, 0.0));
+    assert_eq!(scene.add_instance(model, translate), 1);
+
+    let matrices: Vec<_> = scene.instances().map(|(_, matrix)| matrix).collect();
+    assert_eq!(matrices, vec![Mat4::identity(), translate]);
+  }
+}
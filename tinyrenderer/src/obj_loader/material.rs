@@ -1,4 +1,4 @@
-use super::shader::Shader;
+use super::shader::{GLTypes, GlTypeMap, Shader};
 use crate::math::{Vec2, Vec3, Vec4};
 use crate::utils::swap_and_move;
 use image::{GenericImageView, ImageError};
@@ -75,6 +75,16 @@ macro_rules! make_texture_map {
         Self {$($prop,)+ }
       }
 
+      /// like `from_another_texuture_map`, slots `f` can't resolve are left empty
+      pub fn filter_from<A, F: Fn(&A) -> Option<T>>(instance: &TextureMap<A>, f: F) -> Self {
+        Self {$($prop: instance.$prop.as_ref().and_then(&f),)+ }
+      }
+
+      /// fill the empty slots from `other`
+      pub fn or(self, other: Self) -> Self {
+        Self {$($prop: self.$prop.or(other.$prop),)+ }
+      }
+
       pub fn get_by_key(&self, key: &str) -> Option<&T> {
         match key {
           $(
@@ -101,6 +111,51 @@ make_texture_map!(
 
 pub type TexturePointer = TextureMap<String>;
 
+/// The textures a shader samples, resolved from the names in a `TexturePointer`.
+pub type BoundTextures<'a> = TextureMap<&'a Texture>;
+
+impl Material {
+  /// resolve every texture slot by name, looking through `stores` in order
+  pub fn bind_textures<'a>(&self, stores: &[&'a Textures]) -> BoundTextures<'a> {
+    BoundTextures::filter_from(&self.texture_map, |name| {
+      stores
+        .iter()
+        .find_map(|textures| textures.get_texture_by_name(name))
+    })
+  }
+
+  /// the MTL constants which are set, as uniforms named after the fields
+  pub fn to_uniforms(&self) -> GlTypeMap {
+    let mut uniforms = GlTypeMap::new();
+
+    let vectors = [
+      ("ambient", self.ambient),
+      ("diffuse", self.diffuse),
+      ("specular", self.specular),
+      ("emissive_coeficient", self.emissive_coeficient),
+      ("transmission_filter", self.transmission_filter),
+    ];
+    for (key, value) in vectors {
+      if let Some(value) = value {
+        uniforms.insert(key.to_string(), GLTypes::Vec3(value));
+      }
+    }
+
+    let scalars = [
+      ("specular_exponent", self.specular_exponent),
+      ("dissolve", self.dissolve),
+      ("optical_density", self.optical_density),
+    ];
+    for (key, value) in scalars {
+      if let Some(value) = value {
+        uniforms.insert(key.to_string(), GLTypes::Float(value));
+      }
+    }
+
+    uniforms
+  }
+}
+
 // static GLOBAL_STORE: MtlStores = MtlStores::default();
 
 #[derive(Debug, Default)]
@@ -110,6 +165,12 @@ pub struct MtlStores {
 }
 
 impl MtlStores {
+  /// move the materials and textures of `other` in, returns the new id of every material of `other`
+  pub fn merge(&mut self, other: MtlStores) -> HashMap<u32, u32> {
+    let renames = self.texutres.merge(other.texutres);
+    self.materials.merge(other.materials, &renames)
+  }
+
   pub fn get_mutates(&mut self) -> Result<(&mut Material, &mut Textures), ParserError> {
     self
       .materials
@@ -153,6 +214,35 @@ impl Materials {
     // Ok((material, &mut self.textures))
   }
 
+  /// move the materials of `other` in, renaming the ones whose name is taken and pointing
+  /// them at the renamed textures. Returns the new id of every material of `other`.
+  pub fn merge(
+    &mut self,
+    other: Materials,
+    texture_renames: &HashMap<String, String>,
+  ) -> HashMap<u32, u32> {
+    let mut ids = HashMap::new();
+
+    for (name, mut material) in other.data {
+      let name = unique_name(&name, |n| self.data.contains_key(n));
+      let id = self.auto_incre_id;
+
+      ids.insert(material.id, id);
+      material.id = id;
+      material.name = name.clone();
+      material.texture_map =
+        TexturePointer::from_another_texuture_map(&material.texture_map, |n| {
+          texture_renames.get(n).unwrap_or(n).clone()
+        });
+
+      self.data.insert(name.clone(), material);
+      self.name_id_map.insert(id, name);
+      self.auto_incre_id += 1;
+    }
+
+    ids
+  }
+
   pub fn get_material_by_name(&self, name: &str) -> Option<&Material> {
     self.data.get(name)
   }
@@ -256,10 +346,153 @@ impl Textures {
     self.data.get(&id)
   }
 
+  pub fn get_texture_by_name(&self, name: &str) -> Option<&Texture> {
+    self
+      .name_id_map
+      .get(name)
+      .and_then(|id| self.get_texture_by_id(*id))
+  }
+
   pub fn get_texture_by_ids(&self, ids: Vec<u32>) -> Vec<Option<&Texture>> {
     ids
       .into_iter()
       .map(|id| self.get_texture_by_id(id))
       .collect()
   }
+
+  /// Move the textures of `other` in. A name already taken by the same file is shared,
+  /// one taken by another file gets renamed. Returns the renamed ones, old name to new name.
+  pub fn merge(&mut self, other: Textures) -> HashMap<String, String> {
+    let Textures {
+      mut data,
+      name_id_map,
+      ..
+    } = other;
+    let mut renames = HashMap::new();
+
+    for (name, id) in name_id_map {
+      let mut texture = match data.remove(&id) {
+        Some(texture) => texture,
+        None => continue,
+      };
+
+      if let Some(existing) = self.get_texture_by_name(&name) {
+        if existing.path == texture.path {
+          continue;
+        }
+      }
+
+      let new_name = unique_name(&name, |n| self.name_id_map.contains_key(n));
+      let id = self.auto_incr_id;
+      texture.id = id;
+      texture.name = new_name.clone();
+
+      self.data.insert(id, texture);
+      self.name_id_map.insert(new_name.clone(), id);
+      self.auto_incr_id += 1;
+
+      if new_name != name {
+        renames.insert(name, new_name);
+      }
+    }
+
+    renames
+  }
+}
+
+/// `name`, or `name#n` with the first `n` making it free
+fn unique_name<F: Fn(&str) -> bool>(name: &str, taken: F) -> String {
+  if !taken(name) {
+    return name.to_string();
+  }
+
+  (1..)
+    .map(|n| format!("{}#{}", name, n))
+    .find(|n| !taken(n))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// textures named after their slot, the files don't have to exist
+  fn textures(names: &[&str]) -> Textures {
+    let mut textures = Textures::default();
+    for name in names {
+      textures.load(&format!("{}.png", name), name).unwrap();
+    }
+    textures
+  }
+
+  #[test]
+  fn only_the_set_constants_become_uniforms() {
+    let material = Material {
+      diffuse: Some(Vec3::new(0.5, 0.5, 0.5)),
+      dissolve: Some(0.25),
+      ..Default::default()
+    };
+    let uniforms = material.to_uniforms();
+
+    assert_eq!(uniforms.len(), 2);
+    assert!(matches!(uniforms.get("diffuse"), Some(GLTypes::Vec3(v)) if v.x == 0.5));
+    assert!(matches!(uniforms.get("dissolve"), Some(GLTypes::Float(v)) if *v == 0.25));
+  }
+
+  #[test]
+  fn texture_slots_resolve_through_the_stores_in_order() {
+    let mut material = Material::default();
+    material.texture_map.diffuse = Some("brick".to_string());
+    material.texture_map.norm = Some("missing".to_string());
+    let (own, shared) = (textures(&["brick"]), textures(&["brick", "grass"]));
+
+    let bound = material.bind_textures(&[&own, &shared]);
+    let brick = own.get_texture_by_name("brick").unwrap();
+    assert!(std::ptr::eq(bound.diffuse.unwrap(), brick));
+    assert!(bound.norm.is_none());
+
+    let mut fallback = Material::default();
+    fallback.texture_map.norm = Some("grass".to_string());
+    let bound = bound.or(fallback.bind_textures(&[&shared]));
+    assert_eq!(bound.norm.unwrap().name, "grass");
+  }
+
+  #[test]
+  fn merged_textures_share_files_and_rename_clashes() {
+    let mut textures = textures(&["brick"]);
+    let mut other = Textures::default();
+    other.load("brick.png", "brick").unwrap();
+    other.load("elsewhere/wood.png", "wood").unwrap();
+    textures.load("wood.png", "wood").unwrap();
+
+    let renames = textures.merge(other);
+
+    assert_eq!(
+      renames,
+      HashMap::from([("wood".to_string(), "wood#1".to_string())])
+    );
+    assert_eq!(
+      textures.get_texture_by_name("wood#1").unwrap().path,
+      "elsewhere/wood.png"
+    );
+    assert!(textures.get_texture_by_name("brick#1").is_none());
+  }
+
+  #[test]
+  fn merged_materials_get_new_ids_and_renamed_textures() {
+    let mut materials = Materials::default();
+    materials.new_material("stone");
+    let mut other = Materials::default();
+    other.new_material("stone");
+    other.get_mutates().unwrap().texture_map.diffuse = Some("wood".to_string());
+
+    let renames = HashMap::from([("wood".to_string(), "wood#1".to_string())]);
+    let ids = materials.merge(other, &renames);
+
+    assert_eq!(ids, HashMap::from([(0, 1)]));
+    let merged = materials.get_material_by_id(1).unwrap();
+    assert_eq!(merged.name, "stone#1");
+    assert_eq!(merged.texture_map.diffuse.as_deref(), Some("wood#1"));
+    assert_eq!(materials.get_material_by_id(0).unwrap().name, "stone");
+  }
 }
@@ -7,9 +7,10 @@ use std::{
 use crate::{
   math::{Barycentric, Mat4, Vec2, Vec3, Vec4},
   model::Vertex,
+  renderer::shadow_map::DepthMap,
 };
 
-use super::material::Textures;
+use super::material::BoundTextures;
 
 pub trait Extract<T> {
   fn extract(self) -> Option<T>;
@@ -132,6 +133,27 @@ impl Varyings {
 
     // self.data.insert(key.to_string(), val);
   }
+
+  /// the varyings of a triangle whose corners are mixed from the vertices of this
+  /// one, each by its own weights
+  pub fn mix(&self, corners: &[[f32; 3]; 3]) -> Varyings {
+    let data = self
+      .data
+      .iter()
+      .map(|(key, values)| {
+        let values = match values.as_slice() {
+          [a, b, c] => corners
+            .iter()
+            .map(|[wa, wb, wc]| *a * *wa + *b * *wb + *c * *wc)
+            .collect(),
+          _ => values.clone(),
+        };
+        (key.clone(), values)
+      })
+      .collect();
+
+    Varyings { data }
+  }
 }
 
 pub type GlTypeMap = HashMap<String, GLTypes>;
@@ -158,11 +180,25 @@ pub fn take_value<T: Copy>(v: &T) -> T {
 pub struct Uniform<'a> {
   global: &'a GlTypeMap,
   data: GlTypeMap,
+  shadow_map: Option<&'a DepthMap>,
 }
 
 impl<'a> Uniform<'a> {
   pub fn new(global: &'a GlTypeMap, data: GlTypeMap) -> Self {
-    Self { global, data }
+    Self {
+      global,
+      data,
+      shadow_map: None,
+    }
+  }
+
+  /// the depth rendered from the light by the shadow pass
+  pub fn shadow_map(&self) -> Option<&'a DepthMap> {
+    self.shadow_map
+  }
+
+  pub fn set_shadow_map(&mut self, shadow_map: Option<&'a DepthMap>) {
+    self.shadow_map = shadow_map;
   }
 
   pub fn get(&self, key: &str) -> Option<GLTypes> {
@@ -181,7 +217,7 @@ impl<'a> Uniform<'a> {
 }
 
 type VertexShader = Box<dyn Fn(&Vertex, &Uniform, &mut Varyings) -> Vertex>;
-type FragmentShader = Box<dyn Fn(&Uniform, &Varying, &Textures) -> Vec4>;
+type FragmentShader = Box<dyn Fn(&Uniform, &Varying, &BoundTextures) -> Vec4>;
 
 pub struct Shader {
   pub vertex: VertexShader,
@@ -238,7 +274,7 @@ impl Shader {
     bar: &Barycentric,
     uniforms: &Uniform,
     varyings: &Varyings,
-    textures: &Textures,
+    textures: &BoundTextures,
   ) -> Vec4 {
     let rhws = vertices.map(|v| v.rhw);
     let inv_z = bar.apply_weight(&vertices.map(|v| v.rhw));
@@ -43,6 +43,29 @@ impl Frustum {
   pub fn get_projection_matrix(&self) -> &Mat4 {
     &self.mat
   }
+
+  pub fn near(&self) -> f32 {
+    self.near
+  }
+
+  pub fn far(&self) -> f32 {
+    self.far
+  }
+
+  pub fn fov(&self) -> f32 {
+    self.fov
+  }
+
+  pub fn aspect(&self) -> f32 {
+    self.aspect
+  }
+
+  /// the `w` of clip space positions on the near and on the far plane,
+  /// the projection sets `w` to the distance from the eye
+  pub fn clip_w_range(&self) -> (f32, f32) {
+    (self.near, self.far)
+  }
+
   pub fn contains(&self, pt: &Vec3) -> bool {
     let half_width = self.near * self.fov.tan();
     let half_height = half_width / self.aspect;
@@ -0,0 +1,187 @@
+use crate::{math::Vec4, model::Vertex};
+
+/// x and y are only clipped this many times the clip volume away from its center,
+/// triangles reaching out less than that are left to the bounds of the rasterizer
+pub const GUARD_BAND: f32 = 4.0;
+
+/// A corner of a clipped triangle, mixed from the three vertices of the triangle
+/// by `weights`. Mixing in clip space keeps the weights perspective correct.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipVertex {
+  pub position: Vec4,
+  pub weights: [f32; 3],
+}
+
+impl ClipVertex {
+  fn lerp(&self, other: &Self, t: f32) -> Self {
+    let mut weights = self.weights;
+    for (weight, other) in weights.iter_mut().zip(other.weights) {
+      *weight += (other - *weight) * t;
+    }
+    Self {
+      position: self.position + (other.position - self.position) * t,
+      weights,
+    }
+  }
+}
+
+/// Sutherland–Hodgman, cut the clip space triangle by every plane of the clip volume
+/// in turn. `near_w` and `far_w` are the `w` positions have on the near and the far
+/// plane, see `Frustum::clip_w_range`.
+///
+/// The corners of the convex polygon left inside, in the winding of the triangle.
+pub fn clip_triangle(positions: &[Vec4; 3], near_w: f32, far_w: f32) -> Vec<ClipVertex> {
+  let planes: [&dyn Fn(&Vec4) -> f32; 6] = [
+    &|p| p.w - near_w,
+    &|p| far_w - p.w,
+    &|p| GUARD_BAND * p.w + p.x,
+    &|p| GUARD_BAND * p.w - p.x,
+    &|p| GUARD_BAND * p.w + p.y,
+    &|p| GUARD_BAND * p.w - p.y,
+  ];
+
+  let mut polygon: Vec<_> = (0..3)
+    .map(|i| {
+      let mut weights = [0.0; 3];
+      weights[i] = 1.0;
+      ClipVertex {
+        position: positions[i],
+        weights,
+      }
+    })
+    .collect();
+
+  for plane in planes {
+    if polygon.is_empty() {
+      break;
+    }
+
+    let input = std::mem::take(&mut polygon);
+    for (i, current) in input.iter().enumerate() {
+      let next = &input[(i + 1) % input.len()];
+      let (d_current, d_next) = (plane(&current.position), plane(&next.position));
+
+      if d_current >= 0.0 {
+        polygon.push(*current);
+      }
+      if (d_current >= 0.0) != (d_next >= 0.0) {
+        polygon.push(current.lerp(next, d_current / (d_current - d_next)));
+      }
+    }
+  }
+
+  polygon
+}
+
+/// the vertex at `weights` between the three `vertices`
+pub fn mix_vertex(vertices: &[Vertex; 3], weights: [f32; 3]) -> Vertex {
+  let [a, b, c] = vertices;
+  let [wa, wb, wc] = weights;
+  macro_rules! mix {
+    ($field:ident) => {
+      match (a.$field, b.$field, c.$field) {
+        (Some(a), Some(b), Some(c)) => Some(a * wa + b * wb + c * wc),
+        _ => None,
+      }
+    };
+  }
+
+  Vertex {
+    position: a.position * wa + b.position * wb + c.position * wc,
+    normal: mix!(normal),
+    texture: mix!(texture),
+    tangent: mix!(tangent),
+    rhw: 1.0,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::math::Vec3;
+
+  const NEAR_W: f32 = 1.0;
+  const FAR_W: f32 = 10.0;
+
+  /// a triangle in front of the camera, its vertices `w` away
+  fn triangle(w: [f32; 3]) -> [Vec4; 3] {
+    [
+      Vec4::new(-0.5 * w[0], -0.5 * w[0], 0.0, w[0]),
+      Vec4::new(0.5 * w[1], -0.5 * w[1], 0.0, w[1]),
+      Vec4::new(0.0, 0.5 * w[2], 0.0, w[2]),
+    ]
+  }
+
+  fn mix(positions: &[Vec4; 3], weights: [f32; 3]) -> Vec4 {
+    positions[0] * weights[0] + positions[1] * weights[1] + positions[2] * weights[2]
+  }
+
+  fn assert_clipped(positions: &[Vec4; 3], polygon: &[ClipVertex]) {
+    for corner in polygon {
+      assert!(corner.position.w >= NEAR_W - 1e-4 && corner.position.w <= FAR_W + 1e-4);
+      assert!((corner.weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+      assert!((mix(positions, corner.weights) - corner.position).length() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn triangle_fully_inside_is_kept() {
+    let positions = triangle([2.0, 3.0, 4.0]);
+    let polygon = clip_triangle(&positions, NEAR_W, FAR_W);
+
+    assert_eq!(polygon.len(), 3);
+    for (i, corner) in polygon.iter().enumerate() {
+      assert_eq!(corner.position, positions[i]);
+    }
+  }
+
+  #[test]
+  fn triangle_fully_outside_is_dropped() {
+    assert!(clip_triangle(&triangle([0.2, 0.5, 0.8]), NEAR_W, FAR_W).is_empty());
+    assert!(clip_triangle(&triangle([11.0, 12.0, 20.0]), NEAR_W, FAR_W).is_empty());
+  }
+
+  #[test]
+  fn triangle_crossing_the_near_plane_becomes_a_quad() {
+    let positions = triangle([0.5, 2.0, 2.0]);
+    let polygon = clip_triangle(&positions, NEAR_W, FAR_W);
+
+    assert_eq!(polygon.len(), 4);
+    assert_clipped(&positions, &polygon);
+  }
+
+  #[test]
+  fn triangle_crossing_the_far_plane_is_cut() {
+    let positions = triangle([12.0, 12.0, 5.0]);
+    let polygon = clip_triangle(&positions, NEAR_W, FAR_W);
+
+    assert_eq!(polygon.len(), 3);
+    assert_clipped(&positions, &polygon);
+    assert!(polygon
+      .iter()
+      .any(|corner| (corner.position.w - FAR_W).abs() < 1e-4));
+  }
+
+  #[test]
+  fn mixed_vertices_drop_attributes_missing_on_a_corner() {
+    let vertex = |x: f32, normal: Option<Vec3>| Vertex {
+      position: Vec4::new(x, 0.0, 0.0, 1.0),
+      normal,
+      ..Default::default()
+    };
+    let up = Some(*Vec3::y_axis());
+
+    let mixed = mix_vertex(
+      &[vertex(0.0, up), vertex(1.0, up), vertex(2.0, up)],
+      [0.5, 0.25, 0.25],
+    );
+    assert_eq!(mixed.position, Vec4::new(0.75, 0.0, 0.0, 1.0));
+    assert_eq!(mixed.normal, up);
+
+    let mixed = mix_vertex(
+      &[vertex(0.0, up), vertex(1.0, None), vertex(2.0, up)],
+      [0.5, 0.25, 0.25],
+    );
+    assert_eq!(mixed.normal, None);
+  }
+}
@@ -1,3 +1,6 @@
 pub mod camera;
+pub mod clipping;
 pub mod renderer;
-pub mod shader;
\ No newline at end of file
+pub mod shader;
+pub mod shadow_map;
+pub mod ssao;
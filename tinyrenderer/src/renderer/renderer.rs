@@ -1,8 +1,15 @@
-use super::{camera::Camera, shader};
+use super::{
+  camera::Camera,
+  clipping::{clip_triangle, mix_vertex},
+  shader,
+  shadow_map::{DepthMap, ShadowMap, ShadowMapType},
+  ssao::Ssao,
+};
 use crate::{
+  bresenham_line::{polyline, LineStyle},
   data_array::{ColorBuffer, DepthBuffer},
-  math::{Barycentric, BoundaryBox, Mat4, Vec2, Vec4},
-  model::Scene,
+  math::{Barycentric, BoundaryBox, Mat4, Vec2, Vec3, Vec4},
+  model::{Scene, Vertex},
   obj_loader::{
     material::{self, Material, MtlStores, Texture},
     shader::{GLTypes, GlTypeMap, Shader, Uniform, Varyings},
@@ -59,36 +66,77 @@ impl Viewport {
   }
 }
 
-macro_rules! f {
-  ($tt:tt) => {
-    format!($tt)
-  };
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum FaceCull {
+  Front,
+  Back,
+  #[default]
+  None,
 }
 
-enum ShadowMapType {
-  BasicShadowMap,
-  PCFShadowMap,
-  PCFSoftShadowMap,
-  VSMShadowMap,
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum FrontFace {
+  /**
+   * clockwise
+   */
+  CW,
+  /**
+   * counter clockwise
+   */
+  #[default]
+  CCW,
 }
 
-struct ShadowMap {
-  enabled: bool,
-  map_type: ShadowMapType,
+/// winding of the clip space triangle as seen on the screen, y pointing up
+fn should_cull(positions: &[Vec4; 3], face: FrontFace, cull: FaceCull) -> bool {
+  if cull == FaceCull::None || positions.iter().any(|p| p.w <= 0.0) {
+    return false;
+  }
+
+  let [p0, p1, p2] = positions.map(|p| (p / p.w).truncate_to_vec2());
+  let area = (p1 - p0).cross(&(p2 - p0));
+  let is_front_face = match face {
+    FrontFace::CW => area < 0.0,
+    FrontFace::CCW => area >= 0.0,
+  };
+
+  match cull {
+    FaceCull::Front => is_front_face,
+    FaceCull::Back => !is_front_face,
+    FaceCull::None => false,
+  }
+}
+
+macro_rules! f {
+  ($tt:tt) => {
+    format!($tt)
+  };
 }
 
 pub struct Renderer {
   viewport: Viewport,
   pub camera: Camera,
+  /// placed at the light, the shadow pass renders the scene from here
+  pub light_camera: Camera,
   color: ColorBuffer,
   depth: DepthBuffer,
   stores: MtlStores,
-  default_shader: Shader,
+  /// mix the fragment into the color buffer by its alpha
   blend: bool,
-  shadow_map: bool,
-  cull: bool,
+  shadow_map: ShadowMap,
+  depth_map: Option<DepthMap>,
+  ssao: Ssao,
+  cull: FaceCull,
+  front_face: FrontFace,
+  /// draw the edges of every triangle over the shaded frame
+  wireframe: Option<LineStyle>,
+  wireframe_color: Vec4,
 }
 
+/// how far the wireframe is pulled towards the camera, so edges stay on top of
+/// the faces they border
+const WIREFRAME_DEPTH_BIAS: f32 = 1e-4;
+
 impl Renderer {
   pub fn new(w: u32, h: u32) -> Self {
     let mut depth = DepthBuffer::new(w, h);
@@ -97,44 +145,199 @@ impl Renderer {
     Self {
       viewport: Viewport::new(0.0, 0.0, w as f32, h as f32),
       camera: Camera::new(w as f32, h as f32),
+      light_camera: Camera::new(w as f32, h as f32),
       color: ColorBuffer::new(w, h),
       depth,
       stores: Default::default(),
-      default_shader: Default::default(),
       blend: false,
-      shadow_map: false,
-      cull: false,
+      shadow_map: Default::default(),
+      depth_map: None,
+      ssao: Default::default(),
+      cull: Default::default(),
+      front_face: Default::default(),
+      wireframe: None,
+      wireframe_color: Vec4::new(1.0, 1.0, 1.0, 1.0),
     }
   }
 
+  pub fn set_blend(&mut self, blend: bool) {
+    self.blend = blend;
+  }
+
+  pub fn blend(&self) -> bool {
+    self.blend
+  }
+
+  pub fn set_face_cull(&mut self, cull: FaceCull) {
+    self.cull = cull;
+  }
+
+  pub fn face_cull(&self) -> FaceCull {
+    self.cull
+  }
+
+  pub fn set_front_face(&mut self, face: FrontFace) {
+    self.front_face = face;
+  }
+
+  pub fn front_face(&self) -> FrontFace {
+    self.front_face
+  }
+
+  pub fn set_wireframe(&mut self, wireframe: Option<LineStyle>) {
+    self.wireframe = wireframe;
+  }
+
+  pub fn wireframe(&self) -> Option<LineStyle> {
+    self.wireframe
+  }
+
+  pub fn set_wireframe_color(&mut self, color: Vec4) {
+    self.wireframe_color = color;
+  }
+
+  pub fn set_shadow_map(&mut self, shadow_map: ShadowMap) {
+    self.shadow_map = shadow_map;
+  }
+
+  pub fn shadow_map(&self) -> ShadowMap {
+    self.shadow_map
+  }
+
+  pub fn set_shadow_map_enabled(&mut self, enabled: bool) {
+    self.shadow_map.enabled = enabled;
+  }
+
+  pub fn set_shadow_map_type(&mut self, map_type: ShadowMapType) {
+    self.shadow_map.map_type = map_type;
+  }
+
+  pub fn shadow_map_type(&self) -> ShadowMapType {
+    self.shadow_map.map_type
+  }
+
+  pub fn set_ssao(&mut self, ssao: Ssao) {
+    self.ssao = ssao;
+  }
+
+  pub fn ssao(&self) -> Ssao {
+    self.ssao
+  }
+
+  /// the depth of the last shadow pass, `None` while shadows are disabled
+  pub fn depth_map(&self) -> Option<&DepthMap> {
+    self.depth_map.as_ref()
+  }
+
+  fn light_mvp(&self, model_matrix: Mat4) -> Mat4 {
+    *self.light_camera.get_frustum().get_projection_matrix()
+      * *self.light_camera.get_view_matarix()
+      * model_matrix
+  }
+
+  /// First pass, render the depth of the scene as seen from `light_camera`.
+  /// `model_matrix` is applied on top of the matrix of every instance.
+  pub fn render_shadow(&mut self, scene: &Scene, model_matrix: Mat4) {
+    let mut depth = DepthBuffer::new(self.color.width(), self.color.height());
+    depth.clear(std::f32::MAX);
+
+    let viewport_matrix = self.viewport.get_viewport_matrix();
+    let clip_w = self.light_camera.get_frustum().clip_w_range();
+    let shader = Shader::default();
+
+    for (model, instance_matrix) in scene.instances() {
+      let global_uniforms: GlTypeMap = GlTypeMap::from([
+        (
+          f!("model_matrix"),
+          GLTypes::Mat4(model_matrix * instance_matrix),
+        ),
+        (
+          f!("view_matrix"),
+          GLTypes::Mat4(*self.light_camera.get_view_matarix()),
+        ),
+        (
+          f!("projection_matrix"),
+          GLTypes::Mat4(*self.light_camera.get_frustum().get_projection_matrix()),
+        ),
+      ]);
+      let uniforms = Uniform::new(&global_uniforms, Default::default());
+
+      let vertices = &model.vertices;
+      for i in 0..vertices.len() / 3_usize {
+        let index = i * 3;
+        let mut vertices = [vertices[index], vertices[index + 1], vertices[index + 2]];
+        let mut varyings = Varyings::default();
+
+        for v in &mut vertices {
+          *v = shader.run_vertex(v, &uniforms, &mut varyings);
+        }
+
+        rasterize(
+          &vertices,
+          &varyings,
+          clip_w,
+          viewport_matrix,
+          &mut depth,
+          |_, _, _, _, _| true,
+        );
+      }
+    }
+
+    self.depth_map = Some(DepthMap::new(depth, self.shadow_map.map_type));
+  }
+
+  /// Draw every instance of `scene`, `model_matrix` is applied on top of the matrix of each instance.
   pub fn render(&mut self, scene: &Scene, model_matrix: Mat4, material: &Material) {
-    let width = self.color.width();
-    let height = self.color.height();
+    if self.shadow_map.enabled {
+      self.render_shadow(scene, model_matrix);
+    } else {
+      self.depth_map = None;
+    }
 
     let frustum: &super::camera::Frustum = self.camera.get_frustum();
     let viewport_matrix = self.viewport.get_viewport_matrix();
 
     let view_matrix = *(self.camera.get_view_matarix());
     let projection_matrix = *(frustum.get_projection_matrix());
-    let mvp_it = (view_matrix * model_matrix).inverse_transpose();
-
-    let global_uniforms: GlTypeMap = GlTypeMap::from([
-      (f!("model_matrix"), GLTypes::Mat4(model_matrix)),
-      (f!("view_matrix"), GLTypes::Mat4(view_matrix)),
-      (f!("projection_matrix"), GLTypes::Mat4(projection_matrix)),
-      (f!("viewport_matrix"), GLTypes::Mat4(*viewport_matrix)),
-      (f!("mv_it"), GLTypes::Mat4(mvp_it.unwrap_or_default())),
-    ]);
+    let clip_w = frustum.clip_w_range();
+
+    // `material` brings the shader, every model binds its own MTL textures and constants
+    // and falls back to the ones of `material` for what its MTL doesn't set
+    let shader = &material.shader;
+    let stores = [&scene.stores.texutres, &self.stores.texutres];
+    // the edges are drawn once every face is shaded, faces drawn later would cover them
+    let mut edges: Vec<Vec<Vec3>> = vec![];
+    for (model, instance_matrix) in scene.instances() {
+      let model_matrix = model_matrix * instance_matrix;
+      let mvp_it = (view_matrix * model_matrix).inverse_transpose();
+
+      let mut global_uniforms: GlTypeMap = GlTypeMap::from([
+        (f!("model_matrix"), GLTypes::Mat4(model_matrix)),
+        (f!("view_matrix"), GLTypes::Mat4(view_matrix)),
+        (f!("projection_matrix"), GLTypes::Mat4(projection_matrix)),
+        (f!("viewport_matrix"), GLTypes::Mat4(*viewport_matrix)),
+        (f!("mv_it"), GLTypes::Mat4(mvp_it.unwrap_or_default())),
+      ]);
+
+      if self.depth_map.is_some() {
+        global_uniforms.insert(f!("light_mvp"), GLTypes::Mat4(self.light_mvp(model_matrix)));
+        global_uniforms.insert(f!("shadow_bias"), GLTypes::Float(self.shadow_map.bias));
+      }
 
-    // todo make material mutable then it can call the mutable shaders
-    for model in &scene.models {
       let vertices = &model.vertices;
-      let mut uniforms = Uniform::new(&global_uniforms, Default::default());
-      // let material = model
-      //   .get_material()
-      //   .map_or(None, |id| scene.stores.materials.get_material_by_id(id));
-      let material = Some(material);
-      let shader = material.map(|m| &m.shader).unwrap_or(&self.default_shader);
+      let model_material = model
+        .get_material()
+        .and_then(|id| scene.stores.materials.get_material_by_id(id));
+
+      let mut data = material.to_uniforms();
+      let mut textures = material.bind_textures(&stores);
+      if let Some(model_material) = model_material {
+        data.extend(model_material.to_uniforms());
+        textures = model_material.bind_textures(&stores).or(textures);
+      }
+
+      let mut uniforms = Uniform::new(&global_uniforms, data);
+      uniforms.set_shadow_map(self.depth_map.as_ref());
       for i in 0..vertices.len() / 3_usize {
         let index = (i * 3) as usize;
         let mut vertices = [vertices[index], vertices[index + 1], vertices[index + 2]];
@@ -147,70 +350,260 @@ impl Renderer {
           index += 1.0;
         }
 
-        // restore the x,y,z  with 1/w, as the computation times `w` before
-
-        // store the rhw and perform the v.position.w
-        for v in &mut vertices {
-          v.rhw = 1.0 / v.position.w;
-          v.position /= v.position.w;
-        }
-
-        for v in &mut vertices {
-          v.position = *viewport_matrix * v.position;
+        if should_cull(&vertices.map(|v| v.position), self.front_face, self.cull) {
+          continue;
         }
 
-        let vertices_2d = vertices.map(|v| v.position.truncate_to_vec2());
-
-        let BoundaryBox {
-          x_max,
-          x_min,
-          y_max,
-          y_min,
-        } = BoundaryBox::new(&vertices_2d, width as f32, height as f32);
+        let outline = rasterize(
+          &vertices,
+          &varyings,
+          clip_w,
+          viewport_matrix,
+          &mut self.depth,
+          |x, y, vertices, varyings, barycentric| {
+            let color = shader.run_fragment(vertices, barycentric, &uniforms, varyings, &textures);
 
-        for x in (x_min as u32)..(x_max as u32 + 1) {
-          for y in (y_min as u32)..(y_max as u32 + 1) {
-            let barycentric = Barycentric::new(&Vec2::new(x as f32, y as f32), &vertices_2d);
-
-            if !barycentric.is_inside() {
-              continue;
-            }
+            // let material = model.get_material().unwrap();
+            // let diffuse_texture = material.texture_map.diffuse.unwrap();
 
-            let depth = barycentric.apply_weight(&vertices.map(|v| v.position.z));
-
-            if self.depth.get(x, y) >= depth {
-              self.depth.set(x, y, depth);
-
-              let color = shader.run_fragment(
-                &vertices,
-                &barycentric,
-                &uniforms,
-                &varyings,
-                &self.stores.texutres,
-              );
-
-              // let material = model.get_material().unwrap();
-              // let diffuse_texture = material.texture_map.diffuse.unwrap();
-
-              // let color = texture.get_pixel(vt);
+            // let color = texture.get_pixel(vt);
 
+            if !self.blend {
               self.color.set(x, y, &color);
+              return true;
             }
-          }
+
+            // src * alpha + dst * (1 - alpha), translucent fragments keep the depth behind them
+            let alpha = color.w.clamp(0.0, 1.0);
+            let blended = color * alpha + self.color.get(x, y) * (1.0 - alpha);
+            self.color.set(x, y, &blended);
+
+            alpha >= 1.0
+          },
+        );
+
+        if self.wireframe.is_some() && !outline.is_empty() {
+          edges.push(
+            outline
+              .into_iter()
+              .map(|p| Vec3::new(p.x, p.y, p.z - WIREFRAME_DEPTH_BIAS))
+              .collect(),
+          );
         }
       }
     }
+
+    if let Some(style) = self.wireframe {
+      for outline in &edges {
+        polyline(
+          outline,
+          true,
+          &style,
+          &self.wireframe_color,
+          &mut self.color,
+          &mut self.depth,
+        );
+      }
+    }
   }
 
   pub fn load_texture(&mut self, filepath: &str, name: &str) {
     let _ = self.stores.texutres.load(filepath, name);
   }
 
+  /// the finished frame, the SSAO pass runs here when it's enabled
   pub fn take_color(&mut self) -> ColorBuffer {
     let w = self.color.width();
     let h = self.color.height();
+
+    if self.ssao.enabled {
+      self
+        .ssao
+        .apply(&mut self.color, &self.depth, self.camera.get_frustum());
+    }
     self.depth.clear(std::f32::MAX);
 
     std::mem::replace(&mut self.color, ColorBuffer::new(w, h))
   }
 }
+
+/// Clip the clip space triangle against the frustum, project what is left onto the
+/// screen and call `fragment` for every pixel passing the depth test, the depth is
+/// written when it returns true. `clip_w` is the `w` range of the frustum, see
+/// `Frustum::clip_w_range`.
+///
+/// The screen space outline of the clipped triangle, empty when nothing is left.
+fn rasterize<F>(
+  vertices: &[Vertex; 3],
+  varyings: &Varyings,
+  clip_w: (f32, f32),
+  viewport_matrix: &Mat4,
+  depth_buffer: &mut DepthBuffer,
+  mut fragment: F,
+) -> Vec<Vec3>
+where
+  F: FnMut(u32, u32, &[Vertex; 3], &Varyings, &Barycentric) -> bool,
+{
+  let polygon = clip_triangle(&vertices.map(|v| v.position), clip_w.0, clip_w.1);
+  let mut outline = Vec::with_capacity(polygon.len());
+  for corner in &polygon {
+    let p = *viewport_matrix * (corner.position / corner.position.w);
+    outline.push(p.truncated_to_vec3());
+  }
+
+  // untouched triangles keep their own vertices and varyings
+  if polygon.len() == 3 && polygon.iter().enumerate().all(|(i, c)| c.weights[i] == 1.0) {
+    let mut vertices = *vertices;
+    rasterize_clipped(
+      &mut vertices,
+      varyings,
+      viewport_matrix,
+      depth_buffer,
+      &mut fragment,
+    );
+    return outline;
+  }
+
+  for i in 1..polygon.len().saturating_sub(1) {
+    let corners = [polygon[0], polygon[i], polygon[i + 1]];
+    let mut clipped = corners.map(|c| mix_vertex(vertices, c.weights));
+    let varyings = varyings.mix(&corners.map(|c| c.weights));
+    rasterize_clipped(
+      &mut clipped,
+      &varyings,
+      viewport_matrix,
+      depth_buffer,
+      &mut fragment,
+    );
+  }
+
+  outline
+}
+
+fn rasterize_clipped<F>(
+  vertices: &mut [Vertex; 3],
+  varyings: &Varyings,
+  viewport_matrix: &Mat4,
+  depth_buffer: &mut DepthBuffer,
+  fragment: &mut F,
+) where
+  F: FnMut(u32, u32, &[Vertex; 3], &Varyings, &Barycentric) -> bool,
+{
+  let width = depth_buffer.width();
+  let height = depth_buffer.height();
+
+  // restore the x,y,z  with 1/w, as the computation times `w` before
+
+  // store the rhw and perform the v.position.w
+  for v in vertices.iter_mut() {
+    v.rhw = 1.0 / v.position.w;
+    v.position /= v.position.w;
+  }
+
+  for v in vertices.iter_mut() {
+    v.position = *viewport_matrix * v.position;
+  }
+
+  let vertices_2d = vertices.map(|v| v.position.truncate_to_vec2());
+
+  let BoundaryBox {
+    x_max,
+    x_min,
+    y_max,
+    y_min,
+  } = BoundaryBox::new(&vertices_2d, width as f32, height as f32);
+
+  for x in (x_min as u32)..(x_max as u32 + 1) {
+    for y in (y_min as u32)..(y_max as u32 + 1) {
+      let barycentric = Barycentric::new(&Vec2::new(x as f32, y as f32), &vertices_2d);
+
+      if !barycentric.is_inside() {
+        continue;
+      }
+
+      let depth = barycentric.apply_weight(&vertices.map(|v| v.position.z));
+
+      if depth_buffer.get(x, y) >= depth && fragment(x, y, vertices, varyings, &barycentric) {
+        depth_buffer.set(x, y, depth);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::model::Model;
+
+  /// a triangle facing the default camera, `z` in front of it
+  fn triangle(z: f32) -> Vec<Vertex> {
+    [(-0.5, -0.5), (0.5, -0.5), (0.0, 0.5)]
+      .iter()
+      .map(|(x, y)| Vertex::new(Vec4::new(*x, *y, z, 1.0), None, None))
+      .collect()
+  }
+
+  fn scene(vertices: Vec<Vertex>) -> Scene {
+    let mut scene = Scene::default();
+    scene.add_model(Model::new("triangle", vertices, None));
+    scene
+  }
+
+  #[test]
+  fn shadow_pass_renders_the_depth_seen_from_the_light() {
+    let scene = scene(triangle(-10.0));
+    let material = Material::default();
+    let mut renderer = Renderer::new(16, 16);
+    renderer.set_shadow_map_enabled(true);
+    renderer.render(&scene, Mat4::identity(), &material);
+
+    let depth = renderer.depth_map().unwrap().depth().data();
+    assert!(depth[8 + 8 * 16] < 1.0);
+    assert_eq!(depth[0], f32::MAX);
+
+    renderer.set_shadow_map_enabled(false);
+    renderer.render(&scene, Mat4::identity(), &material);
+    assert!(renderer.depth_map().is_none());
+  }
+
+  fn clip_positions(counter_clockwise: bool) -> [Vec4; 3] {
+    let [a, b, c] = [
+      Vec4::new(-0.5, -0.5, 0.0, 1.0),
+      Vec4::new(0.5, -0.5, 0.0, 1.0),
+      Vec4::new(0.0, 0.5, 0.0, 1.0),
+    ];
+    if counter_clockwise {
+      [a, b, c]
+    } else {
+      [a, c, b]
+    }
+  }
+
+  #[test]
+  fn faces_are_culled_by_their_winding() {
+    let ccw = clip_positions(true);
+    let cw = clip_positions(false);
+
+    assert!(!should_cull(&ccw, FrontFace::CCW, FaceCull::None));
+    assert!(!should_cull(&ccw, FrontFace::CCW, FaceCull::Back));
+    assert!(should_cull(&cw, FrontFace::CCW, FaceCull::Back));
+    assert!(should_cull(&ccw, FrontFace::CCW, FaceCull::Front));
+    assert!(should_cull(&ccw, FrontFace::CW, FaceCull::Back));
+    assert!(!should_cull(&cw, FrontFace::CW, FaceCull::Back));
+  }
+
+  #[test]
+  fn translucent_fragments_are_blended_over_the_frame() {
+    let mut translucent = Material::default();
+    translucent.shader.fragment = Box::new(|_, _, _| Vec4::new(1.0, 0.0, 0.0, 0.5));
+    let opaque = Material::default();
+    let mut renderer = Renderer::new(16, 16);
+
+    renderer.render(&scene(triangle(-10.0)), Mat4::identity(), &opaque);
+    renderer.set_blend(true);
+    renderer.render(&scene(triangle(-5.0)), Mat4::identity(), &translucent);
+
+    let color = renderer.take_color().get(8, 8);
+    assert!((color - Vec4::new(1.0, 0.5, 0.5, 1.0)).length() < 0.01);
+  }
+}
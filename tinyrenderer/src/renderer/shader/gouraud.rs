@@ -29,7 +29,7 @@ pub fn make_gouraud_shader(light_dir: Vec3) -> Shader {
       .get("vUv")
       .map_or(None as Option<Vec2>, |v| v.extract());
 
-    if let (Some(texture), Some(uv)) = (textures.get_texture_by_id(0), vUv) {
+    if let (Some(texture), Some(uv)) = (textures.diffuse, vUv) {
       let mut res = texture.get_pixel(uv) * s;
       res.w = 1.0;
       res
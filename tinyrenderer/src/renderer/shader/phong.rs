@@ -1,8 +1,16 @@
+use super::shadow::{set_shadow_coord, shadow_visibility};
 use crate::{
   math::{Mat3, Mat4, Vec2, Vec3, Vec4},
   obj_loader::shader::{uniform, varying, Extract, GLTypes, Shader},
 };
 
+/// how much light is left in the shadow, like the ambient term of the shadow lesson
+const SHADOW_AMBIENT: f32 = 0.3;
+
+fn occlusion(visibility: f32) -> f32 {
+  SHADOW_AMBIENT + (1.0 - SHADOW_AMBIENT) * visibility
+}
+
 pub fn make_phong_shader(light_dir: Vec3) -> Shader {
   let mut shader = Shader::default();
   let default_vertex = shader.vertex;
@@ -11,6 +19,7 @@ pub fn make_phong_shader(light_dir: Vec3) -> Shader {
     if let Some(uv) = gl_vertex.texture {
       varyings.set("vUv", GLTypes::Vec2(uv));
     }
+    set_shadow_coord(gl_vertex, uniforms, varyings);
 
     default_vertex(gl_vertex, uniforms, varyings)
   });
@@ -21,13 +30,12 @@ pub fn make_phong_shader(light_dir: Vec3) -> Shader {
     let mut color = Vec4::new(1.0, 1.0, 1.0, 1.0);
 
     if let Some(uv) = vUv {
-      if let Some(diffuse) = textures.get_texture_by_id(0) {
+      if let Some(diffuse) = textures.diffuse {
         color = diffuse.get_pixel(uv);
       }
 
-      let t = textures.get_texture_by_ids(vec![1, 3]);
-
-      if let (Some(normal), Some(specular)) = (t[0], t[1]) {
+      // object space normals in `norm`, the specular exponent in `specular_highlight`
+      if let (Some(normal), Some(specular)) = (textures.norm, textures.specular_highlight) {
         let mut nn = normal.get_pixel(uv);
         nn = nn * 2.0 - 1.0;
         // bgr ---> zyx ---> xyz
@@ -41,11 +49,12 @@ pub fn make_phong_shader(light_dir: Vec3) -> Shader {
         let spec_strength = r.z.max(0.0).powf(specular.get_pixel(uv).z * 255.0);
 
         let intense = n.dot(&l).max(0.0);
+        let shadow = occlusion(shadow_visibility(uniforms, varyings));
 
         color = Vec4::new(
-          (5.0 + color.x * 255.0 * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
-          (5.0 + color.y * 255.0 * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
-          (5.0 + color.z * 255.0 * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
+          (5.0 + color.x * 255.0 * shadow * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
+          (5.0 + color.y * 255.0 * shadow * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
+          (5.0 + color.z * 255.0 * shadow * (intense + 1.6 * spec_strength)).min(255.0) / 255.0,
           color.w,
         );
       }
@@ -62,23 +71,20 @@ pub fn make_phong_shader_with_tangent_normal_map(light_dir: Vec3) -> Shader {
   let mut shader = Shader::default();
   let default_vertex = shader.vertex;
   shader.vertex = Box::new(move |gl_vertex, uniforms, varyings| {
-    let i = uniform!(uniforms, f32, "vertex_index", !);
-
     if let Some(uv) = gl_vertex.texture {
       varyings.set("vUv", GLTypes::Vec2(uv));
-      varyings.set(&format!("uv_{}", i), GLTypes::Vec2(uv));
     }
 
     if let Some(n) = gl_vertex.normal {
       varyings.set("normal", GLTypes::Vec3(n));
     }
 
-    let v = default_vertex(gl_vertex, uniforms, varyings);
-    let p = v.position;
-
-    varyings.set(&format!("vertex_{}", i), GLTypes::Vec4(p / p.w));
+    if let Some(t) = gl_vertex.tangent {
+      varyings.set("tangent", GLTypes::Vec4(t));
+    }
+    set_shadow_coord(gl_vertex, uniforms, varyings);
 
-    v
+    default_vertex(gl_vertex, uniforms, varyings)
   });
 
   shader.fragment = Box::new(move |uniforms, varyings, textures| {
@@ -86,45 +92,38 @@ pub fn make_phong_shader_with_tangent_normal_map(light_dir: Vec3) -> Shader {
 
     let mut color = Vec4::new(1.0, 1.0, 1.0, 1.0);
 
-    if let Some(diffuse) = textures.get_texture_by_id(0) {
+    if let Some(diffuse) = textures.diffuse {
       color = diffuse.get_pixel(uv);
     }
 
-    let p0 = varying!(varyings, Vec4, "vertex_0", !);
-    let p1 = varying!(varyings, Vec4, "vertex_1", !);
-    let p2 = varying!(varyings, Vec4, "vertex_2", !);
-
-    let uv0 = varying!(varyings, Vec2, "uv_0", !);
-    let uv1 = varying!(varyings, Vec2, "uv_1", !);
-    let uv2 = varying!(varyings, Vec2, "uv_2", !);
-
-    let uv = varying!(varyings, Vec2, "vUv", !);
-    let bn = varying!(varyings, Vec3, "normal", !);
-    let mut A = Mat3::default();
-
-    A.set_col(0, (p1 - p0).truncated_to_vec3());
-    A.set_col(1, (p2 - p0).truncated_to_vec3());
-    A.set_col(2, bn);
-
-    let AI = A.inverse().unwrap();
-
-    let i: Vec3 = AI * Vec3::new(uv1.x - uv0.x, uv2.x - uv0.x, 0.0);
-    let j: Vec3 = AI * Vec3::new(uv1.y - uv0.y, uv2.y - uv0.y, 0.0);
-
-    let mut B = Mat3::default();
-    B.set_col(0, i.normalize());
-    B.set_col(1, j.normalize());
-    B.set_col(2, bn);
-
-    let t = textures.get_texture_by_ids(vec![2, 3]);
-
-    if let (Some(normal), Some(specular)) = (t[0], t[1]) {
-      let nn = normal.get_pixel(uv);
-      
-      // let mut n = Vec3::zero();
-      let n = B * nn.truncated_to_vec3();
-      let diff = n.normalize().dot(&light_dir).max(0.0);
-      color = color * diff;
+    let bn = varying!(varyings, Vec3, "normal", !).normalize();
+    let tangent = varying!(varyings, Vec4, "tangent");
+
+    // tangent space normals in `bump`
+    if let Some(normal) = textures.bump {
+      let nn = normal.get_pixel(uv) * 2.0 - 1.0;
+
+      // without a tangent frame the map can't be read, keep the interpolated normal
+      let frame = tangent.and_then(|tangent| {
+        let t = tangent.truncated_to_vec3();
+        let t = t - bn * bn.dot(&t);
+        (t.length() > 1e-6).then(|| (t.normalize(), tangent.w))
+      });
+
+      let n = match frame {
+        Some((t, w)) => {
+          let mut tbn = Mat3::default();
+          tbn.set_col(0, t);
+          tbn.set_col(1, bn.cross(&t) * w);
+          tbn.set_col(2, bn);
+          tbn * nn.truncated_to_vec3()
+        }
+        None => bn,
+      };
+
+      let diff = n.normalize().dot(&light_dir.normalize()).max(0.0);
+      let shadow = occlusion(shadow_visibility(uniforms, varyings));
+      color = color * diff * shadow;
       color.w = 1.0;
     };
 
@@ -1,6 +1,7 @@
 use crate::{
-  math::{Vec3, Vec4},
-  obj_loader::shader::{uniform, varying, Extract, GLTypes, Shader},
+  math::{Mat4, Vec3, Vec4},
+  model::Vertex,
+  obj_loader::shader::{uniform, varying, Extract, GLTypes, Shader, Uniform, Varying, Varyings},
 };
 
 pub fn make_shadow_shader() -> Shader {
@@ -24,3 +25,31 @@ pub fn make_shadow_shader() -> Shader {
 
   shader
 }
+
+/// Store where the vertex lands in the shadow map, does nothing when the shadow pass is off.
+/// Call it from a vertex shader with the untransformed vertex.
+pub fn set_shadow_coord(vertex: &Vertex, uniforms: &Uniform, varyings: &mut Varyings) {
+  let light_mvp = uniform!(uniforms, Mat4, "light_mvp");
+  let viewport_matrix = uniform!(uniforms, Mat4, "viewport_matrix");
+
+  if let (Some(light_mvp), Some(viewport_matrix)) = (light_mvp, viewport_matrix) {
+    // the viewport is affine, it can be applied before the perspective division
+    let coord = viewport_matrix * light_mvp * vertex.position;
+    varyings.set("shadow_coord", GLTypes::Vec4(coord));
+  }
+}
+
+/// 1.0 when the fragment is lit, 0.0 when it's in shadow, filtered by the type of the shadow map.
+/// Always 1.0 without a shadow pass.
+pub fn shadow_visibility(uniforms: &Uniform, varyings: &Varying) -> f32 {
+  let coord = varying!(varyings, Vec4, "shadow_coord");
+  let bias = uniform!(uniforms, f32, "shadow_bias").unwrap_or(0.0);
+
+  match (uniforms.shadow_map(), coord) {
+    (Some(map), Some(coord)) if coord.w > 0.0 => {
+      let coord = coord / coord.w;
+      map.visibility(coord.x, coord.y, coord.z, bias)
+    }
+    _ => 1.0,
+  }
+}
@@ -0,0 +1,268 @@
+use crate::{data_array::DepthBuffer, math::Vec2};
+
+/// How the depth rendered from the light is filtered when shading.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadowMapType {
+  /// a single depth comparison, hard and aliased edges
+  #[default]
+  BasicShadowMap,
+  /// averages a 3x3 kernel of comparisons
+  PCFShadowMap,
+  /// averages a 3x3 kernel of bilinearly filtered comparisons
+  PCFSoftShadowMap,
+  /// variance shadow map, a Chebyshev bound over blurred depth moments
+  VSMShadowMap,
+}
+
+/// Shadow settings of the renderer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowMap {
+  /// render the depth from the light before every frame
+  pub enabled: bool,
+  pub map_type: ShadowMapType,
+  /// subtracted from the depth of the shaded point, fights shadow acne
+  pub bias: f32,
+}
+
+impl Default for ShadowMap {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      map_type: Default::default(),
+      bias: 0.005,
+    }
+  }
+}
+
+/// the smallest variance VSM works with, avoids dividing by zero on flat receivers
+const VSM_MIN_VARIANCE: f32 = 0.00002;
+/// cuts the tail of the Chebyshev bound to reduce light bleeding
+const VSM_BLEEDING_REDUCTION: f32 = 0.2;
+/// radius of the box blur applied to the moments
+const VSM_BLUR_RADIUS: i32 = 2;
+
+/// The depth rendered from a light, sampled in screen space of the light,
+/// smaller depth being closer to the light.
+pub struct DepthMap {
+  depth: DepthBuffer,
+  map_type: ShadowMapType,
+  /// blurred (depth, depth^2), only filled for `VSMShadowMap`
+  moments: Vec<Vec2>,
+}
+
+impl DepthMap {
+  pub fn new(depth: DepthBuffer, map_type: ShadowMapType) -> Self {
+    let moments = if map_type == ShadowMapType::VSMShadowMap {
+      blur_moments(&depth)
+    } else {
+      vec![]
+    };
+
+    Self {
+      depth,
+      map_type,
+      moments,
+    }
+  }
+
+  pub fn width(&self) -> u32 {
+    self.depth.width()
+  }
+
+  pub fn height(&self) -> u32 {
+    self.depth.height()
+  }
+
+  pub fn map_type(&self) -> ShadowMapType {
+    self.map_type
+  }
+
+  pub fn depth(&self) -> &DepthBuffer {
+    &self.depth
+  }
+
+  /// 1.0 when the point at pixel (`x`, `y`) with `depth` is lit, 0.0 when it's fully shadowed.
+  /// `bias` is subtracted from `depth` before the comparison.
+  pub fn visibility(&self, x: f32, y: f32, depth: f32, bias: f32) -> f32 {
+    let w = self.width() as f32;
+    let h = self.height() as f32;
+
+    // nothing outside of the light frustum casts shadows
+    if x < 0.0 || y < 0.0 || x >= w || y >= h {
+      return 1.0;
+    }
+
+    let depth = depth - bias;
+
+    match self.map_type {
+      ShadowMapType::BasicShadowMap => self.compare(x as i32, y as i32, depth),
+      ShadowMapType::PCFShadowMap => {
+        let mut sum = 0.0;
+        for dy in -1..=1 {
+          for dx in -1..=1 {
+            sum += self.compare(x as i32 + dx, y as i32 + dy, depth);
+          }
+        }
+        sum / 9.0
+      }
+      ShadowMapType::PCFSoftShadowMap => {
+        let mut sum = 0.0;
+        for dy in -1..=1 {
+          for dx in -1..=1 {
+            sum += self.compare_bilinear(x + dx as f32, y + dy as f32, depth);
+          }
+        }
+        sum / 9.0
+      }
+      ShadowMapType::VSMShadowMap => self.chebyshev(x as i32, y as i32, depth),
+    }
+  }
+
+  fn texel(&self, x: i32, y: i32) -> usize {
+    let x = x.clamp(0, self.width() as i32 - 1) as u32;
+    let y = y.clamp(0, self.height() as i32 - 1) as u32;
+    (x + y * self.width()) as usize
+  }
+
+  fn compare(&self, x: i32, y: i32, depth: f32) -> f32 {
+    if depth <= self.depth.data()[self.texel(x, y)] {
+      1.0
+    } else {
+      0.0
+    }
+  }
+
+  fn compare_bilinear(&self, x: f32, y: f32, depth: f32) -> f32 {
+    // texel centers sit at .5
+    let x = x - 0.5;
+    let y = y - 0.5;
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = x - x0;
+    let ty = y - y0;
+    let (x0, y0) = (x0 as i32, y0 as i32);
+
+    let top = self.compare(x0, y0, depth) * (1.0 - tx) + self.compare(x0 + 1, y0, depth) * tx;
+    let bottom =
+      self.compare(x0, y0 + 1, depth) * (1.0 - tx) + self.compare(x0 + 1, y0 + 1, depth) * tx;
+
+    top * (1.0 - ty) + bottom * ty
+  }
+
+  fn chebyshev(&self, x: i32, y: i32, depth: f32) -> f32 {
+    let Vec2 { x: mean, y: square } = self.moments[self.texel(x, y)];
+
+    if depth <= mean {
+      return 1.0;
+    }
+
+    let variance = (square - mean * mean).max(VSM_MIN_VARIANCE);
+    let d = depth - mean;
+    let p_max = variance / (variance + d * d);
+
+    ((p_max - VSM_BLEEDING_REDUCTION) / (1.0 - VSM_BLEEDING_REDUCTION)).clamp(0.0, 1.0)
+  }
+}
+
+/// separable box blur over (depth, depth^2)
+fn blur_moments(depth: &DepthBuffer) -> Vec<Vec2> {
+  let w = depth.width() as i32;
+  let h = depth.height() as i32;
+  if w == 0 || h == 0 {
+    return vec![];
+  }
+  let index = |x: i32, y: i32| (x.clamp(0, w - 1) + y.clamp(0, h - 1) * w) as usize;
+  let count = (VSM_BLUR_RADIUS * 2 + 1) as f32;
+
+  // texels nothing was drawn to are still cleared to f32::MAX, squaring that overflows
+  let moments: Vec<Vec2> = depth
+    .data()
+    .iter()
+    .map(|d| {
+      let d = d.min(1.0);
+      Vec2::new(d, d * d)
+    })
+    .collect();
+
+  let mut horizontal = vec![Vec2::zero(); moments.len()];
+  for y in 0..h {
+    for x in 0..w {
+      let mut sum = Vec2::zero();
+      for dx in -VSM_BLUR_RADIUS..=VSM_BLUR_RADIUS {
+        sum += moments[index(x + dx, y)];
+      }
+      horizontal[index(x, y)] = sum / count;
+    }
+  }
+
+  let mut result = vec![Vec2::zero(); moments.len()];
+  for y in 0..h {
+    for x in 0..w {
+      let mut sum = Vec2::zero();
+      for dy in -VSM_BLUR_RADIUS..=VSM_BLUR_RADIUS {
+        sum += horizontal[index(x, y + dy)];
+      }
+      result[index(x, y)] = sum / count;
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cleared_depth(w: u32, h: u32) -> DepthBuffer {
+    let mut depth = DepthBuffer::new(w, h);
+    depth.clear(f32::MAX);
+    depth
+  }
+
+  #[test]
+  fn cleared_texels_give_finite_moments() {
+    let map = DepthMap::new(cleared_depth(8, 8), ShadowMapType::VSMShadowMap);
+
+    assert!(map.moments.iter().all(|m| m.x.is_finite() && m.y.is_finite()));
+    assert_eq!(map.visibility(4.0, 4.0, 0.5, 0.0), 1.0);
+  }
+
+  #[test]
+  fn vsm_shadows_behind_an_occluder() {
+    let mut depth = cleared_depth(8, 8);
+    for y in 0..8 {
+      for x in 0..8 {
+        depth.set(x, y, 0.3);
+      }
+    }
+    let map = DepthMap::new(depth, ShadowMapType::VSMShadowMap);
+
+    assert_eq!(map.visibility(4.0, 4.0, 0.2, 0.0), 1.0);
+    assert_eq!(map.visibility(4.0, 4.0, 0.8, 0.0), 0.0);
+  }
+
+  #[test]
+  fn pcf_softens_the_shadow_edge() {
+    let mut depth = cleared_depth(8, 8);
+    for y in 0..8 {
+      for x in 0..4 {
+        depth.set(x, y, 0.3);
+      }
+    }
+    let map = DepthMap::new(depth, ShadowMapType::PCFShadowMap);
+
+    assert_eq!(map.visibility(1.5, 4.5, 0.8, 0.0), 0.0);
+    assert_eq!(map.visibility(6.5, 4.5, 0.8, 0.0), 1.0);
+    let edge = map.visibility(3.5, 4.5, 0.8, 0.0);
+    assert!(edge > 0.0 && edge < 1.0);
+  }
+
+  #[test]
+  fn outside_of_the_map_is_lit() {
+    let map = DepthMap::new(DepthBuffer::new(4, 4), ShadowMapType::BasicShadowMap);
+
+    assert_eq!(map.visibility(-1.0, 2.0, 0.5, 0.0), 1.0);
+    assert_eq!(map.visibility(2.0, 4.0, 0.5, 0.0), 1.0);
+    assert_eq!(map.visibility(2.0, 2.0, 0.5, 0.0), 0.0);
+  }
+}
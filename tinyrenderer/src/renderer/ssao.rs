@@ -0,0 +1,254 @@
+use super::camera::Frustum;
+use crate::{
+  data_array::{ColorBuffer, DepthBuffer},
+  math::Vec4,
+};
+
+/// What the SSAO pass does to the color buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SsaoMode {
+  /// darken the color by the occlusion
+  #[default]
+  Modulate,
+  /// replace the color by the ambient occlusion term, white being unoccluded
+  RawAO,
+}
+
+/// Horizon based ambient occlusion estimated from the depth buffer after the main pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ssao {
+  pub enabled: bool,
+  pub mode: SsaoMode,
+  /// how many directions are marched around every pixel
+  pub directions: u32,
+  /// how far the directions are marched, in pixels
+  pub radius: f32,
+  /// samples further than this in view space don't occlude, avoids halos around silhouettes
+  pub max_distance: f32,
+  /// 1.0 keeps the estimated term, larger values darken it
+  pub intensity: f32,
+  /// radius of the box blur applied to the term, 0 disables it
+  pub blur_radius: i32,
+}
+
+impl Default for Ssao {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      mode: Default::default(),
+      directions: 8,
+      radius: 16.0,
+      max_distance: 1.0,
+      intensity: 1.0,
+      blur_radius: 2,
+    }
+  }
+}
+
+impl Ssao {
+  /// Run the pass over `color` with the depth left by the main pass.
+  pub fn apply(&self, color: &mut ColorBuffer, depth: &DepthBuffer, frustum: &Frustum) {
+    let ao = blur(
+      &ambient_occlusion(depth, frustum, self),
+      depth,
+      self.blur_radius,
+    );
+    let w = depth.width();
+
+    for y in 0..depth.height() {
+      for x in 0..w {
+        let i = (x + y * w) as usize;
+        if is_background(depth.data()[i]) {
+          continue;
+        }
+
+        let ao = ao[i];
+        let result = match self.mode {
+          SsaoMode::Modulate => color.get(x, y) * ao,
+          SsaoMode::RawAO => Vec4::new(ao, ao, ao, 1.0),
+        };
+        color.set(x, y, &result);
+      }
+    }
+  }
+}
+
+/// nothing was drawn where the depth is still cleared
+fn is_background(depth: f32) -> bool {
+  depth >= 1.0
+}
+
+/// the distance to the camera of a depth in [0, 1] written through the viewport
+fn linear_depth(depth: f32, near: f32, far: f32) -> f32 {
+  let z = depth * 2.0 - 1.0;
+  2.0 * near * far / (far + near - z * (far - near))
+}
+
+/// Per pixel ambient term, 1.0 for open surfaces and 0.0 for fully occluded ones.
+/// Marches every direction in screen space and keeps the highest horizon it meets,
+/// the more sky is hidden by the horizons the more occluded the pixel is.
+pub fn ambient_occlusion(depth: &DepthBuffer, frustum: &Frustum, settings: &Ssao) -> Vec<f32> {
+  let w = depth.width() as i32;
+  let h = depth.height() as i32;
+  let data = depth.data();
+  let (near, far) = (frustum.near(), frustum.far());
+  // the width of the view at distance 1.0, over the width in pixels
+  let pixel_size = 2.0 * frustum.fov().tan() / w.max(1) as f32;
+
+  let directions = settings.directions.max(1);
+  let steps = settings.radius.max(1.0) as i32;
+  let half_pi = std::f32::consts::FRAC_PI_2;
+  let mut result = vec![1.0; data.len()];
+
+  for y in 0..h {
+    for x in 0..w {
+      let i = (x + y * w) as usize;
+      if is_background(data[i]) {
+        continue;
+      }
+
+      let center = linear_depth(data[i], near, far);
+      let mut hidden = 0.0;
+
+      for d in 0..directions {
+        let angle = d as f32 / directions as f32 * std::f32::consts::TAU;
+        let (dy, dx) = angle.sin_cos();
+        let mut horizon = 0.0_f32;
+
+        for step in 1..=steps {
+          let sx = (x as f32 + dx * step as f32).round() as i32;
+          let sy = (y as f32 + dy * step as f32).round() as i32;
+          if sx < 0 || sy < 0 || sx >= w || sy >= h {
+            break;
+          }
+
+          let sample = data[(sx + sy * w) as usize];
+          if is_background(sample) {
+            continue;
+          }
+
+          // everything in view space units at the depth of the center
+          let rise = center - linear_depth(sample, near, far);
+          let run = step as f32 * pixel_size * center;
+          if rise <= 0.0 || rise.hypot(run) > settings.max_distance {
+            continue;
+          }
+
+          horizon = horizon.max(rise.atan2(run));
+        }
+
+        hidden += horizon / half_pi;
+      }
+
+      let open = 1.0 - hidden / directions as f32;
+      result[i] = open.powf(settings.intensity).clamp(0.0, 1.0);
+    }
+  }
+
+  result
+}
+
+/// separable box blur of the ambient term, background pixels are left out
+fn blur(ao: &[f32], depth: &DepthBuffer, radius: i32) -> Vec<f32> {
+  let w = depth.width() as i32;
+  let h = depth.height() as i32;
+  if radius <= 0 || w == 0 || h == 0 {
+    return ao.to_vec();
+  }
+  let data = depth.data();
+
+  let pass = |source: &[f32], (ox, oy): (i32, i32)| {
+    let mut target = source.to_vec();
+    for y in 0..h {
+      for x in 0..w {
+        let i = (x + y * w) as usize;
+        if is_background(data[i]) {
+          continue;
+        }
+
+        let mut sum = 0.0;
+        let mut count = 0.0;
+        for r in -radius..=radius {
+          let (sx, sy) = (x + ox * r, y + oy * r);
+          if sx < 0 || sy < 0 || sx >= w || sy >= h {
+            continue;
+          }
+          let j = (sx + sy * w) as usize;
+          if !is_background(data[j]) {
+            sum += source[j];
+            count += 1.0;
+          }
+        }
+        target[i] = sum / count;
+      }
+    }
+    target
+  };
+
+  pass(&pass(ao, (1, 0)), (0, 1))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frustum() -> Frustum {
+    Frustum::new(1.0, 100.0, 1.0, 14f32.to_radians())
+  }
+
+  /// a flat surface at `depth` with a pit of `pit` in its middle
+  fn depth(depth: f32, pit: f32) -> DepthBuffer {
+    let mut buffer = DepthBuffer::new(16, 16);
+    buffer.clear(depth);
+    for y in 6..10 {
+      for x in 6..10 {
+        buffer.set(x, y, pit);
+      }
+    }
+    buffer
+  }
+
+  #[test]
+  fn linear_depth_spans_the_frustum() {
+    assert!((linear_depth(0.0, 1.0, 100.0) - 1.0).abs() < 1e-4);
+    assert!((linear_depth(1.0, 1.0, 100.0) - 100.0).abs() < 1e-2);
+  }
+
+  #[test]
+  fn flat_surfaces_are_open() {
+    let ao = ambient_occlusion(&depth(0.9, 0.9), &frustum(), &Ssao::default());
+
+    assert!(ao.iter().all(|a| (a - 1.0).abs() < 1e-5));
+  }
+
+  #[test]
+  fn pits_are_occluded() {
+    let settings = Ssao {
+      radius: 4.0,
+      max_distance: 10.0,
+      ..Default::default()
+    };
+    let ao = ambient_occlusion(&depth(0.9, 0.91), &frustum(), &settings);
+
+    assert!(ao[8 + 8 * 16] < 0.9);
+    assert_eq!(ao[0], 1.0);
+  }
+
+  #[test]
+  fn raw_ao_leaves_the_background() {
+    let settings = Ssao {
+      enabled: true,
+      mode: SsaoMode::RawAO,
+      ..Default::default()
+    };
+    let mut depth = depth(0.9, 0.9);
+    depth.set(0, 0, f32::MAX);
+    let mut color = ColorBuffer::new(16, 16);
+    color.clear(&Vec4::new(1.0, 0.0, 0.0, 1.0));
+
+    settings.apply(&mut color, &depth, &frustum());
+
+    assert_eq!(color.get(0, 0), Vec4::new(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(color.get(8, 8), Vec4::new(1.0, 1.0, 1.0, 1.0));
+  }
+}